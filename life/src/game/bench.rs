@@ -0,0 +1,249 @@
+//! Fixed workloads for measuring how fast the simulation steps, with no
+//! window or renderer in the way. The numbers can be compared from one change
+//! to the next, as long as they're from the same machine.
+
+use std::{
+    fmt,
+    hash::Hasher,
+    time::{Duration, Instant},
+};
+
+use vec2::Vector2;
+
+#[cfg(feature = "native_threads")]
+use super::{StepThreadNotification, ThreadData};
+use super::{patterns, simulation::Simulation};
+
+/// The acorn, a methuselah that grows from 7 cells to a few hundred over
+/// about 5000 generations before settling down
+const ACORN: &str = "x = 7, y = 3\nbo5b$3bo3b$2o2b3o!";
+
+/// The width and height of the soup
+const SOUP_SIZE: i32 = 256;
+/// How many generations the soup is stepped before it's started over. By
+/// then it has thinned out a lot, but is still crowded enough to be stepped
+/// by chunks.
+const SOUP_GENERATIONS: u64 = 100;
+/// How many cells are on the board of cells that collide
+const COLLIDING_CELLS: i32 = 4096;
+
+/// The hasher cells are hashed with in this build
+pub const CELL_HASHER: &str = if cfg!(feature = "sip_hash") {
+    "SipHash"
+} else {
+    "FxHash"
+};
+
+/// What a benchmark steps
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Workload {
+    /// An acorn under Conway's rule, which stays sparse, so it's stepped cell
+    /// by cell
+    Acorn,
+    /// A square of random cells under Conway's rule, started over every
+    /// `SOUP_GENERATIONS`, which is crowded enough to be stepped by chunks
+    Soup,
+    /// Lone cells picked so that FxHash puts them all in the same bucket, the
+    /// way a pattern from someone trying to stall the simulation would be,
+    /// under a rule where every cell survives. This is what the `sip_hash`
+    /// feature is for.
+    Colliding,
+}
+
+impl Workload {
+    pub const ALL: [Self; 3] = [Self::Acorn, Self::Soup, Self::Colliding];
+
+    /// The board the workload starts with
+    fn start(self) -> Simulation {
+        let mut sim = Simulation::new();
+        match self {
+            Self::Acorn => {
+                let pattern = patterns::parse_rle(ACORN).expect("The acorn's RLE is valid");
+                for cell in pattern.cells {
+                    sim.toggle(cell);
+                }
+            }
+            Self::Soup => {
+                for cell in soup(SOUP_SIZE) {
+                    sim.toggle(cell);
+                }
+            }
+            Self::Colliding => {
+                sim.set_ruleset("B/S012345678".parse().expect("The rule is valid"));
+                for cell in colliding(COLLIDING_CELLS) {
+                    sim.toggle(cell);
+                }
+            }
+        }
+        sim
+    }
+
+    /// How many generations the board is stepped before it's started over, if
+    /// it ever is
+    fn generations(self) -> Option<u64> {
+        match self {
+            Self::Acorn | Self::Colliding => None,
+            Self::Soup => Some(SOUP_GENERATIONS),
+        }
+    }
+}
+
+/// Where a benchmark's steps are computed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stepper {
+    /// On the thread running the benchmark, like `Simulation::step`
+    ThisThread,
+    /// On a worker thread, the way the game steps with `native_threads`,
+    /// counting the time it takes to hand the board over and get it back
+    #[cfg(feature = "native_threads")]
+    Worker,
+}
+
+impl Stepper {
+    pub const ALL: &'static [Self] = &[
+        Self::ThisThread,
+        #[cfg(feature = "native_threads")]
+        Self::Worker,
+    ];
+}
+
+impl fmt::Display for Stepper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ThisThread => "main thread",
+            #[cfg(feature = "native_threads")]
+            Self::Worker => "worker thread",
+        })
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Acorn => "acorn",
+            Self::Soup => "soup",
+            Self::Colliding => "colliding",
+        })
+    }
+}
+
+/// How a benchmark run went
+#[derive(Clone, Copy, Debug)]
+pub struct BenchResult {
+    /// The number of generations computed
+    pub steps: u64,
+    /// How long computing them took
+    pub elapsed: Duration,
+    /// The number of living cells at the end
+    pub population: usize,
+}
+
+impl BenchResult {
+    pub fn steps_per_second(&self) -> f64 {
+        self.steps as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Step a workload for `duration`, and report how many generations that was.
+/// Setting up the board again isn't counted.
+pub fn run(workload: Workload, stepper: Stepper, duration: Duration) -> BenchResult {
+    #[cfg(feature = "native_threads")]
+    let worker = (stepper == Stepper::Worker).then(ThreadData::spawn);
+    let mut sim = workload.start();
+    let mut elapsed = Duration::ZERO;
+    let mut steps = 0;
+    let mut generation = 0;
+    while elapsed < duration {
+        if workload.generations() == Some(generation) {
+            sim = workload.start();
+            generation = 0;
+        }
+        let start = Instant::now();
+        match stepper {
+            Stepper::ThisThread => sim.step(),
+            #[cfg(feature = "native_threads")]
+            Stepper::Worker => step_on(worker.as_ref().unwrap(), &mut sim),
+        }
+        elapsed += start.elapsed();
+        steps += 1;
+        generation += 1;
+    }
+    #[cfg(feature = "native_threads")]
+    if let Some(worker) = worker {
+        worker.shared.notify(StepThreadNotification::Exit);
+        let _ = worker.local.join_handle.join();
+    }
+    BenchResult {
+        steps,
+        elapsed,
+        population: sim.population(),
+    }
+}
+
+/// Step the board on a worker thread the same way `GameState` does
+#[cfg(feature = "native_threads")]
+fn step_on(worker: &ThreadData, sim: &mut Simulation) {
+    worker.shared.notify(StepThreadNotification::Compute(
+        sim.living_cells.clone(),
+        sim.walls.clone(),
+        sim.take_spare(),
+        Box::new(sim.ruleset),
+        sim.boundary,
+    ));
+    let next = worker.local.rx.recv().expect("The worker thread is running");
+    let prev = sim.replace(next);
+    sim.recycle(prev);
+}
+
+/// A `size` by `size` square where about half the cells are alive, the same
+/// every time
+fn soup(size: i32) -> Vec<Vector2<i32>> {
+    // A xorshift generator, so the soup doesn't need the `random` feature
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut cells = Vec::new();
+    for y in 0..size {
+        for x in 0..size {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            if state & 1 == 1 {
+                cells.push(Vector2::new(x, y));
+            }
+        }
+    }
+    cells
+}
+
+/// `count` cells whose FxHashes are all the same in the low 32 bits, which
+/// are the ones hash tables use to pick a bucket
+fn colliding(count: i32) -> Vec<Vector2<i32>> {
+    // FxHash mixes each field in by rotating the hash so far, xoring the
+    // field in and multiplying. The low bits of a product only depend on the
+    // low bits of what's multiplied, so picking y to cancel out the low bits
+    // left by x makes them the same for every cell.
+    (0..count)
+        .map(|x| {
+            let mut hasher = rustc_hash::FxHasher::default();
+            hasher.write_i32(x);
+            let y = hasher.finish().rotate_left(5) as u32;
+            Vector2::new(x, y as i32)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::BuildHasher;
+
+    use super::*;
+
+    #[test]
+    fn colliding_cells_collide() {
+        let fx = std::hash::BuildHasherDefault::<rustc_hash::FxHasher>::default();
+        let cells = colliding(100);
+        let first = fx.hash_one(cells[0]) as u32;
+        for cell in cells {
+            assert_eq!(fx.hash_one(cell) as u32, first, "{cell:?}");
+        }
+    }
+}
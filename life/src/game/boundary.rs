@@ -0,0 +1,78 @@
+//! What happens at the edges of the board.
+
+use std::fmt;
+
+use vec2::Vector2;
+
+use super::LivingList;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BoundaryMode {
+    /// The board goes on forever in every direction
+    #[default]
+    Infinite,
+    /// The board is `width` by `height` cells, starting at (0, 0), and wraps
+    /// around so that cells past one edge are on the opposite one. Neither
+    /// can be less than [`BoundaryMode::min_torus_size`] for the rule's range.
+    Toroidal { width: i32, height: i32 },
+}
+
+impl BoundaryMode {
+    /// The smallest width or height a toroidal board can have under a rule
+    /// whose neighborhood reaches `range` cells out. On anything smaller, the
+    /// neighborhood would wrap all the way around, so that different neighbors
+    /// would be the same cell, or the cell itself, and be counted twice.
+    pub fn min_torus_size(range: u8) -> i32 {
+        2 * range as i32 + 1
+    }
+
+    /// The same mode with the board grown to at least the smallest size for a
+    /// neighborhood reaching `range` cells out
+    pub fn clamped(self, range: u8) -> Self {
+        let min = Self::min_torus_size(range);
+        match self {
+            Self::Infinite => self,
+            Self::Toroidal { width, height } => Self::Toroidal {
+                width: width.max(min),
+                height: height.max(min),
+            },
+        }
+    }
+
+    /// Move a cell onto the board. Cells on an infinite board are left alone.
+    #[inline(always)]
+    pub fn wrap(&self, cell: Vector2<i32>) -> Vector2<i32> {
+        match *self {
+            Self::Infinite => cell,
+            Self::Toroidal { width, height } => {
+                Vector2::new(cell.x.rem_euclid(width), cell.y.rem_euclid(height))
+            }
+        }
+    }
+
+    /// Whether a cell is on the board without wrapping
+    pub fn contains(&self, cell: Vector2<i32>) -> bool {
+        match *self {
+            Self::Infinite => true,
+            Self::Toroidal { width, height } => {
+                (0..width).contains(&cell.x) && (0..height).contains(&cell.y)
+            }
+        }
+    }
+
+    /// Move every cell onto the board, for cells that came from a different
+    /// mode or a board of a different size. Cells that end up in the same
+    /// place are merged.
+    pub fn wrap_all(&self, cells: &LivingList) -> LivingList {
+        cells.iter().map(|c| self.wrap(*c)).collect()
+    }
+}
+
+impl fmt::Display for BoundaryMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Infinite => f.write_str("Infinite"),
+            Self::Toroidal { width, height } => write!(f, "Toroidal {width}\u{d7}{height}"),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Computing steps on 64×64 tiles of bits instead of one cell at a time.
+//!
+//! Each tile is 64 rows of `u64`, so the neighbors of a whole row can be
+//! counted at once with bitwise operations. This is much faster than counting
+//! through a hash map when the board is crowded, but wastes time on tiles
+//! holding only a few cells, so it's only used for boards that are dense
+//! enough.
+//!
+//! This only speeds up stepping; cells aren't stored in chunks. The living
+//! cells are kept in a set between steps, since rendering, payloads, ages,
+//! period detection, saves and pattern export all read and edit that set
+//! directly. The chunks are built from it for each step and thrown away
+//! afterwards, which costs far less than the step saves. Keeping the board in
+//! chunks between steps would mean moving all of those over to them first.
+
+use vec2::Vector2;
+
+use super::{rules::Ruleset, CellMap, LivingList};
+
+const CHUNK_SHIFT: i32 = 6;
+const CHUNK_SIZE: usize = 1 << CHUNK_SHIFT;
+const CHUNK_MASK: i32 = CHUNK_SIZE as i32 - 1;
+/// The fewest living cells per chunk, on average, for stepping by chunks to be
+/// worth it
+const MIN_CELLS_PER_CHUNK: usize = 32;
+/// How many steps a board that was too sparse is stepped cell by cell before
+/// it's checked again
+pub const SPARSE_RECHECK_STEPS: u32 = 16;
+
+/// The cells of a chunk, where bit `x` of row `y` is the cell at (`x`, `y`)
+/// from the chunk's corner
+type Chunk = [u64; CHUNK_SIZE];
+
+/// Living cells grouped into chunks, keyed by the cell coordinates of their
+/// corner divided by the chunk size
+pub struct ChunkedBoard {
+    chunks: CellMap<Chunk>,
+}
+
+impl ChunkedBoard {
+    /// Group the cells into chunks if there are enough cells in each chunk
+    /// for stepping by chunks to be faster than stepping cell by cell, or
+    /// `None` if there aren't. Sparse boards are given up on as soon as they
+    /// cover too many chunks, so finding out doesn't cost much.
+    pub fn from_dense_cells(cells: &LivingList) -> Option<Self> {
+        let max_chunks = cells.len() / MIN_CELLS_PER_CHUNK;
+        let mut chunks = CellMap::<Chunk>::default();
+        for cell in cells {
+            let key = Vector2::new(cell.x >> CHUNK_SHIFT, cell.y >> CHUNK_SHIFT);
+            let chunk = chunks.entry(key).or_insert([0; CHUNK_SIZE]);
+            chunk[(cell.y & CHUNK_MASK) as usize] |= 1 << (cell.x & CHUNK_MASK);
+            if chunks.len() > max_chunks {
+                return None;
+            }
+        }
+        Some(Self { chunks })
+    }
+
+    /// Compute the next generation. Only the populated chunks and the ones
+    /// around them are looked at, since cells can't be born anywhere else.
+    pub fn step(&self, ruleset: &Ruleset) -> Self {
+        let mut candidates = LivingList::default();
+        for key in self.chunks.keys() {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    candidates.insert(*key + Vector2::new(dx, dy));
+                }
+            }
+        }
+
+        let mut chunks = CellMap::<Chunk>::default();
+        for key in candidates {
+            if let Some(next) = self.step_chunk(key, ruleset) {
+                chunks.insert(key, next);
+            }
+        }
+        Self { chunks }
+    }
+
+    /// Add the coordinates of every living cell to a set
+    pub fn write_cells(&self, cells: &mut LivingList) {
+        for (key, chunk) in self.chunks.iter() {
+            let corner = Vector2::new(key.x << CHUNK_SHIFT, key.y << CHUNK_SHIFT);
+            for (y, row) in chunk.iter().enumerate() {
+                let mut bits = *row;
+                while bits != 0 {
+                    let x = bits.trailing_zeros() as i32;
+                    cells.insert(corner + Vector2::new(x, y as i32));
+                    bits &= bits - 1;
+                }
+            }
+        }
+    }
+
+    /// Compute the next generation of one chunk, or `None` if it's empty.
+    fn step_chunk(&self, key: Vector2<i32>, ruleset: &Ruleset) -> Option<Chunk> {
+        // The chunk and its neighbors, indexed by [y][x]
+        let mut around = [[None; 3]; 3];
+        let mut any = false;
+        for (dy, row) in around.iter_mut().enumerate() {
+            for (dx, chunk) in row.iter_mut().enumerate() {
+                *chunk = self
+                    .chunks
+                    .get(&(key + Vector2::new(dx as i32 - 1, dy as i32 - 1)));
+                any |= chunk.is_some();
+            }
+        }
+        if !any {
+            return None;
+        }
+
+        let mut next = [0; CHUNK_SIZE];
+        for (y, next_row) in next.iter_mut().enumerate() {
+            let y = y as i32;
+            let (up_w, up, up_e) = neighbor_rows(&around, y - 1);
+            let (mid_w, mid, mid_e) = neighbor_rows(&around, y);
+            let (down_w, down, down_e) = neighbor_rows(&around, y + 1);
+            let neighbors = [up_w, up, up_e, mid_w, mid_e, down_w, down, down_e];
+            *next_row = apply_rules(ruleset, mid, &neighbors);
+        }
+        next.iter().any(|r| *r != 0).then_some(next)
+    }
+}
+
+/// The cells of a row, and the same row shifted so that each bit is the cell
+/// to its west or east, including the bits that come from the chunks beside
+/// it. Rows outside of the chunk come from the ones above and below it.
+///
+/// Returns `(west, row, east)`.
+fn neighbor_rows(around: &[[Option<&Chunk>; 3]; 3], y: i32) -> (u64, u64, u64) {
+    let chunk_y = if y < 0 {
+        0
+    } else if y >= CHUNK_SIZE as i32 {
+        2
+    } else {
+        1
+    };
+    let y = (y & CHUNK_MASK) as usize;
+    let row = |x: usize| around[chunk_y][x].map_or(0, |c| c[y]);
+    let (left, mid, right) = (row(0), row(1), row(2));
+    let west = (mid << 1) | (left >> (CHUNK_SIZE - 1));
+    let east = (mid >> 1) | (right << (CHUNK_SIZE - 1));
+    (west, mid, east)
+}
+
+/// Count the neighbors of every cell in a row at once and decide which ones
+/// are alive in the next generation.
+fn apply_rules(ruleset: &Ruleset, current: u64, neighbors: &[u64; 8]) -> u64 {
+    // The count of each cell as a 4 bit number, one bit per word
+    let (mut s0, mut s1, mut s2, mut s3) = (0u64, 0u64, 0u64, 0u64);
+    for n in neighbors {
+        let c0 = s0 & n;
+        s0 ^= n;
+        let c1 = s1 & c0;
+        s1 ^= c0;
+        let c2 = s2 & c1;
+        s2 ^= c1;
+        s3 |= c2;
+    }
+
+    let mut next = 0;
+    for count in 0..=8 {
+        // Dead cells with no living neighbors are never looked at when
+        // stepping cell by cell, so they can't be born here either.
+        let birth = ruleset.birth[count] && count != 0;
+        let survive = ruleset.survive[count];
+        if !birth && !survive {
+            continue;
+        }
+        let bit = |word: u64, b: usize| if count & (1 << b) != 0 { word } else { !word };
+        let matches = bit(s0, 0) & bit(s1, 1) & bit(s2, 2) & bit(s3, 3);
+        let alive = (if birth { !current } else { 0 }) | (if survive { current } else { 0 });
+        next |= matches & alive;
+    }
+    next
+}
@@ -0,0 +1,52 @@
+//! Where copied patterns go. With the `clipboard` feature they go through the
+//! system clipboard, so they can be pasted into other programs and patterns
+//! copied from elsewhere can be stamped. Without it, or on the web, the game
+//! keeps what was copied itself.
+
+/// The clipboard patterns are copied to and pasted from
+#[derive(Default)]
+pub struct Clipboard {
+    /// Opened the first time it's used, and kept open so that what was
+    /// copied stays available to other programs
+    #[cfg(all(feature = "clipboard", not(target_arch = "wasm32")))]
+    system: Option<arboard::Clipboard>,
+    #[cfg(not(all(feature = "clipboard", not(target_arch = "wasm32"))))]
+    text: Option<String>,
+}
+
+#[cfg(all(feature = "clipboard", not(target_arch = "wasm32")))]
+impl Clipboard {
+    fn system(&mut self) -> anyhow::Result<&mut arboard::Clipboard> {
+        if self.system.is_none() {
+            self.system = Some(arboard::Clipboard::new()?);
+        }
+        Ok(self.system.as_mut().unwrap())
+    }
+
+    pub fn set_text(&mut self, text: String) -> anyhow::Result<()> {
+        self.system()?.set_text(text)?;
+        Ok(())
+    }
+
+    /// The text on the clipboard, or `None` if there isn't any
+    pub fn get_text(&mut self) -> anyhow::Result<Option<String>> {
+        match self.system()?.get_text() {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(not(all(feature = "clipboard", not(target_arch = "wasm32"))))]
+impl Clipboard {
+    pub fn set_text(&mut self, text: String) -> anyhow::Result<()> {
+        self.text = Some(text);
+        Ok(())
+    }
+
+    /// The text last copied, or `None` if nothing has been
+    pub fn get_text(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(self.text.clone())
+    }
+}
@@ -0,0 +1,310 @@
+//! Which keys do what. Keys are named by what they type, like "c", or for
+//! keys that don't type anything, by their name in winit, like "ArrowUp", so
+//! the same names work on every keyboard layout.
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use winit::keyboard::Key;
+
+#[cfg(feature = "saving")]
+use super::saving::DataStorage;
+
+/// Something that can be done with a key
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    Play,
+    /// Step once, pausing first, or back with Shift
+    Step,
+    Clear,
+    SpeedUp,
+    SlowDown,
+    /// Fast forward while held
+    FastForward,
+    StepUntilChange,
+    ToggleFitZoom,
+    Center,
+    FitView,
+    ResetView,
+    Rotate,
+    FlipX,
+    FlipY,
+    ZoomIn,
+    ZoomOut,
+    ToggleTool,
+    DrawTool,
+    EraseTool,
+    /// Fill the view with random cells, from a new seed each time. This is
+    /// there without the `random` feature too, so that bindings saved by a
+    /// build with it can still be read, but does nothing.
+    RandomFill,
+    NextTheme,
+    Fullscreen,
+    Screenshot,
+    Record,
+    /// Put away the pattern being stamped, or quit if there isn't one
+    Exit,
+}
+
+impl Action {
+    pub const ALL: &'static [Self] = &[
+        Self::Play,
+        Self::Step,
+        Self::Clear,
+        Self::SpeedUp,
+        Self::SlowDown,
+        Self::FastForward,
+        Self::StepUntilChange,
+        Self::ToggleFitZoom,
+        Self::Center,
+        Self::FitView,
+        Self::ResetView,
+        Self::Rotate,
+        Self::FlipX,
+        Self::FlipY,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::ToggleTool,
+        Self::DrawTool,
+        Self::EraseTool,
+        // Not offered when it wouldn't do anything
+        #[cfg(feature = "random")]
+        Self::RandomFill,
+        Self::NextTheme,
+        Self::Fullscreen,
+        Self::Screenshot,
+        Self::Record,
+        Self::Exit,
+    ];
+
+    /// Whether holding the key down does the action again and again
+    pub fn repeats(self) -> bool {
+        matches!(
+            self,
+            Self::Play
+                | Self::Step
+                | Self::SpeedUp
+                | Self::SlowDown
+                | Self::ZoomIn
+                | Self::ZoomOut
+        )
+    }
+
+    /// The keys the action is bound to unless they're changed
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Self::Play => &["Space"],
+            Self::Step => &["Tab"],
+            Self::Clear => &["c"],
+            Self::SpeedUp => &["ArrowUp"],
+            Self::SlowDown => &["ArrowDown"],
+            Self::FastForward => &["ArrowRight"],
+            Self::StepUntilChange => &["n"],
+            Self::ToggleFitZoom => &["z"],
+            Self::Center => &["g"],
+            Self::FitView => &["f"],
+            Self::ResetView => &["Home"],
+            Self::Rotate => &["r"],
+            Self::FlipX => &["x"],
+            Self::FlipY => &["y"],
+            // "=" is "+" without Shift on most keyboards
+            Self::ZoomIn => &["+", "="],
+            Self::ZoomOut => &["-"],
+            Self::ToggleTool => &["1"],
+            Self::DrawTool => &["2"],
+            Self::EraseTool => &["3"],
+            Self::RandomFill => &["R"],
+            Self::NextTheme => &["t"],
+            Self::Fullscreen => &["F11"],
+            Self::Screenshot => &["F12"],
+            Self::Record => &["F9"],
+            Self::Exit => &["Escape"],
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Play => "Play and pause",
+            Self::Step => "Step (Shift to step back)",
+            Self::Clear => "Clear the board",
+            Self::SpeedUp => "Speed up",
+            Self::SlowDown => "Slow down",
+            Self::FastForward => "Fast forward while held",
+            Self::StepUntilChange => "Step until the cell under the cursor changes",
+            Self::ToggleFitZoom => "Zoom to fit the pattern and back",
+            Self::Center => "Center on the living cells",
+            Self::FitView => "Fit the view to the living cells",
+            Self::ResetView => "Go back to the starting view",
+            Self::Rotate => "Rotate the pattern",
+            Self::FlipX => "Flip left to right",
+            Self::FlipY => "Flip upside down",
+            Self::ZoomIn => "Zoom in",
+            Self::ZoomOut => "Zoom out",
+            Self::ToggleTool => "Toggle tool",
+            Self::DrawTool => "Draw tool",
+            Self::EraseTool => "Erase tool",
+            Self::RandomFill => "Fill the view with random cells",
+            Self::NextTheme => "Next color theme",
+            Self::Fullscreen => "Fullscreen",
+            Self::Screenshot => "Screenshot",
+            Self::Record => "Record a GIF",
+            Self::Exit => "Put away the stamp or quit",
+        })
+    }
+}
+
+/// The name a key is bound by, or `None` for keys that can't be bound
+pub fn key_name(key: &Key) -> Option<String> {
+    match key {
+        Key::Character(text) => Some(text.to_string()),
+        Key::Named(named) => Some(format!("{named:?}")),
+        _ => None,
+    }
+}
+
+/// The keys each action is done with. Actions that were unbound have no keys,
+/// and actions that aren't in the map at all get their default keys when the
+/// bindings are loaded.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KeyBindings {
+    keys: BTreeMap<Action, Vec<String>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: Action::ALL
+                .iter()
+                .map(|&a| (a, a.default_keys().iter().map(|k| k.to_string()).collect()))
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// The action a key is bound to, if any
+    pub fn action(&self, key: &Key) -> Option<Action> {
+        let name = key_name(key)?;
+        self.keys
+            .iter()
+            .find_map(|(action, bound)| bound.contains(&name).then_some(*action))
+    }
+
+    /// The names of the keys an action is bound to, which is empty if there
+    /// aren't any
+    pub fn keys(&self, action: Action) -> &[String] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Bind an action to a key instead of the keys it had. Whatever the key
+    /// was bound to before loses it, so that a key never does two things.
+    pub fn bind(&mut self, action: Action, key: String) {
+        for bound in self.keys.values_mut() {
+            bound.retain(|k| *k != key);
+        }
+        self.keys.insert(action, vec![key]);
+    }
+
+    /// Leave an action without a key
+    pub fn unbind(&mut self, action: Action) {
+        self.keys.insert(action, Vec::new());
+    }
+}
+
+#[cfg(feature = "saving")]
+impl KeyBindings {
+    /// What the bindings are stored under
+    const STORAGE_KEY: &'static str = "keys";
+
+    /// The bindings kept by `store`, or the default ones if there aren't any
+    /// or they can't be read
+    pub fn load(storage: &dyn DataStorage) -> Self {
+        let stored = storage.read(Self::STORAGE_KEY).and_then(|data| {
+            data.map(|data| serde_json::from_str::<Self>(&data))
+                .transpose()
+                .map_err(anyhow::Error::from)
+        });
+        match stored {
+            Ok(Some(mut bindings)) => {
+                bindings.add_missing_defaults();
+                bindings
+            }
+            Ok(None) => Self::default(),
+            Err(e) => {
+                log::error!("Failed to load the key bindings: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Keep the bindings so that `load` gets them back
+    pub fn store(&self, storage: &mut dyn DataStorage) -> anyhow::Result<()> {
+        storage.write(Self::STORAGE_KEY, &serde_json::to_string_pretty(self)?)
+    }
+
+    /// Give actions that aren't in the map, like ones added since the
+    /// bindings were stored, the default keys that nothing else has taken
+    fn add_missing_defaults(&mut self) {
+        for &action in Action::ALL {
+            if self.keys.contains_key(&action) {
+                continue;
+            }
+            let free = action
+                .default_keys()
+                .iter()
+                .filter(|&&key| {
+                    self.keys
+                        .values()
+                        .all(|bound| !bound.iter().any(|k| k == key))
+                })
+                .map(|key| key.to_string())
+                .collect();
+            self.keys.insert(action, free);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_takes_the_key_away() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(Action::Clear, "=".to_string());
+        assert_eq!(bindings.keys(Action::Clear), ["="]);
+        assert_eq!(bindings.keys(Action::ZoomIn), ["+"]);
+        bindings.bind(Action::FlipX, "+".to_string());
+        assert!(bindings.keys(Action::ZoomIn).is_empty());
+    }
+
+    #[test]
+    fn bindings_read_with_every_action() {
+        let json = serde_json::to_string(&KeyBindings::default()).unwrap();
+        let mut bindings: KeyBindings = serde_json::from_str(&json).unwrap();
+        assert_eq!(bindings, KeyBindings::default());
+        // Random fills are bound in builds with the `random` feature, and the
+        // bindings have to read the same in ones without it
+        bindings.bind(Action::RandomFill, "R".to_string());
+        let json = serde_json::to_string(&bindings).unwrap();
+        let read: KeyBindings = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.keys(Action::RandomFill), ["R"]);
+        assert_eq!(read.keys(Action::Play), ["Space"]);
+    }
+
+    #[cfg(feature = "saving")]
+    #[test]
+    fn missing_actions_get_free_defaults() {
+        let mut bindings = KeyBindings::default();
+        bindings.unbind(Action::Clear);
+        bindings.bind(Action::FlipX, "=".to_string());
+        bindings.keys.remove(&Action::ZoomIn);
+        bindings.add_missing_defaults();
+        assert_eq!(bindings.keys(Action::ZoomIn), ["+"]);
+        // Unbound actions stay that way
+        assert!(bindings.keys(Action::Clear).is_empty());
+    }
+}
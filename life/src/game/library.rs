@@ -0,0 +1,32 @@
+//! Well-known patterns that come with the game, so they don't have to be
+//! drawn by hand every time.
+
+/// A named pattern in RLE format
+pub struct LibraryPattern {
+    pub name: &'static str,
+    pub rle: &'static str,
+}
+
+/// Every pattern in the library, in the order they're listed in the GUI
+pub const LIBRARY: &[LibraryPattern] = &[
+    LibraryPattern {
+        name: "Glider",
+        rle: "x = 3, y = 3\nbo$2bo$3o!",
+    },
+    LibraryPattern {
+        name: "Lightweight spaceship",
+        rle: "x = 5, y = 4\nbo2bo$o4b$o3bo$4o!",
+    },
+    LibraryPattern {
+        name: "Gosper glider gun",
+        rle: "x = 36, y = 9\n\
+              24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+              2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    },
+    LibraryPattern {
+        name: "Pulsar",
+        rle: "x = 13, y = 13\n\
+              2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$\
+              o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    },
+];
@@ -0,0 +1,70 @@
+//! A small overview of the whole board, for finding the way back to the
+//! pattern after panning away from it.
+
+use vec2::Vector2;
+
+use super::{patterns::bounding_box, LivingList};
+
+/// A thumbnail of how crowded each part of the board is. It covers the living
+/// cells and the view, so that both can be drawn on it.
+pub struct Minimap {
+    /// The top-left corner of the area covered, in cell coordinates
+    pub min: Vector2<f64>,
+    /// How many cells across each square of the thumbnail is
+    pub scale: f64,
+    /// The number of squares across the thumbnail
+    pub width: usize,
+    /// The number of squares down the thumbnail
+    pub height: usize,
+    /// How crowded each square is compared to the most crowded one, from 0 to
+    /// 1, row by row
+    pub density: Vec<f32>,
+}
+
+impl Minimap {
+    /// Count the cells in each square of a thumbnail covering `cells` and the
+    /// view, which is given by its corners in cell coordinates. The longer
+    /// side of the thumbnail is `resolution` squares.
+    pub fn new(cells: &LivingList, view: (Vector2<f64>, Vector2<f64>), resolution: usize) -> Self {
+        let (mut min, mut max) = view;
+        if let Some((cells_min, cells_max)) = bounding_box(cells) {
+            min = Vector2::<f64>::min(min, Vector2::new(cells_min.x as f64, cells_min.y as f64));
+            // The far edges of the last cells
+            max = Vector2::<f64>::max(
+                max,
+                Vector2::new(cells_max.x as f64 + 1.0, cells_max.y as f64 + 1.0),
+            );
+        }
+        let resolution = resolution.max(1);
+        let size = max - min;
+        let scale = size.x.max(size.y) / resolution as f64;
+        let width = ((size.x / scale).ceil() as usize).clamp(1, resolution);
+        let height = ((size.y / scale).ceil() as usize).clamp(1, resolution);
+
+        let mut counts = vec![0u32; width * height];
+        for cell in cells {
+            let x = ((cell.x as f64 - min.x) / scale) as usize;
+            let y = ((cell.y as f64 - min.y) / scale) as usize;
+            counts[y.min(height - 1) * width + x.min(width - 1)] += 1;
+        }
+        let most = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+        Self {
+            min,
+            scale,
+            width,
+            height,
+            density: counts.into_iter().map(|n| n as f32 / most).collect(),
+        }
+    }
+
+    /// The size of the area covered, in cells
+    pub fn size(&self) -> Vector2<f64> {
+        Vector2::new(self.width as f64, self.height as f64) * self.scale
+    }
+
+    /// The point on the board at a fraction of the way across and down the
+    /// thumbnail, in cell coordinates
+    pub fn point_at(&self, fraction: Vector2<f64>) -> Vector2<f64> {
+        self.min + Vector2::<f64>::scale(fraction, self.size())
+    }
+}
@@ -1,6 +1,6 @@
-use rustc_hash::{FxHashMap, FxHashSet};
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, HashSet, VecDeque},
+    ops::ControlFlow,
     time::Duration,
     sync::Arc
 };
@@ -12,13 +12,15 @@ use std::thread::JoinHandle;
 use std::sync::{
     self,
     atomic::{self, AtomicBool},
-    mpsc, Condvar, Mutex
+    mpsc, Condvar, Mutex, PoisonError,
 };
 
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
-    keyboard::{Key, KeyCode, NamedKey, PhysicalKey, SmolStr},
+    event::{
+        ElementState, KeyEvent, MouseButton, MouseScrollDelta, Touch, TouchPhase, WindowEvent,
+    },
+    keyboard::{Key, KeyCode, ModifiersState, NamedKey, PhysicalKey, SmolStr},
     window::Window,
 };
 #[cfg(not(target_arch = "wasm32"))]
@@ -26,37 +28,183 @@ use std::time::Instant;
 #[cfg(target_arch = "wasm32")]
 use web_time::Instant;
 
-#[cfg(feature = "saving")]
+#[cfg(all(feature = "saving", not(target_arch = "wasm32")))]
 use crate::game::saving::SaveFile;
 #[cfg(feature = "saving")]
 use self::saving::SaveGame;
 
-use super::render::Cell;
+use self::keys::Action;
+use super::render::{theme, Cell, CellDelta, Highlight};
 use vec2::Vector2;
 
 #[cfg(feature = "saving")]
 pub mod saving;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bench;
+pub mod boundary;
+mod chunks;
+mod clipboard;
+pub mod keys;
+pub mod library;
+pub mod minimap;
+pub mod patterns;
+pub mod payload;
+pub mod rules;
+pub mod share;
+pub mod simulation;
+pub mod stats;
+#[cfg(all(feature = "saving", target_arch = "wasm32"))]
+pub mod web_storage;
 
 /// The interval between simulation steps in auto-play mode.
 const DEFAULT_INTERVAL: Duration = Duration::from_millis(300);
 /// The factor by which the interval will be multiplied or divided when
 /// the player changes the simulation speed.
 const INTERVAL_P: f32 = 1.2;
+/// The most steps that will be taken looking for a change to the cell under the
+/// cursor before giving up.
+const MAX_STEPS_UNTIL_CHANGE: u64 = 1000;
+/// The smallest grid size that can be zoomed out to
+pub(crate) const MIN_GRID_SIZE: f32 = 0.005;
+/// The largest grid size that can be zoomed in to
+pub(crate) const MAX_GRID_SIZE: f32 = 1.0;
+/// The fraction of the window that a pattern takes up when zoomed to fit it
+const FIT_FRACTION: f64 = 0.8;
+/// The fraction of cells that are alive after a random fill from the keyboard
+#[cfg(feature = "random")]
+const RANDOM_FILL_DENSITY: f32 = 0.3;
+/// The default maximum time between the clicks of a double-click
+const DEFAULT_DOUBLE_CLICK_TIME: Duration = Duration::from_millis(300);
+/// How long the view takes to glide in or out when toggling the fit zoom
+const ZOOM_TOGGLE_TIME: Duration = Duration::from_millis(250);
+/// The default number of past generations kept for stepping backwards
+const DEFAULT_HISTORY_LIMIT: usize = 64;
+/// The default cap on the memory used by past generations, in bytes. Browsers
+/// give pages much less memory, so the history is capped there.
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_HISTORY_MEMORY_LIMIT: Option<usize> = None;
+#[cfg(target_arch = "wasm32")]
+const DEFAULT_HISTORY_MEMORY_LIMIT: Option<usize> = Some(32 << 20);
+/// How much one line of scrolling zooms by at a sensitivity of 1, as the log of
+/// the zoom factor
+const ZOOM_PER_LINE: f64 = 0.1;
+/// How much one press of a zoom key zooms by, as the log of the zoom factor
+const ZOOM_PER_KEY: f64 = 0.25;
+/// How many pixels of a pixel-based scroll (trackpads and browsers) count as one
+/// line of a mouse wheel
+#[cfg(not(target_arch = "wasm32"))]
+const PIXELS_PER_LINE: f64 = 20.0;
+#[cfg(target_arch = "wasm32")]
+const PIXELS_PER_LINE: f64 = 100.0;
+/// The default multiplier for how fast scrolling zooms
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_SCROLL_SENSITIVITY: f64 = 1.0;
+#[cfg(target_arch = "wasm32")]
+const DEFAULT_SCROLL_SENSITIVITY: f64 = 0.5;
+/// The modifier that makes scrolling step through generations while paused.
+/// Browsers send trackpad pinches as scrolling with Ctrl held, so Alt is used
+/// there instead.
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_SCROLL_STEP_MODIFIER: Option<StepModifier> = Some(StepModifier::Control);
+#[cfg(target_arch = "wasm32")]
+const DEFAULT_SCROLL_STEP_MODIFIER: Option<StepModifier> = Some(StepModifier::Alt);
+/// How far one press of a panning key moves the view, as a fraction of the
+/// size of the window
+const KEY_PAN_FRACTION: f64 = 0.1;
+/// How far a finger can move, in pixels, between touching the screen and
+/// lifting off for it to still count as a tap
+const TAP_SLOP: f64 = 10.0;
+/// The most generations autoplay can fall behind by. Slow frames make up for
+/// lost time with extra steps, but only this many, so that steps taking longer
+/// than the interval don't make each frame slower than the last.
+const MAX_STEPS_PER_FRAME: u64 = 8;
+/// The longest a frame spends stepping when fast forwarding or running a
+/// number of generations as fast as possible, so that the window still
+/// responds
+const RUN_FAST_BUDGET: Duration = Duration::from_millis(8);
+/// The default time between autosaves
+#[cfg(feature = "saving")]
+pub const DEFAULT_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+/// The save slot the game is autosaved to
+#[cfg(feature = "saving")]
+const AUTOSAVE_SLOT: &str = "autosave";
+/// The title of the window when it isn't showing the generation
+const WINDOW_TITLE: &str = "life";
+/// What the window title shows by default. `{gen}` is replaced with the
+/// generation and `{cells}` with the number of living cells.
+pub const DEFAULT_TITLE_FORMAT: &str = "life \u{2014} gen {gen}, {cells} cells";
+/// The least time between changes to the window title, so the windowing
+/// system isn't asked to change it every frame
+const TITLE_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+/// How long a message to the player stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(4);
 
-type LivingList = FxHashSet<Vector2<i32>>;
+/// The hasher for sets and maps keyed by cell. FxHash is much faster than the
+/// standard library's SipHash, but anyone who controls the pattern can choose
+/// cells that all collide and make every step quadratic. The `sip_hash`
+/// feature switches to randomly seeded SipHash for when patterns come from
+/// untrusted sources.
+#[cfg(not(feature = "sip_hash"))]
+type CellHasher = std::hash::BuildHasherDefault<rustc_hash::FxHasher>;
+#[cfg(feature = "sip_hash")]
+type CellHasher = std::collections::hash_map::RandomState;
+
+type LivingList = HashSet<Vector2<i32>, CellHasher>;
+/// A map from cells to some value, using the same hasher as `LivingList`
+type CellMap<V> = HashMap<Vector2<i32>, V, CellHasher>;
 
 pub struct GameState {
     pan_position: Vector2<f64>,
-    /// A hashset of cells (by coordinates) that are living.
-    living_cells: LivingList,
+    /// The living cells and the rules they follow
+    sim: simulation::Simulation,
     /// Timing and play information
     loop_state: LoopState,
     /// The interval between steps in auto-play mode
     interval: std::time::Duration,
+    /// Whether steps are taken on a fixed schedule, counting the generations
+    /// that couldn't be computed in time instead of slowing down.
+    strict_rate: bool,
+    /// The number of generations skipped in strict-rate mode
+    dropped_generations: u64,
     window: Arc<Window>,
     mouse_position: Option<Vector2<f64>>,
     grid_size: f32,
+    /// The grid size the game started with
+    initial_grid_size: f32,
     drag_state: DragState,
+    /// Where each finger touching the screen is, by touch id
+    touches: HashMap<u64, Vector2<f64>>,
+    /// The finger that could still be tapping a cell and where it first
+    /// touched, if there is one. It stops being a tap once it moves too far
+    /// or another finger touches.
+    tap: Option<(u64, Vector2<f64>)>,
+    /// The cells being painted while the left button is held, if it is
+    paint_stroke: Option<PaintStroke>,
+    /// What clicking on cells does to them
+    tool: Tool,
+    /// Which mirror images of the cells clicked on are changed with them
+    symmetry: Symmetry,
+    /// Which mouse buttons drag the view around
+    pan_button: PanButton,
+    /// The last left click that changed cells, for detecting double-clicks
+    last_click: Option<Click>,
+    /// The maximum time between the clicks of a double-click
+    double_click_time: Duration,
+    /// A multiplier for how fast scrolling zooms
+    scroll_sensitivity: f64,
+    /// The modifier that makes scrolling step forwards and backwards while
+    /// paused instead of zooming, if any
+    scroll_step_modifier: Option<StepModifier>,
+    /// Scrolling towards the next step, in lines, for scrolls that come a
+    /// fraction of a line at a time
+    scroll_steps: f64,
+    /// The view to go back to when toggling out of the fit-to-pattern zoom
+    previous_camera: Option<Camera>,
+    /// Whether toggling the fit zoom glides between the views instead of
+    /// jumping
+    animate_zoom: bool,
+    /// The glide between views that's going on, if any
+    camera_transition: Option<CameraTransition>,
     /// A queue of inputs that were made during computation and therefore
     /// deferred.
     input_queue: VecDeque<QueueAction>,
@@ -66,7 +214,13 @@ pub struct GameState {
     living_cell_count: usize,
 
     /// These are for the statistics view
-    pub step_count: u64,
+    /// The period of the pattern, if it has started repeating itself
+    pub detected_period: Option<u64>,
+    /// How fast the pattern moves, if it has started repeating itself
+    /// somewhere else
+    pub detected_velocity: Option<stats::Velocity>,
+    /// Whether the pattern has died out or stopped changing
+    pub status: stats::SimStatus,
     pub living_count_history: Vec<usize>,
 
     /// Changes to the state between renders are tracked here if they are
@@ -84,8 +238,115 @@ pub struct GameState {
     /// the game is closed.
     #[cfg(feature = "saving")]
     pub save_file: Option<saving::SaveFile>,
+    /// Where named save slots are kept
+    #[cfg(feature = "saving")]
+    slot_storage: Box<dyn saving::DataStorage + Send>,
+    /// Where settings that aren't part of a save, like the key bindings, are
+    /// kept
+    #[cfg(feature = "saving")]
+    settings_storage: Box<dyn saving::DataStorage + Send>,
+    /// How often the game is saved to `AUTOSAVE_SLOT`, if at all
+    #[cfg(feature = "saving")]
+    autosave_interval: Option<Duration>,
+    #[cfg(feature = "saving")]
+    last_autosave: Instant,
+    /// Whether the board or view changed since the last autosave
+    #[cfg(feature = "saving")]
+    unsaved_changes: bool,
+
+    /// Data attached to the living cells by an embedder, if any. See
+    /// `payload::CellPayload`.
+    payloads: Option<Box<dyn payload::PayloadStore>>,
+    /// The age of every living cell
+    ages: stats::AgeMap,
+    /// The most recent past generations, oldest first, for stepping backwards
+    history: VecDeque<LivingList>,
+    /// The most past generations that are kept
+    history_limit: usize,
+    /// The most memory the past generations can take up, in bytes, if there's
+    /// a limit. The oldest ones are dropped first to stay under it.
+    history_memory_limit: Option<usize>,
+    /// The modifier keys that are currently held
+    modifiers: ModifiersState,
+    /// Which keys do what
+    key_bindings: keys::KeyBindings,
+    /// The action that the next key pressed will be bound to, if one is
+    /// being rebound
+    rebinding: Option<Action>,
+    /// The panning keys that are currently held, so that holding two pans
+    /// diagonally
+    held_pan_keys: HashSet<KeyCode>,
+    /// Recent generations, for finding oscillators
+    period_detector: stats::PeriodDetector,
+    /// Whether autoplay stops when the pattern dies out or stops changing
+    auto_pause: bool,
+    /// Whether autoplay stops while the window isn't focused
+    pause_on_unfocus: bool,
+    /// Whether autoplay was stopped because the window lost focus, so it
+    /// should start again when it gets it back
+    paused_for_focus: bool,
+    /// The generation to stop at, if autoplay was started for a number of
+    /// generations
+    run_target: Option<u64>,
+    /// Whether running a number of generations ignores the interval
+    run_fast: bool,
+    /// Whether the fast forward key is held
+    turbo: bool,
+    /// How the living cells changed since the renderer was last told
+    cell_changes: CellChanges,
+    /// Whether cells that were just born or just died are tinted
+    highlight_changes: bool,
+    /// The cells born in the last step, when highlighting changes
+    last_born: LivingList,
+    /// The cells that died in the last step, when highlighting changes
+    last_died: LivingList,
+    /// How long cells take to fade out after they die, as a fraction of the
+    /// interval, or `None` if they disappear straight away
+    fade_out: Option<f32>,
+    /// The cells that died in the last step and are still fading out, and
+    /// when they started
+    fading: LivingList,
+    fade_started: Option<Instant>,
+    /// The seed for the next random fill from the keyboard
+    #[cfg(feature = "random")]
+    random_seed: u64,
+    /// A pattern waiting to be placed by the next click
+    stamp: Option<Stamp>,
+    /// Whether clearing the board has to be confirmed first
+    confirm_clear: bool,
+    /// Whether clearing the board is waiting to be confirmed
+    pending_clear: bool,
+    /// The bounding box and center of mass of the living cells
+    shape: stats::ShapeStats,
+    /// Whether the board was changed by hand since `shape` was worked out
+    shape_stale: bool,
+    /// How fast the center of mass has been moving
+    drift: stats::DriftTracker,
+    /// The name of the color theme. The GUI applies it to the renderer, and
+    /// it's kept here so that it's saved with the game.
+    theme: &'static str,
+    /// How often each cell has been alive while the heatmap was shown
+    heatmap: stats::HeatMap,
+    /// Whether the cells are colored by `heatmap` instead of being drawn as
+    /// they are
+    show_heatmap: bool,
+    /// What the window title shows, or `None` to leave it as `WINDOW_TITLE`
+    title_format: Option<String>,
+    /// When the window title was last changed
+    title_updated: Option<Instant>,
+    /// Where patterns are copied to, as RLE
+    clipboard: clipboard::Clipboard,
+    /// A message for the player, and when it was shown
+    toast: Option<(String, Instant)>,
+    /// Called by an embedder after every generation, if set. See
+    /// `set_on_step`.
+    on_step: Option<StepCallback>,
 }
 
+/// A function called after every generation with what it came to. Returning
+/// `ControlFlow::Break` stops autoplay.
+pub type StepCallback = Box<dyn FnMut(&StepInfo) -> ControlFlow<()> + Send>;
+
 impl GameState {
     pub fn is_playing(&self) -> bool {
         self.loop_state.is_playing()
@@ -96,84 +357,486 @@ impl GameState {
         self.living_cell_count
     }
 
+    /// Whether the population has recently been growing, shrinking, or
+    /// holding steady
+    pub fn population_trend(&self) -> stats::PopulationTrend {
+        stats::PopulationTrend::of(&self.living_count_history)
+    }
+
     pub fn get_interval(&self) -> Duration {
         self.interval
     }
 
+    /// The bounding box and center of mass of the living cells, as of the
+    /// last step or the last frame after the board was edited
+    pub fn shape_stats(&self) -> stats::ShapeStats {
+        self.shape
+    }
+
+    /// How far the center of mass has moved per generation, averaged over the
+    /// last `stats::DRIFT_WINDOW` generations. This is the velocity of a
+    /// spaceship. It's `None` until there have been that many steps since the
+    /// board was last edited.
+    pub fn center_of_mass_drift(&self) -> Option<Vector2<f64>> {
+        self.drift.velocity()
+    }
+
+    /// Work out the bounding box and center of mass again if the board was
+    /// edited since they last were
+    fn refresh_shape(&mut self) {
+        if self.shape_stale {
+            self.shape = stats::ShapeStats::of(&self.sim.living_cells);
+            self.shape_stale = false;
+        }
+    }
+
+    /// The number of living cells of each age, from 1 generation old up to
+    /// `stats::AGE_BUCKETS` or more
+    pub fn age_histogram(&self) -> &[usize] {
+        self.ages.histogram()
+    }
+
+    /// The rule being simulated, in B/S notation
+    pub fn rule_string(&self) -> String {
+        self.sim.ruleset.to_string()
+    }
+
+    pub fn ruleset(&self) -> rules::Ruleset {
+        self.sim.ruleset
+    }
+
+    /// Change the rule used from the next step on. A step that's already
+    /// being computed still uses the old one, unless the board is a torus too
+    /// small for the new rule, in which case it's grown first.
+    pub fn set_ruleset(&mut self, ruleset: rules::Ruleset) {
+        let grown = self.sim.boundary.clamped(ruleset.neighborhood.range());
+        if grown != self.sim.boundary {
+            self.set_boundary(grown);
+        }
+        self.sim.set_ruleset(ruleset);
+        self.reset_detection();
+        // Decaying cells may have died
+        self.cell_changes.refresh_all();
+    }
+
+    pub fn noise(&self) -> Option<rules::Noise> {
+        self.sim.noise
+    }
+
+    /// Make the rule a noisy one, where cells that qualify to be born or to
+    /// survive only do so with some probability, or a deterministic one again
+    /// with `None`.
+    pub fn set_noise(&mut self, noise: Option<rules::Noise>) {
+        self.sim.set_noise(noise);
+        self.reset_detection();
+    }
+
+    pub fn boundary(&self) -> boundary::BoundaryMode {
+        self.sim.boundary
+    }
+
+    /// Change what happens at the edges of the board. Living cells that are
+    /// off the new board are wrapped onto it, and boards that are too small
+    /// for the rule are grown to [`boundary::BoundaryMode::min_torus_size`].
+    pub fn set_boundary(&mut self, boundary: boundary::BoundaryMode) {
+        self.finish_pending_step();
+        self.sim.set_boundary(boundary);
+        // Past generations may be off the new board
+        self.history.clear();
+        self.reset_detection();
+        self.living_cell_count = self.sim.living_cells.len();
+        if let Some(payloads) = &mut self.payloads {
+            payloads.reset(&self.sim.living_cells);
+        }
+        self.ages.reset(&self.sim.living_cells);
+        self.clear_highlights();
+        self.cell_changes.refresh_all();
+    }
+
+    /// The size of a cell, as a fraction of the window's height
+    pub fn grid_size(&self) -> f32 {
+        self.grid_size
+    }
+
+    /// The coordinates of every living cell, in no particular order
+    pub fn living_cells(&self) -> impl Iterator<Item = Vector2<i32>> + '_ {
+        self.sim.living_cells()
+    }
+
+    /// The board and rules without anything to do with the window
+    pub fn simulation(&self) -> &simulation::Simulation {
+        &self.sim
+    }
+
+    /// The number of steps taken since the game started or the statistics
+    /// were last reset
+    pub fn step_count(&self) -> u64 {
+        self.sim.step_count
+    }
+
+    pub fn reset_step_count(&mut self) {
+        self.sim.step_count = 0;
+    }
+
+    /// The smallest and largest coordinates of the cells that are at least
+    /// partly in the window.
+    pub fn visible_cells(&self) -> (Vector2<i32>, Vector2<i32>) {
+        let size = self.window.inner_size();
+        let corner = Vector2::new(size.width as f64, size.height as f64);
+        (
+            find_cell_num(size, Vector2::new(0.0, 0.0), self.pan_position, self.grid_size),
+            find_cell_num(size, corner, self.pan_position, self.grid_size),
+        )
+    }
+
+    /// The corners of the part of the board that's in the window, in cell
+    /// coordinates. Unlike `visible_cells`, this isn't rounded to whole cells.
+    pub fn view_rect(&self) -> (Vector2<f64>, Vector2<f64>) {
+        let size = self.window.inner_size();
+        let corner = Vector2::new(size.width as f64, size.height as f64);
+        let to_cells = |p| (view_position(size, p) + self.pan_position) / self.grid_size as f64;
+        (to_cells(Vector2::new(0.0, 0.0)), to_cells(corner))
+    }
+
+    /// A thumbnail of where the living cells are, with its longer side
+    /// `resolution` squares. This goes through every living cell, so it
+    /// shouldn't be done every frame.
+    pub fn minimap(&self, resolution: usize) -> minimap::Minimap {
+        minimap::Minimap::new(&self.sim.living_cells, self.view_rect(), resolution)
+    }
+
+    /// The cell under the mouse cursor, or `None` if the cursor isn't in the
+    /// window. It's worked out when asked for, so it follows panning and
+    /// zooming as well as the cursor.
+    pub fn cursor_cell(&self) -> Option<Vector2<i32>> {
+        let position = self.mouse_position?;
+        let size = self.window.inner_size();
+        Some(find_cell_num(size, position, self.pan_position, self.grid_size))
+    }
+
     pub fn set_interval(&mut self, to: Duration) {
         self.interval = to;
     }
 
-    /// Toggles playing. If it is starting, then it steps immediately.
+    /// Run at exactly `sps` steps per second, or go back to the normal pacing
+    /// with `None`.
+    ///
+    /// In strict mode, autoplay keeps to a fixed schedule. When a step can't
+    /// be computed in time, it is skipped and counted in
+    /// `dropped_generations` rather than delaying the ones after it, so that
+    /// the timing stays honest for recording. Rates that are zero, negative,
+    /// or too far out to be an interval are turned down and leave the pacing
+    /// as it was.
+    pub fn set_strict_rate(&mut self, sps: Option<f64>) {
+        if let Some(sps) = sps {
+            let interval = Duration::try_from_secs_f64(sps.recip());
+            let Some(interval) = interval.ok().filter(|i| !i.is_zero()) else {
+                log::warn!("Can't run at {sps} steps per second");
+                return;
+            };
+            self.interval = interval;
+            self.strict_rate = true;
+            self.dropped_generations = 0;
+        } else {
+            self.strict_rate = false;
+        }
+    }
+
+    pub fn is_strict_rate(&self) -> bool {
+        self.strict_rate
+    }
+
+    /// The number of generations that were skipped because they couldn't be
+    /// computed in time since strict-rate mode was turned on.
+    pub fn dropped_generations(&self) -> u64 {
+        self.dropped_generations
+    }
+
+    /// How many steps autoplay wants now, at most `max`. Outside of strict
+    /// mode, steps that are due but can't be taken yet are made up for in
+    /// later frames.
+    fn steps_due(&mut self, max: u64) -> u64 {
+        if self.strict_rate {
+            let (due, skipped) = self.loop_state.update_strict(&self.interval, max);
+            self.dropped_generations += skipped;
+            due
+        } else {
+            self.loop_state.update(&self.interval, max)
+        }
+    }
+
+    /// Toggles playing. Pausing remembers how long was left until the next
+    /// step, and resuming waits out the rest of it, so pausing and resuming
+    /// quickly can't step faster than the interval. Starting when it wasn't
+    /// paused steps immediately.
     pub fn toggle_playing(&mut self) {
+        self.paused_for_focus = false;
+        self.run_target = None;
         if self.loop_state.is_playing() {
-            self.loop_state = LoopState::Stopped;
+            self.loop_state.pause(&self.interval);
+        } else if let LoopState::Paused { remaining } = self.loop_state {
+            self.loop_state = LoopState::resumed(remaining, &self.interval);
         } else {
             self.step();
-            let now = Instant::now();
-            self.loop_state = LoopState::Playing { last_update: now }
+            self.loop_state = LoopState::playing();
         }
     }
 
     /// Get a vector of all the cells that should be rendered
     fn get_cells(&self) -> Vec<Cell> {
-        let res: Vec<Cell> = self
+        let mut res = if self.show_heatmap {
+            self.get_heat_cells()
+        } else {
+            self.get_living_cells()
+        };
+        res.extend(self.sim.walls.iter().map(|c| Cell {
+            highlight: Highlight::Wall,
+            ..to_cell(*c, self.grid_size)
+        }));
+        // A pattern about to be stamped is previewed under the cursor
+        if let Some(stamp) = &self.stamp
+            && let Some(cell) = stamp.cell
+        {
+            res.extend(stamp.cells_at(cell).map(|c| Cell {
+                highlight: Highlight::Ghost,
+                ..to_cell(c, self.grid_size)
+            }));
+        }
+        res
+    }
+
+    /// The living cells, along with the ones that just died if changes are
+    /// highlighted
+    fn get_living_cells(&self) -> Vec<Cell> {
+        let mut res: Vec<Cell> = self
+            .sim
             .living_cells
             .iter()
-            .map(|i| to_cell(*i, self.grid_size))
+            .map(|i| {
+                let mut cell = to_cell(*i, self.grid_size);
+                if self.last_born.contains(i) {
+                    cell.highlight = Highlight::Born;
+                }
+                cell
+            })
             .collect();
+        // Decaying cells fade out as they get closer to dying
+        let states = self.sim.ruleset.states;
+        res.extend(
+            self.sim
+                .decaying
+                .iter()
+                .filter(|(c, _)| !self.sim.living_cells.contains(c))
+                .map(|(c, state)| Cell {
+                    highlight: Highlight::Decaying,
+                    level: (state - 1) as f32 / (states - 1) as f32,
+                    ..to_cell(*c, self.grid_size)
+                }),
+        );
+        // Cells that just died fade out over a moment, if that's turned on
+        let fade = self.fade_progress();
+        res.extend(
+            self.fading
+                .iter()
+                .filter(|c| !self.sim.living_cells.contains(c) && !self.last_died.contains(c))
+                .map(|c| Cell {
+                    highlight: Highlight::Fading,
+                    level: fade,
+                    ..to_cell(*c, self.grid_size)
+                }),
+        );
+        // Cells that just died are still drawn for one generation
+        res.extend(
+            self.last_died
+                .iter()
+                .filter(|c| !self.sim.living_cells.contains(c) && !self.sim.decaying.contains_key(c))
+                .map(|c| Cell {
+                    highlight: Highlight::Died,
+                    ..to_cell(*c, self.grid_size)
+                }),
+        );
         res
     }
 
-    fn handle_scroll(&mut self, delta: MouseScrollDelta) {
-        #[cfg(not(target_arch = "wasm32"))]
-        const PIXEL_MUL: f64 = 3.0;
+    /// Every cell that has been alive since the heatmap was reset, colored by
+    /// how often. Living cells that haven't been counted yet, like ones just
+    /// placed by hand, are drawn as cold as can be.
+    fn get_heat_cells(&self) -> Vec<Cell> {
+        let mut res: Vec<Cell> = self
+            .heatmap
+            .iter()
+            .map(|(c, heat)| Cell {
+                highlight: Highlight::Heat,
+                level: heat,
+                ..to_cell(c, self.grid_size)
+            })
+            .collect();
+        res.extend(
+            self.sim
+                .living_cells
+                .iter()
+                .filter(|c| !self.heatmap.contains(c))
+                .map(|c| Cell {
+                    highlight: Highlight::Heat,
+                    ..to_cell(*c, self.grid_size)
+                }),
+        );
+        res
+    }
 
-        #[cfg(target_arch = "wasm32")]
-        const PIXEL_MUL: f64 = 0.2;
+    fn handle_scroll(&mut self, delta: MouseScrollDelta) {
+        // Put both kinds of scrolling in terms of lines so a mouse wheel notch
+        // and the equivalent trackpad swipe zoom by about the same amount.
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, n) => n as f64,
+            MouseScrollDelta::PixelDelta(PhysicalPosition { y, .. }) => y / PIXELS_PER_LINE,
+        };
+        // Go through generations one line at a time while paused with the
+        // modifier held, forwards when scrolling up and back down
+        if !self.is_playing()
+            && self
+                .scroll_step_modifier
+                .is_some_and(|m| m.is_held(self.modifiers))
+        {
+            self.scroll_steps += lines;
+            while self.scroll_steps >= 1.0 {
+                self.scroll_steps -= 1.0;
+                self.step();
+                self.finish_pending_step();
+            }
+            while self.scroll_steps <= -1.0 {
+                self.scroll_steps += 1.0;
+                self.step_back();
+            }
+            return;
+        }
+        self.scroll_steps = 0.0;
+        // Zooming exponentially makes scrolling in and back out by the same
+        // amount end up at the same size.
+        let factor = (lines * ZOOM_PER_LINE * self.scroll_sensitivity).exp();
+        let anchor = self.mouse_position.unwrap_or_else(|| self.window_center());
+        self.zoom(factor, anchor);
+    }
 
-        let prev_size = self.grid_size;
+    /// Move the view along with something dragged `pix_diff` pixels across
+    /// the window, so the same spot on the board stays under it
+    fn pan_by_pixels(&mut self, pix_diff: Vector2<f64>) {
         let size = self.window.inner_size();
-        let change = size.height as f64
-            * 0.000005
-            * match delta {
-                MouseScrollDelta::LineDelta(_, n) => n as f64,
-                MouseScrollDelta::PixelDelta(PhysicalPosition { y, .. }) => y * PIXEL_MUL
-            };
+        let w = size.width as f64;
+        let h = size.height as f64;
+        let ratio = w / h;
 
-        self.grid_size = (self.grid_size as f64 * (1.0 + change)).clamp(0.005, 1.0) as f32;
-        self.changes.grid_size = Some(self.grid_size);
+        let norm_diff = Vector2::<f64>::scale(pix_diff, Vector2::new(w.recip(), h.recip()));
+        let diff = Vector2::<f64>::scale(norm_diff, Vector2::new(ratio, 1.0));
 
-        let center = if let Some(v) = self.mouse_position {
-            let aspect_ratio = size.width as f64 / size.height as f64;
-            let shift_amount = (size.width as f64 - size.height as f64) / 2.0;
-            let x_shifted = v.x - shift_amount;
-            let x_scaled = x_shifted * aspect_ratio;
-            Vector2::<f64>::scale(
-                Vector2::new(x_scaled, v.y),
-                Vector2::new((size.width as f64).recip(), (size.height as f64).recip()),
-            ) + self.pan_position
-        } else {
-            Vector2::<f64>::new(0.0, 0.0)
-        };
+        self.pan_position -= diff;
+        self.changes.offset = Some(self.pan_position);
+    }
+
+    /// Handle a finger on a touchscreen. Dragging one finger pans, pinching
+    /// two zooms around the middle of them, and a tap acts like a left
+    /// click.
+    fn handle_touch(&mut self, touch: &Touch) {
+        let position = Vector2::new(touch.location.x, touch.location.y);
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.insert(touch.id, position);
+                self.tap = (self.touches.len() == 1).then_some((touch.id, position));
+            }
+            TouchPhase::Moved => {
+                let Some(prev) = self.touches.insert(touch.id, position) else {
+                    return;
+                };
+                if let Some((_, start)) = self.tap
+                    && Vector2::<f64>::distance(start, position) > TAP_SLOP
+                {
+                    self.tap = None;
+                }
+                // Only the first two fingers do anything
+                let mut others = self.touches.iter().filter(|(id, _)| **id != touch.id);
+                match (others.next(), others.next()) {
+                    (None, _) => self.pan_by_pixels(position - prev),
+                    (Some((_, &other)), None) => {
+                        // Pan with the middle of the fingers, then zoom by how
+                        // much further apart they got
+                        let prev_middle = Vector2::<f64>::lerp(prev, other, 0.5);
+                        let middle = Vector2::<f64>::lerp(position, other, 0.5);
+                        self.pan_by_pixels(middle - prev_middle);
+                        let prev_spread = Vector2::<f64>::distance(prev, other);
+                        if prev_spread > 0.0 {
+                            let factor = Vector2::<f64>::distance(position, other) / prev_spread;
+                            self.zoom(factor, middle);
+                        }
+                    }
+                    (Some(_), Some(_)) => (),
+                }
+                self.update_stamp_preview();
+            }
+            TouchPhase::Ended => {
+                self.touches.remove(&touch.id);
+                if let Some((id, _)) = self.tap
+                    && id == touch.id
+                {
+                    self.tap = None;
+                    self.handle_click(position);
+                    // A tap doesn't start a stroke, since lifting the finger
+                    // already ended it
+                    self.paint_stroke = None;
+                }
+            }
+            TouchPhase::Cancelled => {
+                self.touches.remove(&touch.id);
+                self.tap = self.tap.filter(|(id, _)| *id != touch.id);
+            }
+        }
+    }
+
+    /// Handle a pinch on a trackpad, which zooms by how much the fingers
+    /// spread apart around the cursor. This only comes on platforms that
+    /// report trackpad gestures; elsewhere a pinch arrives as scrolling and
+    /// goes through `handle_scroll`.
+    fn handle_magnify(&mut self, delta: f64) {
+        // The delta is how much bigger things should get, as a fraction of
+        // their size, so it can be used for the factor without guessing at
+        // how it compares to scrolling
+        let factor = delta.exp();
+        let anchor = self.mouse_position.unwrap_or_else(|| self.window_center());
+        self.zoom(factor, anchor);
+    }
 
-        let change = (self.grid_size / prev_size) as f64 - 1.0;
+    /// The middle of the window, in pixels
+    fn window_center(&self) -> Vector2<f64> {
+        let size = self.window.inner_size();
+        Vector2::new(size.width as f64 / 2.0, size.height as f64 / 2.0)
+    }
 
-        // Technically the math works out to the opposite of this, but this is
-        // what works with the current coordinate system.
-        let extra_offset = center * change;
+    /// Zoom in by `factor`, or out if it's less than 1, keeping the point at
+    /// `anchor` in the window over the same spot on the board.
+    fn zoom(&mut self, factor: f64, anchor: Vector2<f64>) {
+        let prev_size = self.grid_size;
+        self.grid_size = (self.grid_size as f64 * factor)
+            .clamp(MIN_GRID_SIZE as f64, MAX_GRID_SIZE as f64) as f32;
+        self.changes.grid_size = Some(self.grid_size);
 
-        // extra_offset is actually the inverse of the way pan_position works
-        self.pan_position += extra_offset;
+        self.pan_position = zoomed_pan(
+            self.window.inner_size(),
+            anchor,
+            self.pan_position,
+            prev_size,
+            self.grid_size,
+        );
         self.changes.offset = Some(self.pan_position);
-        self.changes.cells = Some(self.get_cells());
+        self.cell_changes.refresh_all();
+        self.update_stamp_preview();
     }
 
     pub fn handle_window_event(&mut self, event: &WindowEvent) {
         let c_char = SmolStr::new_static("c");
+        let v_char = SmolStr::new_static("v");
 
         match event {
-            // Clear the screen when "c" pressed
+            // Copy the pattern with Ctrl+C and paste it with Ctrl+V. These
+            // come first so that Ctrl+C doesn't clear the board.
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -183,35 +846,66 @@ impl GameState {
                         ..
                     },
                 ..
-            } if *keystr == c_char => {
-                self.clear();
+            } if self.modifiers.control_key() && (*keystr == c_char || *keystr == v_char) => {
+                if *keystr == c_char {
+                    self.copy_pattern();
+                } else {
+                    self.paste_pattern();
+                }
             }
 
-            // Speed up
+            // Bind the action being rebound to the next key pressed. Escape
+            // leaves it bound to the keys it had, and so do the panning keys,
+            // which would otherwise pan and do the action both.
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
-                        logical_key: Key::Named(NamedKey::ArrowUp),
+                        logical_key,
+                        physical_key,
+                        repeat: false,
                         state: ElementState::Pressed,
                         ..
                     },
                 ..
-            } => self.interval = self.interval.div_f32(INTERVAL_P),
+            } if let Some(action) = self.rebinding => {
+                self.rebinding = None;
+                if pans(physical_key) {
+                    self.show_toast("The WASD keys are kept for panning".to_string());
+                } else if *logical_key != Key::Named(NamedKey::Escape)
+                    && let Some(name) = keys::key_name(logical_key)
+                {
+                    self.bind_key(action, name);
+                }
+            }
 
-            // Slow down
+            // Everything that can be rebound. Only fast forwarding cares when
+            // its key is let go. The panning keys go by where they are, so on
+            // some layouts they type a key that's bound, and then they pan.
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
-                        logical_key: Key::Named(NamedKey::ArrowDown),
-                        state: ElementState::Pressed,
+                        logical_key,
+                        physical_key,
+                        repeat,
+                        state,
                         ..
                     },
                 ..
-            } => self.interval = self.interval.mul_f32(INTERVAL_P),
+            } if !pans(physical_key)
+                && let Some(action) = self.key_bindings.action(logical_key)
+                && (*state == ElementState::Pressed || action == Action::FastForward)
+                && (!*repeat || action.repeats()) =>
+            {
+                self.handle_action(action, *state == ElementState::Pressed);
+            }
+
+            WindowEvent::Focused(focused) => self.handle_focus(*focused),
 
             // Forget the cursor position if it left the window
             WindowEvent::CursorLeft { .. } => {
                 self.mouse_position = None;
+                self.paint_stroke = None;
+                self.update_stamp_preview();
                 //self.drag_state = DragState::NotDragging;
             }
 
@@ -220,6 +914,11 @@ impl GameState {
                 self.handle_scroll(*delta);
             }
 
+            // Zooming with a pinch on a trackpad
+            WindowEvent::TouchpadMagnify { delta, .. } => {
+                self.handle_magnify(*delta);
+            }
+
             // Track the cursor
             //
             // Getting the location of the cursor in the window can only be done
@@ -229,31 +928,24 @@ impl GameState {
             // This block also handles panning
             WindowEvent::CursorMoved { position, .. } => {
                 self.mouse_position = Some([position.x, position.y].into());
+                self.continue_paint_stroke();
                 if let DragState::Dragging { prev_pos } = self.drag_state {
                     let pos = self.mouse_position.unwrap();
-                    let size = self.window.inner_size();
-                    let w = size.width as f64;
-                    let h = size.height as f64;
-                    let ratio = w / h;
-
-                    let pix_diff = pos - prev_pos;
-                    let norm_diff =
-                        Vector2::<f64>::scale(pix_diff, Vector2::new(w.recip(), h.recip()));
-                    let raw_diff = Vector2::<f64>::scale(norm_diff, Vector2::new(ratio, 1.0));
-                    let diff = raw_diff; // self.grid_size as f64;
-
-                    self.pan_position -= diff;
+                    self.pan_by_pixels(pos - prev_pos);
                     self.drag_state = DragState::Dragging { prev_pos: pos };
-                    self.changes.offset = Some(self.pan_position);
                 }
+                self.update_stamp_preview();
             }
 
+            // Pan, zoom and toggle cells with fingers
+            WindowEvent::Touch(touch) => self.handle_touch(touch),
+
             // Start panning
             WindowEvent::MouseInput {
-                button: MouseButton::Right,
+                button,
                 state: ElementState::Pressed,
                 ..
-            } => {
+            } if self.pan_button.pans_with(*button) => {
                 if let Some(p) = self.mouse_position {
                     self.drag_state = DragState::Dragging { prev_pos: p };
                 }
@@ -261,37 +953,34 @@ impl GameState {
 
             // Stop panning
             WindowEvent::MouseInput {
-                button: MouseButton::Right,
+                button,
                 state: ElementState::Released,
                 ..
-            } => {
+            } if self.pan_button.pans_with(*button) => {
                 self.drag_state = DragState::NotDragging;
             }
 
-            // Toggle autoplay with space
+            // Pan with WASD, by where the keys are rather than what they type
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
-                        physical_key: PhysicalKey::Code(KeyCode::Space),
-                        state: ElementState::Pressed,
+                        physical_key: PhysicalKey::Code(code),
+                        state,
                         ..
                     },
                 ..
-            } => {
-                self.toggle_playing();
-            }
+            } if pan_key_direction(*code).is_some() => match state {
+                ElementState::Pressed => {
+                    self.held_pan_keys.insert(*code);
+                    self.pan_with_keys();
+                }
+                ElementState::Released => {
+                    self.held_pan_keys.remove(code);
+                }
+            },
 
-            // Individual step with Tab
-            WindowEvent::KeyboardInput {
-                event:
-                    KeyEvent {
-                        logical_key: Key::Named(NamedKey::Tab),
-                        state: ElementState::Pressed,
-                        ..
-                    },
-                ..
-            } => {
-                self.step();
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
             }
 
             // Cell state toggling with LMB
@@ -300,129 +989,1745 @@ impl GameState {
                 button: MouseButton::Left,
                 ..
             } if let Some(mouse_position) = self.mouse_position => {
-                self.handle_left(mouse_position);
+                self.handle_click(mouse_position);
             }
-            _ => (),
-        };
-    }
 
-    /// Clear the screen
-    fn clear_action(&mut self) {
-        self.living_cells.clear();
-        self.step_count = 0;
-        self.living_count_history = vec![0];
-        self.living_cell_count = 0;
+            // Stop painting cells
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.paint_stroke = None;
+            }
 
-        self.changes.cells = Some(Vec::new());
-        self.toggle_record.clear();
+            _ => (),
+        };
     }
 
-    /// Resolve the input queue (`self.input_queue`)
-    fn resolve_queue(&mut self) {
-        while let Some(i) = self.input_queue.pop_front() {
-            match i {
-                QueueAction::Clear => {
-                    self.clear_action();
+    /// Do what a key bound to `action` does. `pressed` is whether the key went
+    /// down rather than up.
+    fn handle_action(&mut self, action: Action, pressed: bool) {
+        match action {
+            Action::Play => self.toggle_playing(),
+            Action::Step if self.modifiers.shift_key() => self.step_back(),
+            Action::Step => self.step_once(),
+            Action::Clear => self.request_clear(),
+            Action::SpeedUp => self.interval = self.interval.div_f32(INTERVAL_P),
+            Action::SlowDown => self.interval = self.interval.mul_f32(INTERVAL_P),
+            Action::FastForward => self.set_turbo(pressed),
+            Action::StepUntilChange => {
+                if let Some(mouse_position) = self.mouse_position {
+                    let size = self.window.inner_size();
+                    let cell_pos =
+                        find_cell_num(size, mouse_position, self.pan_position, self.grid_size);
+                    self.step_until_cell_changes(cell_pos, MAX_STEPS_UNTIL_CHANGE);
                 }
-                QueueAction::Toggle(cell) => {
-                    self.left_action(cell);
+            }
+            Action::ToggleFitZoom => self.toggle_fit_zoom(),
+            Action::Center => self.center_on_centroid(),
+            Action::FitView => self.fit_to_population(),
+            Action::ResetView => self.reset_view(),
+            Action::Rotate => {
+                if self.stamp.is_some() {
+                    self.rotate_stamp(1);
+                } else {
+                    self.rotate_selection(1);
                 }
-                #[cfg(feature = "saving")]
-                QueueAction::Load(save) => {
-                    self.load_action(save);
+            }
+            Action::FlipX => self.flip_selection(Axis::X),
+            Action::FlipY => self.flip_selection(Axis::Y),
+            Action::ZoomIn => self.zoom(ZOOM_PER_KEY.exp(), self.window_center()),
+            Action::ZoomOut => self.zoom((-ZOOM_PER_KEY).exp(), self.window_center()),
+            Action::ToggleTool => self.tool = Tool::Toggle,
+            Action::DrawTool => self.tool = Tool::Draw,
+            Action::EraseTool => self.tool = Tool::Erase,
+            // A new seed each time, so that it doesn't fill the same way
+            // twice
+            #[cfg(feature = "random")]
+            Action::RandomFill => {
+                let seed = self.random_seed;
+                self.random_seed = self.random_seed.wrapping_add(1);
+                log::info!("Filling the view with random cells from seed {seed}");
+                self.random_fill(RANDOM_FILL_DENSITY, seed);
+            }
+            #[cfg(not(feature = "random"))]
+            Action::RandomFill => (),
+            Action::NextTheme => self.cycle_theme(),
+            // Quitting is up to the window, which checks for a stamp first
+            Action::Exit => {
+                if self.stamp.is_some() {
+                    self.cancel_stamp();
                 }
             }
+            // These are up to the window and renderer
+            Action::Fullscreen | Action::Screenshot | Action::Record => (),
         }
     }
 
-    /// Handle a left click by toggling the particular cell. This should not be
-    /// called if the click was on the GUI.
-    fn left_action(&mut self, cell_pos: Vector2<i32>) {
-        if let Some(i) = self.living_cells.get(&cell_pos).cloned() {
-            self.living_cells.remove(&i);
-        } else {
-            self.living_cells.insert(cell_pos);
-        }
+    pub fn key_bindings(&self) -> &keys::KeyBindings {
+        &self.key_bindings
+    }
+
+    /// Bind an action to a key, taking the key away from whatever it did
+    /// before
+    pub fn bind_key(&mut self, action: Action, key: String) {
+        self.key_bindings.bind(action, key);
+        #[cfg(feature = "saving")]
+        self.store_key_bindings();
+    }
+
+    /// Leave an action without a key
+    pub fn unbind_key(&mut self, action: Action) {
+        self.key_bindings.unbind(action);
+        #[cfg(feature = "saving")]
+        self.store_key_bindings();
+    }
 
-        let cells = self.get_cells();
-        self.toggle_record.push(self.step_count);
-        self.changes.cells = Some(cells);
+    /// Put every key back to what it does by default
+    pub fn reset_key_bindings(&mut self) {
+        self.key_bindings = keys::KeyBindings::default();
+        #[cfg(feature = "saving")]
+        self.store_key_bindings();
     }
 
+    /// Keep the key bindings for the next time the game is started. They're
+    /// kept apart from the saves, so that loading a save doesn't change them.
     #[cfg(feature = "saving")]
-    fn load_action(&mut self, save: SaveGame) {
-        self.clear_action();
-        self.living_cells = save.living_cells();
-        self.pan_position = save.pan_position();
-        self.grid_size = save.grid_size();
+    fn store_key_bindings(&mut self) {
+        if let Err(e) = self.key_bindings.store(&mut *self.settings_storage) {
+            log::error!("Failed to store the key bindings: {e}");
+        }
+    }
 
-        self.changes.cells = Some(self.get_cells());
-        self.changes.grid_size = Some(self.grid_size);
-        self.changes.offset = Some(self.pan_position);
+    /// Bind `action` to the next key pressed, or stop waiting for one with
+    /// `None`
+    pub fn set_rebinding(&mut self, action: Option<Action>) {
+        self.rebinding = action;
     }
-}
 
-#[cfg(feature = "native_threads")]
-impl GameState {
-    pub fn new(window: Arc<Window>, grid_size: f32) -> Self {
-        use StepThreadNotification as STN;
-        let (tx, rx) = mpsc::channel();
-        let condvar = Condvar::new();
-        let notification = Mutex::new(StepThreadNotification::Waiting);
-        let shared_thread_data = Arc::new(SharedThreadData {
-            condvar,
-            notification,
-            computing: AtomicBool::new(false),
-        });
-        let join_handle = {
-            let thread_data = Arc::clone(&shared_thread_data);
-            std::thread::spawn(move || loop {
-                let cvar = &thread_data.condvar;
-                let lock = &thread_data.notification;
-                let data_guard = lock.lock().unwrap();
-                let mut data_guard = cvar.wait(data_guard).unwrap();
-                match &*data_guard {
-                    STN::Exit => break,
-                    STN::Waiting => (),
-                    STN::Compute(data) => {
-                        thread_data
-                            .computing
-                            .store(true, sync::atomic::Ordering::Relaxed);
-                        tx.send(compute_step(data)).unwrap();
-                        *data_guard = STN::Waiting;
-                    }
+    /// The action waiting to be bound to the next key pressed, if any
+    pub fn get_rebinding(&self) -> Option<Action> {
+        self.rebinding
+    }
+
+    /// Handle a left click. With Ctrl held it puts up or takes down a wall,
+    /// and while a pattern is being stamped it places the pattern. Otherwise
+    /// the cell and its mirror images are changed with the current tool,
+    /// unless it's the second click of a double-click. That click toggles the
+    /// zoom instead, and puts back the cells the first click changed.
+    fn handle_click(&mut self, mouse_position: Vector2<f64>) {
+        let size = self.window.inner_size();
+        let cell_pos = find_cell_num(size, mouse_position, self.pan_position, self.grid_size);
+        // Ctrl+click puts up and takes down walls
+        if self.modifiers.control_key() {
+            self.toggle_wall(cell_pos);
+            return;
+        }
+        if let Some(stamp) = self.stamp.take() {
+            self.set_cells(stamp.cells_at(cell_pos), true);
+            // The preview has to be taken down even if no cells changed
+            self.cell_changes.refresh_all();
+            return;
+        }
+        let cells: Vec<_> = std::iter::once(cell_pos)
+            .chain(self.mirror_images(cell_pos))
+            .collect();
+        match click_cells(
+            &mut self.last_click,
+            &self.sim,
+            self.tool,
+            &cells,
+            Instant::now(),
+            self.double_click_time,
+        ) {
+            ClickOutcome::Single { alive } => {
+                // Dragging from here paints cells the way this one is being
+                // changed
+                self.paint_stroke = Some(PaintStroke {
+                    alive,
+                    last_cell: cell_pos,
+                });
+                for cell in cells {
+                    self.set_cell(cell, alive);
                 }
-            })
+            }
+            ClickOutcome::Double { before } => {
+                for (cell, alive) in before {
+                    self.set_cell(cell, alive);
+                }
+                self.toggle_fit_zoom();
+            }
+        }
+    }
+
+    /// Paint every cell between the last one painted and the one under the
+    /// cursor, if a stroke is going on. Every cell on the line between them is
+    /// painted, so moving the mouse quickly doesn't leave gaps.
+    fn continue_paint_stroke(&mut self) {
+        let (Some(stroke), Some(mouse_position)) = (self.paint_stroke, self.mouse_position) else {
+            return;
         };
+        let size = self.window.inner_size();
+        let cell_pos = find_cell_num(size, mouse_position, self.pan_position, self.grid_size);
+        if cell_pos == stroke.last_cell {
+            return;
+        }
+        for cell in cells_on_line(stroke.last_cell, cell_pos).into_iter().skip(1) {
+            self.set_cell(cell, stroke.alive);
+            for image in self.mirror_images(cell) {
+                self.set_cell(image, stroke.alive);
+            }
+        }
+        self.paint_stroke = Some(PaintStroke {
+            last_cell: cell_pos,
+            ..stroke
+        });
+    }
+
+    /// Get a pattern in RLE format ready to be stamped onto the board by the
+    /// next left click, centered on the cell clicked. Until then, it's shown
+    /// under the cursor. Unlike loading a pattern, the cells already on the
+    /// board are kept and the rule isn't changed, and any walls in the pattern
+    /// are left out.
+    pub fn start_stamp(&mut self, rle: &str) -> anyhow::Result<()> {
+        let pattern = patterns::parse_rle(rle)?;
+        self.stamp = Some(Stamp {
+            pattern,
+            cell: None,
+        });
+        self.update_stamp_preview();
+        Ok(())
+    }
 
-        let local_thread_data = LocalThreadData { join_handle, rx };
+    /// Copy the living cells as RLE, to be pasted with `paste_pattern`.
+    /// There's no way to select part of the board yet, so this is the whole
+    /// pattern.
+    pub fn copy_pattern(&mut self) {
+        if let Err(e) = self.clipboard.set_text(self.export_rle()) {
+            self.show_toast(format!("Couldn't copy the pattern: {e}"));
+        }
+    }
 
-        let thread_data = ThreadData {
-            local: local_thread_data,
-            shared: shared_thread_data,
+    /// Stamp the pattern last copied onto the board, like `start_stamp`. If
+    /// there isn't one or it can't be read, the board is left alone and the
+    /// player is told why.
+    pub fn paste_pattern(&mut self) {
+        let rle = match self.clipboard.get_text() {
+            Ok(Some(rle)) => rle,
+            Ok(None) => {
+                self.show_toast("Nothing has been copied yet".to_string());
+                return;
+            }
+            Err(e) => {
+                self.show_toast(format!("Couldn't read the clipboard: {e}"));
+                return;
+            }
         };
+        if let Err(e) = self.start_stamp(&rle) {
+            self.show_toast(format!("The clipboard doesn't hold a pattern: {e}"));
+        }
+    }
 
+    /// Show a message to the player for a few seconds
+    pub fn show_toast(&mut self, message: String) {
+        log::warn!("{message}");
+        self.toast = Some((message, Instant::now()));
+    }
+
+    /// The message being shown to the player, if there is one
+    pub fn get_toast(&self) -> Option<&str> {
+        self.toast
+            .as_ref()
+            .filter(|(_, shown)| shown.elapsed() < TOAST_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    pub fn is_stamping(&self) -> bool {
+        self.stamp.is_some()
+    }
+
+    /// Put away the pattern waiting to be stamped without placing it
+    pub fn cancel_stamp(&mut self) {
+        if self.stamp.take().is_some() {
+            self.cell_changes.refresh_all();
+        }
+    }
+
+    /// Rotate the pattern waiting to be stamped clockwise by some number of
+    /// quarter turns, so it's placed turned
+    pub fn rotate_stamp(&mut self, quarter_turns: i32) {
+        if let Some(stamp) = &mut self.stamp {
+            for _ in 0..quarter_turns.rem_euclid(4) {
+                stamp.rotate();
+            }
+            self.cell_changes.refresh_all();
+        }
+    }
+
+    /// Move the preview of the pattern being stamped to the cell under the
+    /// cursor, if that changed.
+    fn update_stamp_preview(&mut self) {
+        let size = self.window.inner_size();
+        let cell = self
+            .mouse_position
+            .map(|p| find_cell_num(size, p, self.pan_position, self.grid_size));
+        if let Some(stamp) = &mut self.stamp
+            && stamp.cell != cell
+        {
+            stamp.cell = cell;
+            self.cell_changes.refresh_all();
+        }
+    }
+
+    pub fn get_tool(&self) -> Tool {
+        self.tool
+    }
+
+    /// Choose what clicking and dragging does to cells
+    pub fn set_tool(&mut self, to: Tool) {
+        self.tool = to;
+    }
+
+    pub fn get_symmetry(&self) -> Symmetry {
+        self.symmetry
+    }
+
+    /// Choose which mirror images of the cells clicked on and painted are
+    /// changed along with them
+    pub fn set_symmetry(&mut self, to: Symmetry) {
+        self.symmetry = to;
+    }
+
+    pub fn get_pan_button(&self) -> PanButton {
+        self.pan_button
+    }
+
+    /// Choose which mouse buttons drag the view around
+    pub fn set_pan_button(&mut self, to: PanButton) {
+        self.pan_button = to;
+        // A drag with a button that doesn't pan anymore couldn't be stopped
+        self.drag_state = DragState::NotDragging;
+    }
+
+    /// The mirror images of a cell under the symmetry mode, through the cell
+    /// in the middle of the view. The cell itself isn't included, even if it's
+    /// on an axis.
+    fn mirror_images(&self, cell: Vector2<i32>) -> Vec<Vector2<i32>> {
+        let center = self.view_center_cell();
+        let flip_x = Vector2::new(2 * center.x - cell.x, cell.y);
+        let flip_y = Vector2::new(cell.x, 2 * center.y - cell.y);
+        let candidates = match self.symmetry {
+            Symmetry::None => vec![],
+            Symmetry::X => vec![flip_x],
+            Symmetry::Y => vec![flip_y],
+            Symmetry::Both => vec![flip_x, flip_y, center * 2 - cell],
+        };
+        let mut images = Vec::with_capacity(candidates.len());
+        for image in candidates {
+            if image != cell && !images.contains(&image) {
+                images.push(image);
+            }
+        }
+        images
+    }
+
+    /// Make a cell alive or dead, whatever state it's in now
+    pub fn set_cell(&mut self, cell: Vector2<i32>, alive: bool) {
+        if self.is_computing() {
+            self.input_queue.push_back(QueueAction::Set(cell, alive));
+        } else {
+            self.set_action(cell, alive);
+        }
+    }
+
+    /// Make every cell in `cells` alive or dead, whatever state they're in
+    /// now. Unlike calling `set_cell` for each of them, this counts as a
+    /// single edit, and the renderer is sent the whole board once instead of
+    /// every cell separately. Cells off a bounded board are left alone.
+    pub fn set_cells(&mut self, cells: impl IntoIterator<Item = Vector2<i32>>, alive: bool) {
+        self.finish_pending_step();
+        let mut changed = false;
+        for cell in cells {
+            if !self.sim.boundary.contains(cell)
+                || self.sim.is_wall(cell)
+                || self.sim.is_alive(cell) == alive
+            {
+                continue;
+            }
+            self.sim.toggle(cell);
+            if let Some(payloads) = &mut self.payloads {
+                payloads.set(cell, alive);
+            }
+            self.ages.set(cell, alive);
+            changed = true;
+        }
+        if !changed {
+            return;
+        }
+        self.living_cell_count = self.sim.living_cells.len();
+        self.reset_detection();
+        self.toggle_record.push(self.sim.step_count);
+        self.cell_changes.refresh_all();
+    }
+
+    /// Put a wall on a cell or take one away. Walls never change, but count
+    /// as living neighbors of the cells around them. A living cell where a
+    /// wall is put is removed.
+    pub fn toggle_wall(&mut self, cell: Vector2<i32>) {
+        // The step being computed doesn't know about the change
+        self.finish_pending_step();
+        let was_alive = self.sim.is_alive(cell);
+        self.sim.toggle_wall(cell);
+        if was_alive {
+            if let Some(payloads) = &mut self.payloads {
+                payloads.set(cell, false);
+            }
+            self.ages.set(cell, false);
+            self.living_cell_count = self.sim.living_cells.len();
+        }
+        self.reset_detection();
+        self.toggle_record.push(self.sim.step_count);
+        self.cell_changes.refresh_all();
+    }
+
+    /// The coordinates of every wall, in no particular order
+    pub fn walls(&self) -> impl Iterator<Item = Vector2<i32>> + '_ {
+        self.sim.walls()
+    }
+
+    fn set_action(&mut self, cell: Vector2<i32>, alive: bool) {
+        if self.sim.is_alive(cell) != alive {
+            self.left_action(cell);
+        }
+    }
+
+    /// Clear the board, or if that has to be confirmed, ask for it to be.
+    /// Nothing is cleared until `confirm_clear` is called.
+    pub fn request_clear(&mut self) {
+        if self.confirm_clear {
+            self.pending_clear = true;
+        } else {
+            self.clear();
+        }
+    }
+
+    /// Whether clearing the board is waiting to be confirmed
+    pub fn is_clear_pending(&self) -> bool {
+        self.pending_clear
+    }
+
+    /// Clear the board if that was waiting to be confirmed
+    pub fn confirm_clear(&mut self) {
+        if std::mem::take(&mut self.pending_clear) {
+            self.clear();
+        }
+    }
+
+    /// Leave the board alone after all
+    pub fn cancel_clear(&mut self) {
+        self.pending_clear = false;
+    }
+
+    pub fn get_confirm_clear(&self) -> bool {
+        self.confirm_clear
+    }
+
+    /// Choose whether clearing the board has to be confirmed first, or
+    /// happens as soon as it's asked for.
+    pub fn set_confirm_clear(&mut self, to: bool) {
+        self.confirm_clear = to;
+    }
+
+    pub fn theme_name(&self) -> &'static str {
+        self.theme
+    }
+
+    /// Switch to the color theme with this name. Names that aren't one of
+    /// `theme::THEMES` are ignored.
+    pub fn set_theme(&mut self, name: &str) {
+        match theme::find(name) {
+            Some(theme) => {
+                self.theme = theme.name;
+                #[cfg(feature = "saving")]
+                {
+                    self.unsaved_changes = true;
+                }
+            }
+            None => log::warn!("There's no theme called {name}"),
+        }
+    }
+
+    /// Switch to the next color theme, going back to the first after the last
+    pub fn cycle_theme(&mut self) {
+        let current = theme::THEMES
+            .iter()
+            .position(|t| t.name == self.theme)
+            .unwrap_or(0);
+        let next = &theme::THEMES[(current + 1) % theme::THEMES.len()];
+        self.set_theme(next.name);
+    }
+
+    pub fn get_double_click_time(&self) -> Duration {
+        self.double_click_time
+    }
+
+    /// Set the maximum time between the clicks of a double-click.
+    pub fn set_double_click_time(&mut self, to: Duration) {
+        self.double_click_time = to;
+    }
+
+    pub fn get_animate_zoom(&self) -> bool {
+        self.animate_zoom
+    }
+
+    /// Choose whether toggling the fit zoom glides between the views or jumps
+    /// straight to the other one
+    pub fn set_animate_zoom(&mut self, to: bool) {
+        self.animate_zoom = to;
+    }
+
+    pub fn get_scroll_sensitivity(&self) -> f64 {
+        self.scroll_sensitivity
+    }
+
+    /// Set the multiplier for how fast scrolling zooms.
+    pub fn set_scroll_sensitivity(&mut self, to: f64) {
+        self.scroll_sensitivity = to;
+    }
+
+    pub fn get_scroll_step_modifier(&self) -> Option<StepModifier> {
+        self.scroll_step_modifier
+    }
+
+    /// Choose the modifier that makes scrolling step through generations
+    /// while paused, or let scrolling always zoom with `None`
+    pub fn set_scroll_step_modifier(&mut self, to: Option<StepModifier>) {
+        self.scroll_step_modifier = to;
+    }
+
+    /// Switch between the current view and one that fits the whole pattern.
+    /// Calling this again goes back to the view from before. Does nothing if
+    /// there's no pattern to fit to.
+    pub fn toggle_fit_zoom(&mut self) {
+        // Toggling again partway through a glide turns around, and going back
+        // after that returns to where the glide was headed
+        let current = self
+            .camera_transition
+            .take()
+            .map_or_else(|| self.camera(), |t| t.to);
+        if let Some(camera) = self.previous_camera.take() {
+            self.glide_camera(camera);
+        } else if let Some(fit) = self.fit_camera() {
+            self.previous_camera = Some(current);
+            self.glide_camera(fit);
+        }
+    }
+
+    /// Move the view to `to`, gliding there over the next frames if zoom
+    /// toggles are animated
+    fn glide_camera(&mut self, to: Camera) {
+        if self.animate_zoom {
+            self.camera_transition = Some(CameraTransition {
+                from: self.camera(),
+                to,
+                shown: self.camera(),
+                started: Instant::now(),
+            });
+        } else {
+            self.set_camera(to);
+        }
+    }
+
+    /// Move the view along the glide that's going on, if any. Panning or
+    /// zooming some other way in the meantime stops it where it is.
+    fn update_camera_transition(&mut self) {
+        let Some(transition) = self.camera_transition else {
+            return;
+        };
+        if self.camera() != transition.shown {
+            self.camera_transition = None;
+            return;
+        }
+        let t = transition.started.elapsed().as_secs_f64() / ZOOM_TOGGLE_TIME.as_secs_f64();
+        let camera = if t >= 1.0 {
+            self.camera_transition = None;
+            transition.to
+        } else {
+            transition.at(t)
+        };
+        self.set_camera(camera);
+        let shown = self.camera();
+        if let Some(transition) = &mut self.camera_transition {
+            transition.shown = shown;
+        }
+    }
+
+    /// Zoom and pan so that the whole pattern fills most of the window. With
+    /// no living cells, the view goes back to the one the game started with.
+    pub fn fit_to_population(&mut self) {
+        match self.fit_camera() {
+            Some(camera) => {
+                self.previous_camera = None;
+                self.set_camera(camera);
+            }
+            None => self.reset_view(),
+        }
+    }
+
+    /// Go back to the view the game started with, at the origin and the
+    /// starting zoom. The cells are left alone.
+    pub fn reset_view(&mut self) {
+        self.previous_camera = None;
+        self.set_camera(self.default_camera());
+    }
+
+    /// The view the game starts with
+    fn default_camera(&self) -> Camera {
+        Camera {
+            pan_position: [0.0, 0.0].into(),
+            grid_size: self.initial_grid_size,
+        }
+    }
+
+    fn camera(&self) -> Camera {
+        Camera {
+            pan_position: self.pan_position,
+            grid_size: self.grid_size,
+        }
+    }
+
+    /// Move the view, passing the changes on to the renderer.
+    fn set_camera(&mut self, camera: Camera) {
+        self.pan_position = camera.pan_position;
+        self.grid_size = camera.grid_size;
+        self.changes.offset = Some(self.pan_position);
+        self.changes.grid_size = Some(self.grid_size);
+        self.cell_changes.refresh_all();
+    }
+
+    /// Find the view that fits the bounding box of the living cells into
+    /// `FIT_FRACTION` of the window, or `None` if there are no living cells.
+    fn fit_camera(&self) -> Option<Camera> {
+        let (min, max) = patterns::bounding_box(&self.sim.living_cells)?;
+        let width = (max.x - min.x + 1) as f64;
+        let height = (max.y - min.y + 1) as f64;
+
+        let size = self.window.inner_size();
+        let aspect_ratio = size.width as f64 / size.height as f64;
+        let grid_size = (FIT_FRACTION / height)
+            .min(FIT_FRACTION * aspect_ratio / width)
+            .clamp(MIN_GRID_SIZE as f64, MAX_GRID_SIZE as f64);
+
+        let center = Vector2::new(
+            (min.x + max.x + 1) as f64 / 2.0,
+            (min.y + max.y + 1) as f64 / 2.0,
+        );
+        Some(Camera {
+            pan_position: pan_centered_on(center, grid_size as f32),
+            grid_size: grid_size as f32,
+        })
+    }
+
+    /// Move the view so that the mean position of the living cells is in the
+    /// center of the window, without changing the zoom. Does nothing if there
+    /// are no living cells.
+    pub fn center_on_centroid(&mut self) {
+        let Some(centroid) = stats::center_of_mass(&self.sim.living_cells) else {
+            return;
+        };
+        self.pan_position = pan_centered_on(centroid, self.grid_size);
+        self.changes.offset = Some(self.pan_position);
+    }
+
+    /// Move the view so that the cell at (`x`, `y`) is in the middle of the
+    /// window, keeping the zoom as it is.
+    pub fn goto(&mut self, x: i32, y: i32) {
+        let center = Vector2::new(x as f64 + 0.5, y as f64 + 0.5);
+        self.pan_position = pan_centered_on(center, self.grid_size);
+        self.changes.offset = Some(self.pan_position);
+        self.update_stamp_preview();
+    }
+
+    /// The cell in the center of the window
+    fn view_center_cell(&self) -> Vector2<i32> {
+        let center = (self.pan_position + Vector2::new(0.5, 0.5)) / self.grid_size as f64;
+        Vector2::new(center.x.floor() as i32, center.y.floor() as i32)
+    }
+
+    /// Replace the board with a pattern, centered on the view. The rule is
+    /// changed to the pattern's if it has one.
+    fn place_pattern(&mut self, pattern: patterns::Pattern) {
+        let corner = self.view_center_cell() - Vector2::new(pattern.width / 2, pattern.height / 2);
+        let cells: LivingList = pattern.cells.iter().map(|c| *c + corner).collect();
+        let walls: LivingList = pattern.walls.iter().map(|c| *c + corner).collect();
+        self.replace_board(&cells, &walls, pattern.rule);
+    }
+
+    /// Clear the board and put `cells` and `walls` on it, wrapping them onto it
+    /// if needed. With a rule, the rule is changed first, so that a torus too
+    /// small for its neighborhood is grown before the cells are wrapped onto
+    /// it.
+    fn replace_board(
+        &mut self,
+        cells: &LivingList,
+        walls: &LivingList,
+        ruleset: Option<rules::Ruleset>,
+    ) {
+        self.finish_pending_step();
+        self.clear_action();
+        if let Some(ruleset) = ruleset {
+            self.set_ruleset(ruleset);
+        }
+        self.sim.replace_board(cells, walls);
+        self.living_cell_count = self.sim.living_cells.len();
+        if let Some(payloads) = &mut self.payloads {
+            payloads.reset(&self.sim.living_cells);
+        }
+        self.ages.reset(&self.sim.living_cells);
+        self.clear_highlights();
+        self.cell_changes.refresh_all();
+    }
+
+    /// Rotate the pattern clockwise by some number of quarter turns, about the
+    /// center of its bounding box. There's no way to select part of the board
+    /// yet, so this is the whole pattern.
+    pub fn rotate_selection(&mut self, quarter_turns: i32) {
+        self.transform_pattern(|c| match quarter_turns.rem_euclid(4) {
+            0 => c,
+            1 => Vector2::new(-c.y, c.x),
+            2 => Vector2::new(-c.x, -c.y),
+            _ => Vector2::new(c.y, -c.x),
+        });
+    }
+
+    /// Mirror the pattern about the center of its bounding box, reversing its
+    /// coordinates along `axis`. Like rotating, this is the whole pattern.
+    pub fn flip_selection(&mut self, axis: Axis) {
+        self.transform_pattern(|c| match axis {
+            Axis::X => Vector2::new(-c.x, c.y),
+            Axis::Y => Vector2::new(c.x, -c.y),
+        });
+    }
+
+    /// Move every living cell with a transformation about the center of their
+    /// bounding box, so that the pattern stays where it is on the screen.
+    ///
+    /// `transform` is given coordinates doubled and relative to the center,
+    /// so that centers between cells are still whole numbers.
+    fn transform_pattern(&mut self, transform: impl Fn(Vector2<i32>) -> Vector2<i32>) {
+        self.finish_pending_step();
+        let Some((min, max)) = patterns::bounding_box(&self.sim.living_cells) else {
+            return;
+        };
+        let sum = min + max;
+        let cells: LivingList = self
+            .sim
+            .living_cells
+            .iter()
+            .map(|c| {
+                let moved = transform(*c + *c - sum) + sum;
+                Vector2::new(moved.x.div_euclid(2), moved.y.div_euclid(2))
+            })
+            .collect();
+        self.sim.living_cells = self.sim.boundary.wrap_all(&cells);
+        // Cells can't be moved onto walls
+        let walls = &self.sim.walls;
+        self.sim.living_cells.retain(|cell| !walls.contains(cell));
+        self.sim.clear_decaying();
+        self.living_cell_count = self.sim.living_cells.len();
+        if let Some(payloads) = &mut self.payloads {
+            payloads.reset(&self.sim.living_cells);
+        }
+        self.ages.reset(&self.sim.living_cells);
+        self.clear_highlights();
+        self.reset_detection();
+        self.toggle_record.push(self.sim.step_count);
+        self.cell_changes.refresh_all();
+    }
+
+    /// Replace the board with random cells covering the window, each alive
+    /// with a probability of `density`. The same seed always gives the same
+    /// cells for the same view.
+    #[cfg(feature = "random")]
+    pub fn random_fill(&mut self, density: f32, seed: u64) {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let density = density.clamp(0.0, 1.0) as f64;
+        let (min, max) = self.visible_cells();
+        let cells: LivingList = (min.y..=max.y)
+            .flat_map(|y| (min.x..=max.x).map(move |x| Vector2::new(x, y)))
+            .filter(|_| rng.gen_bool(density))
+            .collect();
+        self.replace_board(&cells, &LivingList::default(), None);
+    }
+
+    /// Replace the board with a pattern in RLE format, centered on the view.
+    /// If the pattern gives a rule, it's used from then on.
+    ///
+    /// Nothing is changed if the pattern can't be read.
+    pub fn load_rle(&mut self, text: &str) -> anyhow::Result<()> {
+        let pattern = patterns::parse_rle(text)?;
+        self.place_pattern(pattern);
+        Ok(())
+    }
+
+    /// Save the game in a slot of its own, replacing whatever was saved there
+    /// before.
+    #[cfg(feature = "saving")]
+    pub fn save_to_slot(&mut self, name: &str) -> anyhow::Result<()> {
+        let save = SaveGame::new(self, name.to_string());
+        self.slot_storage
+            .write(name, &serde_json::to_string_pretty(&save)?)
+    }
+
+    /// Load the game saved in a slot. Nothing is changed if the slot is empty
+    /// or can't be read.
+    #[cfg(feature = "saving")]
+    pub fn load_from_slot(&mut self, name: &str) -> anyhow::Result<()> {
+        let data = self
+            .slot_storage
+            .read(name)?
+            .ok_or_else(|| anyhow::anyhow!("Nothing is saved in the slot \"{name}\""))?;
+        let save: SaveGame = serde_json::from_str(&data)?;
+        self.load_save(&save);
+        Ok(())
+    }
+
+    #[cfg(feature = "saving")]
+    pub fn delete_slot(&mut self, name: &str) -> anyhow::Result<()> {
+        self.slot_storage.delete(name)
+    }
+
+    /// Keep save slots somewhere else from now on. Slots that were already
+    /// saved aren't moved.
+    #[cfg(feature = "saving")]
+    pub fn set_slot_storage(&mut self, storage: Box<dyn saving::DataStorage + Send>) {
+        self.slot_storage = storage;
+    }
+
+    /// The names of the slots that have something saved in them, sorted
+    #[cfg(feature = "saving")]
+    pub fn slot_names(&self) -> anyhow::Result<Vec<String>> {
+        self.slot_storage.keys()
+    }
+
+    /// Get a summary of the game saved in a slot without loading it. Returns
+    /// `None` if the slot is empty or can't be read.
+    #[cfg(feature = "saving")]
+    pub fn slot_info(&self, name: &str) -> Option<saving::SaveInfo> {
+        saving::slot_info(&*self.slot_storage, name)
+    }
+
+    #[cfg(feature = "saving")]
+    pub fn get_autosave_interval(&self) -> Option<Duration> {
+        self.autosave_interval
+    }
+
+    /// Set how often the game is autosaved, or turn autosaving off with
+    /// `None`. With it off, the game isn't autosaved when it closes either,
+    /// but the saves made by hand are still written.
+    #[cfg(feature = "saving")]
+    pub fn set_autosave_interval(&mut self, to: Option<Duration>) {
+        self.autosave_interval = to;
+    }
+
+    #[cfg(feature = "saving")]
+    fn autosave_if_due(&mut self) {
+        let Some(interval) = self.autosave_interval else {
+            return;
+        };
+        if self.last_autosave.elapsed() >= interval {
+            self.last_autosave = Instant::now();
+            self.autosave();
+        }
+    }
+
+    /// Save the game to `AUTOSAVE_SLOT` and write the save file, skipping
+    /// whichever hasn't changed since it was last written.
+    #[cfg(feature = "saving")]
+    fn autosave(&mut self) {
+        if self.unsaved_changes {
+            match self.save_to_slot(AUTOSAVE_SLOT) {
+                Ok(()) => self.unsaved_changes = false,
+                Err(e) => log::error!("Failed to autosave: {e}"),
+            }
+        }
+        self.write_save_file();
+    }
+
+    /// Write the save file to the disk if it changed since it was last
+    /// written
+    #[cfg(feature = "saving")]
+    fn write_save_file(&mut self) {
+        if let Some(save_file) = &mut self.save_file
+            && save_file.is_modified()
+            && let Err(e) = save_file.write_to_disk()
+        {
+            log::error!("Failed to write saves with error:\n{e}");
+        }
+    }
+
+    /// Keep saves in the file at `path`, along with the ones already in it, or
+    /// don't keep a save file at all with `None`. There isn't one until this
+    /// is called. The saves in the file used before are written out first.
+    #[cfg(all(feature = "saving", not(target_arch = "wasm32")))]
+    pub fn set_save_path(&mut self, path: Option<std::path::PathBuf>) {
+        self.write_save_file();
+        self.save_file = path.and_then(|path| match SaveFile::new(path.clone()) {
+            Ok(save_file) => {
+                log::info!("Keeping saves in {}", path.display());
+                Some(save_file)
+            }
+            Err(e) => {
+                log::error!("Can't keep saves in {}: {e}", path.display());
+                None
+            }
+        });
+    }
+
+    /// Take exactly one step and stay paused, pausing autoplay first if it's
+    /// on. A step autoplay already started is finished before it, and this
+    /// one is finished before returning, so it behaves the same with and
+    /// without the worker thread.
+    pub fn step_once(&mut self) {
+        self.run_target = None;
+        self.paused_for_focus = false;
+        self.loop_state.pause(&self.interval);
+        self.finish_pending_step();
+        self.step();
+        self.finish_pending_step();
+    }
+
+    /// Go back to the previous generation, if it's still in the history.
+    ///
+    /// Payloads, cell ages and decaying cells can't be recovered, so they
+    /// start over as if the cells had just been placed.
+    pub fn step_back(&mut self) {
+        self.finish_pending_step();
+        let Some(prev) = self.history.pop_back() else {
+            return;
+        };
+        self.sim.living_cells = prev;
+        self.sim.clear_decaying();
+        self.reset_detection();
+        if let Some(payloads) = &mut self.payloads {
+            payloads.reset(&self.sim.living_cells);
+        }
+        self.ages.reset(&self.sim.living_cells);
+        self.clear_highlights();
+        self.sim.step_count = self.sim.step_count.saturating_sub(1);
+        self.living_cell_count = self.sim.living_cells.len();
+        if self.living_count_history.len() > 1 {
+            self.living_count_history.pop();
+        }
+        self.cell_changes.refresh_all();
+    }
+
+    pub fn get_history_limit(&self) -> usize {
+        self.history_limit
+    }
+
+    /// Set how many past generations are kept for stepping backwards. Zero
+    /// turns the history off.
+    pub fn set_history_limit(&mut self, to: usize) {
+        self.history_limit = to;
+        self.trim_history();
+    }
+
+    pub fn get_history_memory_limit(&self) -> Option<usize> {
+        self.history_memory_limit
+    }
+
+    /// Cap the memory taken up by past generations, in bytes, or let them
+    /// take up as much as they need with `None`. The oldest generations are
+    /// dropped to stay under the cap.
+    pub fn set_history_memory_limit(&mut self, to: Option<usize>) {
+        self.history_memory_limit = to;
+        self.trim_history();
+    }
+
+    /// Drop the oldest past generations until there are no more than the
+    /// limit and they fit in the memory cap
+    fn trim_history(&mut self) {
+        trim_history(
+            &mut self.history,
+            self.history_limit,
+            self.history_memory_limit,
+        );
+    }
+
+    /// Roughly how many bytes the past generations kept for stepping back
+    /// take up
+    pub fn history_memory(&self) -> usize {
+        self.history.iter().map(stats::set_memory).sum()
+    }
+
+    /// Roughly how many bytes everything kept about past generations takes
+    /// up: the generations kept for stepping back, what's remembered for
+    /// finding oscillators, and the population of every generation
+    pub fn total_history_memory(&self) -> usize {
+        self.history_memory()
+            + self.period_detector.memory()
+            + self.living_count_history.capacity() * std::mem::size_of::<usize>()
+    }
+
+    /// Write the living cells and walls as RLE, framed by their bounding box,
+    /// with the current rule in the header.
+    pub fn export_rle(&self) -> String {
+        let cells = self.sim.living_cells.iter().copied().collect::<Vec<_>>();
+        let walls = self.sim.walls().collect::<Vec<_>>();
+        patterns::encode_rle(&cells, &walls, &self.sim.ruleset)
+    }
+
+    /// Put the living cells in the fragment of the page's URL, so the link can
+    /// be shared. Loading the page from it puts them back with
+    /// `load_from_url`.
+    #[cfg(target_arch = "wasm32")]
+    pub fn share_to_url(&self) -> anyhow::Result<()> {
+        let window = web_sys::window().ok_or_else(|| anyhow::anyhow!("There's no window"))?;
+        window
+            .location()
+            .set_hash(&share::encode(&self.export_rle()))
+            .map_err(|e| anyhow::anyhow!("Couldn't change the URL: {e:?}"))
+    }
+
+    /// Replace the board with the pattern shared in the fragment of the page's
+    /// URL, if there is one. Returns whether there was.
+    #[cfg(target_arch = "wasm32")]
+    pub fn load_from_url(&mut self) -> anyhow::Result<bool> {
+        let window = web_sys::window().ok_or_else(|| anyhow::anyhow!("There's no window"))?;
+        let hash = window
+            .location()
+            .hash()
+            .map_err(|e| anyhow::anyhow!("Couldn't read the URL: {e:?}"))?;
+        let shared = hash.trim_start_matches('#');
+        if shared.is_empty() {
+            return Ok(false);
+        }
+        self.load_rle(&share::decode(shared)?)?;
+        Ok(true)
+    }
+
+    /// Replace the board with a pattern in the plaintext `.cells` format,
+    /// centered on the view.
+    ///
+    /// Nothing is changed if the pattern can't be read.
+    pub fn load_plaintext(&mut self, text: &str) -> anyhow::Result<()> {
+        let pattern = patterns::parse_plaintext(text)?;
+        self.place_pattern(pattern);
+        Ok(())
+    }
+
+    /// Write the living cells in the plaintext `.cells` format, framed by
+    /// their bounding box. Fails if the box is too big to write out.
+    pub fn export_plaintext(&self) -> anyhow::Result<String> {
+        let cells = self.sim.living_cells.iter().copied().collect::<Vec<_>>();
+        patterns::encode_plaintext(&cells)
+    }
+
+    /// Replace the board with what's in a file, read according to its
+    /// extension: `.rle`, `.cells`, or a `.json` save.
+    ///
+    /// Nothing is changed if the file can't be read.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_pattern_file(&mut self, path: &std::path::Path) -> anyhow::Result<()> {
+        let text = std::fs::read_to_string(path)?;
+        match PatternFormat::from_path(path)? {
+            PatternFormat::Rle => self.load_rle(&text),
+            PatternFormat::Plaintext => self.load_plaintext(&text),
+            #[cfg(feature = "saving")]
+            PatternFormat::Save => {
+                let save: SaveGame = serde_json::from_str(&text)?;
+                self.load_save(&save);
+                Ok(())
+            }
+        }
+    }
+
+    /// Write the board to a file, in the format given by its extension:
+    /// `.rle`, `.cells`, or a `.json` save.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_pattern_file(&mut self, path: &std::path::Path) -> anyhow::Result<()> {
+        let text = match PatternFormat::from_path(path)? {
+            PatternFormat::Rle => self.export_rle(),
+            PatternFormat::Plaintext => self.export_plaintext()?,
+            #[cfg(feature = "saving")]
+            PatternFormat::Save => {
+                let name = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                serde_json::to_string_pretty(&SaveGame::new(self, name))?
+            }
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// The generation the first population in `living_count_history` is from.
+    /// The last one is from the current generation.
+    pub fn first_history_generation(&self) -> u64 {
+        (self.sim.step_count + 1).saturating_sub(self.living_count_history.len() as u64)
+    }
+
+    /// The population at each generation in the history as CSV, with a header
+    /// row. The `edits` column is the number of cells toggled by hand at that
+    /// generation, and is left empty if there weren't any.
+    pub fn export_history_csv(&self) -> String {
+        use std::fmt::Write;
+
+        let mut edits = rustc_hash::FxHashMap::<u64, usize>::default();
+        for generation in &self.toggle_record {
+            *edits.entry(*generation).or_default() += 1;
+        }
+        let first = self.first_history_generation();
+        let mut out = String::from("generation,population,edits\n");
+        for (i, population) in self.living_count_history.iter().enumerate() {
+            let generation = first + i as u64;
+            let _ = write!(out, "{generation},{population},");
+            if let Some(n) = edits.get(&generation) {
+                let _ = write!(out, "{n}");
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Take the changes that the renderer hasn't been told about yet.
+    fn take_changes(&mut self) -> StateChanges {
+        // Fading cells are drawn fainter every frame until they're gone
+        if !self.fading.is_empty() {
+            if self.fade_progress() >= 1.0 {
+                self.fading.clear();
+            }
+            self.cell_changes.refresh_all();
+        }
+        let mut changes = std::mem::take(&mut self.changes);
+        match self.cell_changes.take(self.sim.living_cells.len()) {
+            Some(CellUpdate::All) => changes.cells = Some(self.get_cells()),
+            // Cells that just died or are decaying are drawn at the same
+            // coordinates that can be toggled back on, so patching them in
+            // place won't work
+            Some(_)
+                if self.highlight_changes
+                    || !self.fading.is_empty()
+                    || self.stamp.is_some()
+                    || self.show_heatmap
+                    || self.sim.ruleset.is_generations() =>
+            {
+                changes.cells = Some(self.get_cells())
+            }
+            Some(CellUpdate::Delta { born, died }) => {
+                changes.cell_delta = Some(CellDelta {
+                    added: born.into_iter().map(|c| to_cell(c, self.grid_size)).collect(),
+                    removed: died.into_iter().map(Into::into).collect(),
+                })
+            }
+            None => (),
+        }
+        #[cfg(feature = "saving")]
+        if changes.cells.is_some()
+            || changes.cell_delta.is_some()
+            || changes.offset.is_some()
+            || changes.grid_size.is_some()
+        {
+            self.unsaved_changes = true;
+        }
+        changes
+    }
+
+    /// Compute the next generation on this thread and apply it, without
+    /// telling the renderer.
+    fn step_now(&mut self) {
+        let next = self.sim.compute_next();
+        self.advance(next);
+    }
+
+    /// Replace the living cells with the next generation and update the
+    /// statistics. The renderer isn't told about the change, since callers
+    /// stepping many times only need to do that once.
+    fn advance(&mut self, next: LivingList) {
+        let prev = self.sim.replace(next);
+        let unchanged = prev == self.sim.living_cells;
+        self.cell_changes.step(&prev, &self.sim.living_cells);
+        if self.fade_out.is_some() {
+            // Only the cells that died in the last step fade, since a fade is
+            // over by the time the next step is due
+            if !self.fading.is_empty() {
+                self.cell_changes.refresh_all();
+            }
+            self.fading.clear();
+            self.fading
+                .extend(prev.difference(&self.sim.living_cells).copied());
+            self.fade_started = Some(Instant::now());
+        }
+        if self.highlight_changes {
+            self.last_born = self.sim.living_cells.difference(&prev).copied().collect();
+            self.last_died = prev.difference(&self.sim.living_cells).copied().collect();
+            // Last generation's highlights have to be cleared too
+            self.cell_changes.refresh_all();
+        }
+        if self.show_heatmap {
+            self.heatmap.step(&self.sim.living_cells);
+        }
+        if self.show_heatmap || self.sim.ruleset.is_generations() {
+            // Cells that aren't alive any more are still drawn, and decaying
+            // ones change even when the living ones don't
+            self.cell_changes.refresh_all();
+        }
+        // Whatever generation is no longer needed becomes the buffer for the
+        // next one
+        let spare = if self.history_limit > 0 {
+            let oldest = if self.history.len() >= self.history_limit {
+                self.history.pop_front()
+            } else {
+                None
+            };
+            self.history.push_back(prev);
+            oldest
+        } else {
+            Some(prev)
+        };
+        if let Some(spare) = spare {
+            self.sim.recycle(spare);
+        }
+        if self.history_memory_limit.is_some() {
+            self.trim_history();
+        }
+        if let Some(payloads) = &mut self.payloads {
+            payloads.step(
+                &self.sim.living_cells,
+                &self.sim.ruleset.neighborhood,
+                &self.sim.boundary,
+            );
+        }
+        self.ages.step(&self.sim.living_cells);
+        self.living_cell_count = self.sim.living_cells.len();
+        self.living_count_history.push(self.living_cell_count);
+        let repetition = self
+            .period_detector
+            .observe(&self.sim.living_cells, &self.sim.decaying);
+        self.detected_period = repetition.map(|r| r.period);
+        self.detected_velocity = repetition.and_then(|r| r.velocity());
+        self.shape = stats::ShapeStats::of(&self.sim.living_cells);
+        self.shape_stale = false;
+        self.drift.observe(self.shape.center_of_mass);
+
+        self.status = if self.sim.living_cells.is_empty() {
+            stats::SimStatus::Extinct
+        } else if unchanged {
+            stats::SimStatus::Stable
+        } else {
+            stats::SimStatus::Running
+        };
+        if self.auto_pause && self.status != stats::SimStatus::Running {
+            self.loop_state = LoopState::Stopped;
+            self.run_target = None;
+        }
+        if self.run_target.is_some_and(|t| self.sim.step_count >= t) {
+            self.loop_state = LoopState::Stopped;
+            self.run_target = None;
+        }
+        if let Some(on_step) = &mut self.on_step {
+            let info = StepInfo {
+                generation: self.sim.step_count,
+                population: self.living_cell_count,
+                status: self.status,
+            };
+            if on_step(&info).is_break() {
+                self.loop_state = LoopState::Stopped;
+                self.run_target = None;
+            }
+        }
+    }
+
+    /// Call `on_step` after every generation from now on, or stop calling
+    /// anything if it's `None`. It's called exactly once per generation,
+    /// whether the generation was computed on the worker thread or not, but
+    /// not for stepping backwards or for edits to the board.
+    ///
+    /// The game state is locked while it runs, so it can't reach back into
+    /// the game; it can stop autoplay by returning `ControlFlow::Break`.
+    pub fn set_on_step(&mut self, on_step: Option<StepCallback>) {
+        self.on_step = on_step;
+    }
+
+    /// Play until `n` more generations have been computed, then stop. The
+    /// steps are taken at the current interval, or as fast as possible if
+    /// `set_run_fast` was turned on.
+    pub fn run_generations(&mut self, n: u64) {
+        if n == 0 {
+            return;
+        }
+        // Count from the generation that's being computed, if there is one
+        self.finish_pending_step();
+        self.paused_for_focus = false;
+        self.run_target = Some(self.sim.step_count + n);
+        self.loop_state = LoopState::playing();
+    }
+
+    /// How many generations are left to run before stopping, if autoplay was
+    /// started with `run_generations`
+    pub fn generations_left(&self) -> Option<u64> {
+        self.run_target
+            .map(|t| t.saturating_sub(self.sim.step_count))
+    }
+
+    pub fn get_run_fast(&self) -> bool {
+        self.run_fast
+    }
+
+    /// Choose whether `run_generations` steps as fast as it can instead of
+    /// waiting for the interval. The renderer is still only told about the
+    /// last generation of each frame.
+    pub fn set_run_fast(&mut self, to: bool) {
+        self.run_fast = to;
+    }
+
+    /// Whether the fast forward key is held
+    pub fn is_turbo(&self) -> bool {
+        self.turbo
+    }
+
+    /// Start or stop fast forwarding, which steps as many times as it can
+    /// each frame whether or not autoplay is on.
+    pub fn set_turbo(&mut self, to: bool) {
+        if self.turbo && !to && self.loop_state.is_playing() {
+            // Don't make up for the time spent fast forwarding
+            self.loop_state = LoopState::playing();
+        }
+        self.turbo = to;
+    }
+
+    /// Whether steps are taken back to back this frame instead of waiting for
+    /// the interval, either to fast forward or to run a number of generations
+    /// as fast as possible
+    fn is_running_fast(&self) -> bool {
+        self.turbo || (self.run_fast && self.run_target.is_some() && self.loop_state.is_playing())
+    }
+
+    pub fn get_auto_pause(&self) -> bool {
+        self.auto_pause
+    }
+
+    /// Choose whether autoplay stops by itself once the pattern dies out or
+    /// stops changing.
+    pub fn set_auto_pause(&mut self, to: bool) {
+        self.auto_pause = to;
+    }
+
+    pub fn get_pause_on_unfocus(&self) -> bool {
+        self.pause_on_unfocus
+    }
+
+    /// Choose whether autoplay stops while the window isn't focused, and
+    /// starts again when it is.
+    pub fn set_pause_on_unfocus(&mut self, to: bool) {
+        self.pause_on_unfocus = to;
+    }
+
+    /// Move the view a step in the direction of every panning key held. Key
+    /// repeat only repeats the last key pressed, so this is done for every
+    /// press or repeat of any of them.
+    fn pan_with_keys(&mut self) {
+        let direction = self
+            .held_pan_keys
+            .iter()
+            .filter_map(|k| pan_key_direction(*k))
+            .fold(Vector2::new(0.0, 0.0), |sum, d| sum + d);
+        let size = self.window.inner_size();
+        let aspect_ratio = size.width as f64 / size.height as f64;
+        // The view is one unit tall and `aspect_ratio` units wide
+        self.pan_position += Vector2::<f64>::scale(
+            direction,
+            Vector2::new(aspect_ratio * KEY_PAN_FRACTION, KEY_PAN_FRACTION),
+        );
+        self.changes.offset = Some(self.pan_position);
+        self.update_stamp_preview();
+    }
+
+    /// Stop autoplay when the window loses focus, if it's playing and that's
+    /// turned on, and start it again when the window gets focus back.
+    fn handle_focus(&mut self, focused: bool) {
+        // Keys released while the window wasn't focused are never heard about
+        if !focused {
+            self.held_pan_keys.clear();
+            self.set_turbo(false);
+        }
+        if !focused && self.pause_on_unfocus && self.loop_state.is_playing() {
+            // Apply the step in progress now rather than leaving it waiting
+            // until the window comes back
+            self.finish_pending_step();
+            self.loop_state.pause(&self.interval);
+            self.paused_for_focus = true;
+        } else if focused && self.paused_for_focus {
+            self.paused_for_focus = false;
+            if let LoopState::Paused { remaining } = self.loop_state {
+                self.loop_state = LoopState::resumed(remaining, &self.interval);
+            }
+        }
+    }
+
+    pub fn get_highlight_changes(&self) -> bool {
+        self.highlight_changes
+    }
+
+    /// Choose whether cells are tinted for a generation after they're born or
+    /// die. This only changes how they're drawn.
+    pub fn set_highlight_changes(&mut self, to: bool) {
+        self.highlight_changes = to;
+        self.clear_highlights();
+    }
+
+    pub fn get_fade_out(&self) -> Option<f32> {
+        self.fade_out
+    }
+
+    /// Fade cells out over `fraction` of the interval after they die instead
+    /// of removing them straight away, or stop with `None`. This only changes
+    /// how they're drawn.
+    pub fn set_fade_out(&mut self, fraction: Option<f32>) {
+        self.fade_out = fraction.map(|f| f.clamp(0.0, 1.0));
+        if self.fade_out.is_none() && !self.fading.is_empty() {
+            self.fading.clear();
+            self.cell_changes.refresh_all();
+        }
+    }
+
+    /// How far the fading cells are through fading out, from 0 to 1
+    fn fade_progress(&self) -> f32 {
+        let (Some(fraction), Some(started)) = (self.fade_out, self.fade_started) else {
+            return 1.0;
+        };
+        let duration = self.interval.mul_f32(fraction);
+        if duration.is_zero() {
+            return 1.0;
+        }
+        (started.elapsed().as_secs_f32() / duration.as_secs_f32()).min(1.0)
+    }
+
+    pub fn get_title_format(&self) -> Option<&str> {
+        self.title_format.as_deref()
+    }
+
+    /// Choose what the window title shows, with `{gen}` standing for the
+    /// generation and `{cells}` for the number of living cells. `None` goes
+    /// back to a plain title.
+    pub fn set_title_format(&mut self, format: Option<String>) {
+        if format.is_none() {
+            self.window.set_title(WINDOW_TITLE);
+        }
+        self.title_format = format;
+        self.title_updated = None;
+    }
+
+    /// Show the generation and population in the window title, if it's been
+    /// long enough since it last changed
+    fn update_title(&mut self) {
+        let Some(format) = &self.title_format else {
+            return;
+        };
+        if self
+            .title_updated
+            .is_some_and(|t| t.elapsed() < TITLE_UPDATE_INTERVAL)
+        {
+            return;
+        }
+        let title = format
+            .replace("{gen}", &self.sim.step_count.to_string())
+            .replace("{cells}", &self.living_cell_count.to_string());
+        self.window.set_title(&title);
+        self.title_updated = Some(Instant::now());
+    }
+
+    pub fn get_show_heatmap(&self) -> bool {
+        self.show_heatmap
+    }
+
+    /// Choose whether cells are colored by how often they've been alive
+    /// instead of being drawn as they are. They're only counted while it's
+    /// shown, so hiding it pauses the counts rather than resetting them.
+    pub fn set_show_heatmap(&mut self, to: bool) {
+        self.show_heatmap = to;
+        self.cell_changes.refresh_all();
+    }
+
+    /// Forget how often every cell has been alive
+    pub fn reset_heatmap(&mut self) {
+        self.heatmap.reset();
+        self.cell_changes.refresh_all();
+    }
+
+    /// Stop tinting the cells that changed in the last step
+    fn clear_highlights(&mut self) {
+        if !self.last_born.is_empty() || !self.last_died.is_empty() || !self.fading.is_empty() {
+            self.last_born.clear();
+            self.last_died.clear();
+            self.fading.clear();
+            self.cell_changes.refresh_all();
+        }
+    }
+
+    /// Forget what's been found out about how the pattern behaves over time,
+    /// for when the board is changed by something other than a step.
+    fn reset_detection(&mut self) {
+        self.period_detector.reset();
+        self.detected_period = None;
+        self.detected_velocity = None;
+        self.status = stats::SimStatus::Running;
+        self.drift.reset();
+        // This is done for every cell painted, so the shape is only worked
+        // out again once a frame
+        self.shape_stale = true;
+    }
+
+    /// Start attaching a payload of type `P` to every living cell. Cells that
+    /// are already alive get the default payload. This replaces any payloads
+    /// that were attached before.
+    pub fn attach_payloads<P: payload::CellPayload>(&mut self) {
+        self.payloads = Some(Box::new(payload::Payloads::<P>::new(&self.sim.living_cells)));
+    }
+
+    /// Stop attaching payloads to cells, dropping the ones there are.
+    pub fn detach_payloads(&mut self) {
+        self.payloads = None;
+    }
+
+    /// Get the payload of a living cell. Returns `None` if the cell is dead or
+    /// payloads of type `P` aren't attached.
+    pub fn payload<P: payload::CellPayload>(&self, cell: Vector2<i32>) -> Option<&P> {
+        self.payloads
+            .as_ref()?
+            .as_any()
+            .downcast_ref::<payload::Payloads<P>>()?
+            .0
+            .get(&cell)
+    }
+
+    /// Get the payload of a living cell mutably. Returns `None` if the cell is
+    /// dead or payloads of type `P` aren't attached.
+    pub fn payload_mut<P: payload::CellPayload>(&mut self, cell: Vector2<i32>) -> Option<&mut P> {
+        self.payloads
+            .as_mut()?
+            .as_any_mut()
+            .downcast_mut::<payload::Payloads<P>>()?
+            .0
+            .get_mut(&cell)
+    }
+
+    /// Advance exactly `n` generations before returning, regardless of
+    /// threading. If a step is already being computed on the worker thread,
+    /// this waits for it and applies it first, without counting it.
+    ///
+    /// This bypasses autoplay pacing entirely, so it's meant for tests and
+    /// scripts rather than interactive use.
+    pub fn step_n_blocking(&mut self, n: u64) {
+        self.finish_pending_step();
+        for _ in 0..n {
+            self.step_now();
+        }
+    }
+
+    /// Step synchronously until the cell at `pos` is born or dies, or until
+    /// `max_steps` steps have been taken. Returns the number of steps taken.
+    ///
+    /// This is meant for finding out when something happens to a particular
+    /// cell, like the phase of an oscillator. Like `step_n_blocking`, it
+    /// finishes any step that's in progress first and ignores autoplay.
+    pub fn step_until_cell_changes(&mut self, pos: Vector2<i32>, max_steps: u64) -> u64 {
+        self.finish_pending_step();
+        let start = self.sim.living_cells.contains(&pos);
+        let mut steps = 0;
+        while steps < max_steps {
+            self.step_now();
+            steps += 1;
+            if self.sim.living_cells.contains(&pos) != start {
+                break;
+            }
+        }
+        steps
+    }
+
+    /// Clear the screen
+    fn clear_action(&mut self) {
+        self.sim.living_cells.clear();
+        self.sim.walls = Default::default();
+        self.sim.clear_decaying();
+        self.history.clear();
+        self.reset_detection();
+        if let Some(payloads) = &mut self.payloads {
+            payloads.reset(&self.sim.living_cells);
+        }
+        self.ages.reset(&self.sim.living_cells);
+        self.clear_highlights();
+        self.sim.step_count = 0;
+        self.living_count_history = vec![0];
+        self.living_cell_count = 0;
+
+        self.cell_changes.refresh_all();
+        self.toggle_record.clear();
+    }
+
+    /// Resolve the input queue (`self.input_queue`)
+    fn resolve_queue(&mut self) {
+        while let Some(i) = self.input_queue.pop_front() {
+            match i {
+                QueueAction::Clear => {
+                    self.clear_action();
+                }
+                QueueAction::Set(cell, alive) => {
+                    self.set_action(cell, alive);
+                }
+                #[cfg(feature = "saving")]
+                QueueAction::Load(save) => {
+                    self.load_action(save);
+                }
+            }
+        }
+    }
+
+    /// Handle a left click by toggling the particular cell. This should not be
+    /// called if the click was on the GUI.
+    fn left_action(&mut self, cell_pos: Vector2<i32>) {
+        // Clicks off a bounded board or on walls don't do anything
+        if !self.sim.boundary.contains(cell_pos) || self.sim.is_wall(cell_pos) {
+            return;
+        }
+        let alive = self.sim.toggle(cell_pos);
+        if let Some(payloads) = &mut self.payloads {
+            payloads.set(cell_pos, alive);
+        }
+        self.reset_detection();
+        self.ages.set(cell_pos, alive);
+
+        if alive {
+            self.cell_changes.born(cell_pos);
+        } else {
+            self.cell_changes.died(cell_pos);
+        }
+        self.toggle_record.push(self.sim.step_count);
+    }
+
+    #[cfg(feature = "saving")]
+    fn load_action(&mut self, save: SaveGame) {
+        // The save may be from a different boundary mode or rule, so the rule
+        // is set first and its cells are wrapped onto this board.
+        self.replace_board(&save.living_cells(), &save.walls(), Some(save.ruleset()));
+        self.pan_position = save.pan_position();
+        self.grid_size = save.grid_size();
+        self.sim.step_count = save.step_count();
+        self.living_count_history = save.living_count_history();
+        if let Some(interval) = save.interval() {
+            self.interval = interval;
+        }
+        if let Some(theme) = save.theme() {
+            self.set_theme(theme);
+        }
+
+        self.changes.grid_size = Some(self.grid_size);
+        self.changes.offset = Some(self.pan_position);
+    }
+}
+
+#[cfg(feature = "native_threads")]
+impl GameState {
+    pub fn new(window: Arc<Window>, grid_size: f32) -> Self {
+        let thread_data = ThreadData::spawn();
+        #[cfg(feature = "saving")]
+        let settings_storage = saving::settings_storage();
         #[cfg(feature = "saving")]
-        let save_file = SaveFile::new("./save.json".into()).unwrap();
+        let key_bindings = keys::KeyBindings::load(&*settings_storage);
+        #[cfg(not(feature = "saving"))]
+        let key_bindings = keys::KeyBindings::default();
 
         Self {
             pan_position: [0.0, 0.0].into(),
-            living_cells: FxHashSet::default(),
+            sim: simulation::Simulation::new(),
             loop_state: LoopState::new(),
             interval: DEFAULT_INTERVAL,
+            strict_rate: false,
+            dropped_generations: 0,
             window,
             mouse_position: None,
             grid_size,
+            initial_grid_size: grid_size,
             drag_state: DragState::NotDragging,
+            touches: HashMap::new(),
+            tap: None,
+            paint_stroke: None,
+            tool: Tool::Toggle,
+            stamp: None,
+            symmetry: Symmetry::None,
+            pan_button: PanButton::Either,
+            confirm_clear: true,
+            pending_clear: false,
+            shape: stats::ShapeStats::default(),
+            shape_stale: false,
+            drift: stats::DriftTracker::default(),
+            theme: theme::THEMES[0].name,
+            heatmap: stats::HeatMap::default(),
+            show_heatmap: false,
+            title_format: Some(DEFAULT_TITLE_FORMAT.to_string()),
+            title_updated: None,
+            clipboard: clipboard::Clipboard::default(),
+            toast: None,
+            on_step: None,
+            last_click: None,
+            double_click_time: DEFAULT_DOUBLE_CLICK_TIME,
+            scroll_sensitivity: DEFAULT_SCROLL_SENSITIVITY,
+            scroll_step_modifier: DEFAULT_SCROLL_STEP_MODIFIER,
+            scroll_steps: 0.0,
+            previous_camera: None,
+            animate_zoom: true,
+            camera_transition: None,
             thread_data,
             input_queue: VecDeque::new(),
             living_cell_count: 0,
-            step_count: 0,
             living_count_history: vec![0],
             changes: StateChanges::default(),
             toggle_record: Vec::new(),
             #[cfg(feature = "saving")]
-            save_file: Some(save_file),
+            save_file: None,
+            #[cfg(feature = "saving")]
+            slot_storage: saving::default_storage(),
+            #[cfg(feature = "saving")]
+            settings_storage,
+            #[cfg(feature = "saving")]
+            autosave_interval: Some(DEFAULT_AUTOSAVE_INTERVAL),
+            #[cfg(feature = "saving")]
+            last_autosave: Instant::now(),
+            #[cfg(feature = "saving")]
+            unsaved_changes: false,
+            payloads: None,
+            ages: stats::AgeMap::default(),
+            history: VecDeque::new(),
+            history_limit: DEFAULT_HISTORY_LIMIT,
+            history_memory_limit: DEFAULT_HISTORY_MEMORY_LIMIT,
+            modifiers: ModifiersState::empty(),
+            key_bindings,
+            rebinding: None,
+            period_detector: stats::PeriodDetector::default(),
+            detected_period: None,
+            detected_velocity: None,
+            status: stats::SimStatus::Running,
+            auto_pause: false,
+            pause_on_unfocus: true,
+            paused_for_focus: false,
+            run_target: None,
+            run_fast: false,
+            turbo: false,
+            held_pan_keys: HashSet::new(),
+            cell_changes: CellChanges::default(),
+            highlight_changes: false,
+            last_born: LivingList::default(),
+            last_died: LivingList::default(),
+            fade_out: None,
+            fading: LivingList::default(),
+            fade_started: None,
+            #[cfg(feature = "random")]
+            random_seed: 0,
             #[cfg(target_arch = "wasm32")]
             scroll_mode: Default::default(),
         }
@@ -430,90 +2735,138 @@ impl GameState {
 
     #[cfg(feature = "saving")]
     pub fn load_save(&mut self, save: &SaveGame) {
-        if self
-            .thread_data
-            .shared
-            .computing
-            .load(atomic::Ordering::Relaxed)
-        {
+        if self.is_computing() {
             self.input_queue.push_back(QueueAction::Load(save.clone()));
         } else {
             self.load_action(save.clone());
         }
     }
 
-    pub fn step(&mut self) {
-        if self
-            .thread_data
-            .shared
-            .computing
-            .load(atomic::Ordering::Relaxed)
-        {
+    /// Whether the worker thread has been given a step that hasn't been
+    /// received yet.
+    pub fn is_computing(&self) -> bool {
+        self.thread_data.is_computing()
+    }
+
+    /// Wait for the step that's being computed, if there is one, and apply it.
+    fn finish_pending_step(&mut self) {
+        if !self.is_computing() {
             return;
         }
-        let mut noti_lock = self.thread_data.shared.notification.lock().unwrap();
-        *noti_lock = StepThreadNotification::Compute(self.living_cells.clone());
-        self.thread_data.shared.condvar.notify_all();
+        // This fails only if the worker died, in which case the step is lost
+        if let Ok(v) = self.thread_data.local.rx.recv() {
+            self.receive_step(v);
+        }
     }
 
-    pub fn clear(&mut self) {
-        if self
-            .thread_data
+    /// Apply a step received from the worker thread.
+    fn receive_step(&mut self, next: LivingList) {
+        self.thread_data
             .shared
             .computing
-            .load(atomic::Ordering::Relaxed)
-        {
-            self.input_queue.push_back(QueueAction::Clear);
-        } else {
-            self.clear_action();
+            .store(false, atomic::Ordering::Relaxed);
+        self.advance(next);
+        self.resolve_queue();
+    }
+
+    /// How long the step that's currently being computed has taken so far, or
+    /// `None` if there isn't one.
+    pub fn computing_time(&self) -> Option<Duration> {
+        self.is_computing()
+            .then(|| self.thread_data.local.step_started.map(|t| t.elapsed()))
+            .flatten()
+    }
+
+    /// Whether the worker thread has stopped, which only happens if something
+    /// went wrong. Steps are then computed on this thread instead, which can
+    /// make the game slow to respond on big boards.
+    pub fn worker_died(&self) -> bool {
+        self.thread_data.local.join_handle.is_finished()
+    }
+
+    pub fn step(&mut self) {
+        if self.is_computing() {
+            return;
+        }
+        // Without the worker, the game carries on stepping on this thread
+        if self.worker_died() {
+            self.step_now();
+            return;
         }
+        self.thread_data.shared.notify(StepThreadNotification::Compute(
+            self.sim.living_cells.clone(),
+            Arc::clone(&self.sim.walls),
+            self.sim.take_spare(),
+            Box::new(self.sim.ruleset),
+            self.sim.boundary,
+        ));
+        self.thread_data.local.step_started = Some(Instant::now());
     }
 
-    fn handle_left(&mut self, mouse_position: Vector2<f64>) {
-        let size = self.window.inner_size();
-        let cell_pos = find_cell_num(size, mouse_position, self.pan_position, self.grid_size);
-        if self
-            .thread_data
-            .shared
-            .computing
-            .load(atomic::Ordering::Relaxed)
-        {
-            self.input_queue.push_back(QueueAction::Toggle(cell_pos));
+    /// Kill every cell, take down the walls, and start the generation count,
+    /// history and statistics over. If a step is being computed, this waits
+    /// until it's in, so the step doesn't bring the cells back.
+    pub fn clear(&mut self) {
+        if self.is_computing() {
+            self.input_queue.push_back(QueueAction::Clear);
         } else {
-            self.left_action(cell_pos);
+            self.clear_action();
         }
     }
 
     pub fn update(&mut self) -> StateChanges {
-        let should_step = self.loop_state.update(&self.interval);
+        self.update_camera_transition();
+        if self.is_running_fast() {
+            // Wait for each step in turn, since only one can be computed at a
+            // time
+            let start = Instant::now();
+            while self.is_running_fast() && start.elapsed() < RUN_FAST_BUDGET {
+                self.step();
+                self.finish_pending_step();
+            }
+        }
+        // Steps that come due while one is still being computed wait for the
+        // next frames
+        let max_steps = if self.is_computing() {
+            0
+        } else {
+            MAX_STEPS_PER_FRAME
+        };
+        let due = if self.is_running_fast() {
+            0
+        } else {
+            self.steps_due(max_steps)
+        };
 
-        if should_step
-            && !self
-                .thread_data
-                .shared
-                .computing
-                .load(atomic::Ordering::Relaxed)
-        {
+        // Only one step can be computed at a time, so each one but the last is
+        // waited for. The last is left to finish in the background.
+        for taken in 1..=due {
             self.step();
+            if taken == due {
+                break;
+            }
+            self.finish_pending_step();
+            // Autoplay may have stopped by itself
+            if !self.loop_state.is_playing() {
+                break;
+            }
         }
 
         if let Ok(v) = self.thread_data.local.rx.try_recv() {
-            self.living_cells = v;
-            self.changes.cells = Some(self.get_cells());
-            self.thread_data
-                .shared
-                .computing
-                .store(false, atomic::Ordering::Relaxed);
-            let mut lock = self.thread_data.shared.notification.lock().unwrap();
-            *lock = StepThreadNotification::Waiting;
-            self.step_count += 1;
-            self.living_cell_count = self.living_cells.len();
-            self.living_count_history.push(self.living_cell_count);
-            drop(lock);
+            self.receive_step(v);
+        }
+        // Edits made while a step was being computed are kept until it's in,
+        // which it never will be if the worker died computing it
+        if !self.is_computing() {
             self.resolve_queue();
         }
 
-        std::mem::take(&mut self.changes)
+        self.refresh_shape();
+        self.update_title();
+        let changes = self.take_changes();
+        #[cfg(feature = "saving")]
+        self.autosave_if_due();
+        changes
     }
 }
 
@@ -521,40 +2874,130 @@ impl GameState {
 #[cfg(not(feature = "native_threads"))]
 impl GameState {
     pub fn new(window: Arc<Window>, grid_size: f32) -> Self {
-        #[cfg(not(target_arch = "wasm32"))]
         #[cfg(feature = "saving")]
-        let save_file = SaveFile::new("./save.json".into()).unwrap();
+        let settings_storage = saving::settings_storage();
+        #[cfg(feature = "saving")]
+        let key_bindings = keys::KeyBindings::load(&*settings_storage);
+        #[cfg(not(feature = "saving"))]
+        let key_bindings = keys::KeyBindings::default();
+
         Self {
             pan_position: [0.0, 0.0].into(),
-            living_cells: FxHashSet::default(),
+            sim: simulation::Simulation::new(),
             loop_state: LoopState::new(),
             interval: DEFAULT_INTERVAL,
+            strict_rate: false,
+            dropped_generations: 0,
             window,
             mouse_position: None,
             grid_size,
+            initial_grid_size: grid_size,
             drag_state: DragState::NotDragging,
+            touches: HashMap::new(),
+            tap: None,
+            paint_stroke: None,
+            tool: Tool::Toggle,
+            stamp: None,
+            symmetry: Symmetry::None,
+            pan_button: PanButton::Either,
+            confirm_clear: true,
+            pending_clear: false,
+            shape: stats::ShapeStats::default(),
+            shape_stale: false,
+            drift: stats::DriftTracker::default(),
+            theme: theme::THEMES[0].name,
+            heatmap: stats::HeatMap::default(),
+            show_heatmap: false,
+            title_format: Some(DEFAULT_TITLE_FORMAT.to_string()),
+            title_updated: None,
+            clipboard: clipboard::Clipboard::default(),
+            toast: None,
+            on_step: None,
+            last_click: None,
+            double_click_time: DEFAULT_DOUBLE_CLICK_TIME,
+            scroll_sensitivity: DEFAULT_SCROLL_SENSITIVITY,
+            scroll_step_modifier: DEFAULT_SCROLL_STEP_MODIFIER,
+            scroll_steps: 0.0,
+            previous_camera: None,
+            animate_zoom: true,
+            camera_transition: None,
             input_queue: VecDeque::new(),
             living_cell_count: 0,
-            step_count: 0,
             living_count_history: vec![0],
             toggle_record: Vec::new(),
             changes: StateChanges::default(),
             #[cfg(feature = "saving")]
-            save_file: Some(save_file),
+            save_file: None,
+            #[cfg(feature = "saving")]
+            slot_storage: saving::default_storage(),
+            #[cfg(feature = "saving")]
+            settings_storage,
+            #[cfg(feature = "saving")]
+            autosave_interval: Some(DEFAULT_AUTOSAVE_INTERVAL),
+            #[cfg(feature = "saving")]
+            last_autosave: Instant::now(),
+            #[cfg(feature = "saving")]
+            unsaved_changes: false,
+            payloads: None,
+            ages: stats::AgeMap::default(),
+            history: VecDeque::new(),
+            history_limit: DEFAULT_HISTORY_LIMIT,
+            history_memory_limit: DEFAULT_HISTORY_MEMORY_LIMIT,
+            modifiers: ModifiersState::empty(),
+            key_bindings,
+            rebinding: None,
+            period_detector: stats::PeriodDetector::default(),
+            detected_period: None,
+            detected_velocity: None,
+            status: stats::SimStatus::Running,
+            auto_pause: false,
+            pause_on_unfocus: true,
+            paused_for_focus: false,
+            run_target: None,
+            run_fast: false,
+            turbo: false,
+            held_pan_keys: HashSet::new(),
+            cell_changes: CellChanges::default(),
+            highlight_changes: false,
+            last_born: LivingList::default(),
+            last_died: LivingList::default(),
+            fade_out: None,
+            fading: LivingList::default(),
+            fade_started: None,
+            #[cfg(feature = "random")]
+            random_seed: 0,
         }
     }
 
     pub fn step(&mut self) {
-        self.living_cells = compute_step(&self.living_cells);
-        self.changes.cells = Some(self.get_cells());
-        self.step_count += 1;
-        self.living_cell_count = self.living_cells.len();
-        self.living_count_history.push(self.living_cell_count);
+        self.step_now();
+    }
+
+    /// Whether a step is currently being computed. Without a worker thread,
+    /// steps are computed synchronously, so this is always false.
+    pub fn is_computing(&self) -> bool {
+        false
+    }
+
+    /// Steps are computed synchronously without the `native_threads` feature,
+    /// so there's never one to finish.
+    fn finish_pending_step(&mut self) {}
+
+    /// Steps are computed synchronously without the `native_threads` feature,
+    /// so one is never in progress.
+    pub fn computing_time(&self) -> Option<Duration> {
+        None
     }
 
+    /// There's no worker thread to die without the `native_threads` feature.
+    pub fn worker_died(&self) -> bool {
+        false
+    }
+
+    /// Kill every cell, take down the walls, and start the generation count,
+    /// history and statistics over, the same as with the worker thread.
     pub fn clear(&mut self) {
-        self.living_cells.clear();
-        self.changes.cells = Some(Vec::new());
+        self.clear_action();
     }
 
     #[cfg(feature = "saving")]
@@ -562,23 +3005,31 @@ impl GameState {
         self.load_action(save.clone());
     }
 
-    fn handle_left(&mut self, mouse_position: Vector2<f64>) {
-        let size = self.window.inner_size();
-        let cell_pos = find_cell_num(size, mouse_position, self.pan_position, self.grid_size);
-
-        self.left_action(cell_pos);
-    }
-
     pub fn update(&mut self) -> StateChanges {
-        let should_step = self.loop_state.update(&self.interval);
-
-        if should_step {
-            self.step();
+        self.update_camera_transition();
+        if self.is_running_fast() {
+            let start = Instant::now();
+            while self.is_running_fast() && start.elapsed() < RUN_FAST_BUDGET {
+                self.step();
+            }
+        } else {
+            for _ in 0..self.steps_due(MAX_STEPS_PER_FRAME) {
+                self.step();
+                // Autoplay may have stopped by itself
+                if !self.loop_state.is_playing() {
+                    break;
+                }
+            }
         }
 
         self.resolve_queue();
 
-        std::mem::take(&mut self.changes)
+        self.refresh_shape();
+        self.update_title();
+        let changes = self.take_changes();
+        #[cfg(feature = "saving")]
+        self.autosave_if_due();
+        changes
     }
 }
 
@@ -586,7 +3037,17 @@ impl GameState {
 enum StepThreadNotification {
     Exit,
     Waiting,
-    Compute(LivingList),
+    /// Step the first set of cells with the walls in the second, putting the
+    /// next generation in the third so that the worker doesn't have to
+    /// allocate one. The rule is boxed since its count tables are much bigger
+    /// than the rest.
+    Compute(
+        LivingList,
+        Arc<LivingList>,
+        LivingList,
+        Box<rules::Ruleset>,
+        boundary::BoundaryMode,
+    ),
 }
 
 #[cfg(feature = "native_threads")]
@@ -596,35 +3057,153 @@ struct SharedThreadData {
     computing: AtomicBool,
 }
 
+#[cfg(feature = "native_threads")]
+impl SharedThreadData {
+    /// Lock the notification. Nothing that can panic happens while it's held,
+    /// and it's only ever replaced as a whole, so poisoning is ignored.
+    fn lock_notification(&self) -> sync::MutexGuard<StepThreadNotification> {
+        self.notification
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Give the worker thread something to do
+    fn notify(&self, notification: StepThreadNotification) {
+        *self.lock_notification() = notification;
+        self.condvar.notify_all();
+    }
+}
+
 #[cfg(feature = "native_threads")]
 struct ThreadData {
     shared: Arc<SharedThreadData>,
     local: LocalThreadData,
 }
 
+#[cfg(feature = "native_threads")]
+impl ThreadData {
+    /// Start a worker thread that computes steps when told to
+    fn spawn() -> Self {
+        use StepThreadNotification as STN;
+        let (tx, rx) = mpsc::channel();
+        let shared = Arc::new(SharedThreadData {
+            condvar: Condvar::new(),
+            notification: Mutex::new(StepThreadNotification::Waiting),
+            computing: AtomicBool::new(false),
+        });
+        let join_handle = {
+            let thread_data = Arc::clone(&shared);
+            std::thread::spawn(move || {
+                // Kept from one step to the next, like on the main thread
+                let mut buffers = StepBuffers::default();
+                let mut data_guard = thread_data.lock_notification();
+                loop {
+                    // Take the data out so the lock isn't held while computing
+                    match std::mem::replace(&mut *data_guard, STN::Waiting) {
+                        STN::Exit => break,
+                        STN::Waiting => {
+                            data_guard = thread_data
+                                .condvar
+                                .wait(data_guard)
+                                .unwrap_or_else(PoisonError::into_inner);
+                        }
+                        STN::Compute(data, walls, mut next, ruleset, boundary) => {
+                            thread_data
+                                .computing
+                                .store(true, sync::atomic::Ordering::Relaxed);
+                            drop(data_guard);
+                            compute_step_into(
+                                &data,
+                                &walls,
+                                &ruleset,
+                                &boundary,
+                                &mut buffers,
+                                &mut next,
+                            );
+                            // If the receiver is gone, so is the game, and
+                            // there's nothing left to do.
+                            if tx.send(next).is_err() {
+                                break;
+                            }
+                            data_guard = thread_data.lock_notification();
+                        }
+                    }
+                }
+            })
+        };
+
+        Self {
+            shared,
+            local: LocalThreadData {
+                join_handle,
+                rx,
+                step_started: None,
+            },
+        }
+    }
+
+    /// Whether the worker thread has been given a step that hasn't been
+    /// received yet. If the worker died, the step is never coming, so it's
+    /// forgotten.
+    fn is_computing(&self) -> bool {
+        // The worker sets `computing` before releasing the lock after taking a
+        // step to compute, so the lock has to be taken first to not miss it.
+        let mut notification = self.shared.lock_notification();
+        if self.local.join_handle.is_finished() {
+            *notification = StepThreadNotification::Waiting;
+            self.shared
+                .computing
+                .store(false, atomic::Ordering::Relaxed);
+            return false;
+        }
+        matches!(*notification, StepThreadNotification::Compute(..))
+            || self.shared.computing.load(atomic::Ordering::Relaxed)
+    }
+}
+
 #[cfg(feature = "native_threads")]
 struct LocalThreadData {
-    // The join handle is good to have around, so we'll keep it here even though
-    // it's unused.
-    #[allow(dead_code)]
+    /// Used to tell if the worker thread has died
     join_handle: JoinHandle<()>,
     rx: mpsc::Receiver<LivingList>,
+    /// When the worker thread was last given a step to compute
+    step_started: Option<Instant>,
 }
 
 #[derive(Default)]
 pub struct StateChanges {
     pub grid_size: Option<f32>,
     pub cells: Option<Vec<Cell>>,
+    /// Cells that were born and died, when not all of them are in `cells`
+    pub cell_delta: Option<CellDelta>,
     pub offset: Option<Vector2<f64>>,
 }
 
+/// What a generation came to, for `GameState::set_on_step`
+#[derive(Clone, Copy, Debug)]
+pub struct StepInfo {
+    /// The number of steps taken, counting this one
+    pub generation: u64,
+    /// The number of living cells
+    pub population: usize,
+    /// Whether the pattern has died out or stopped changing
+    pub status: stats::SimStatus,
+}
+
 impl std::ops::AddAssign<StateChanges> for StateChanges {
     fn add_assign(&mut self, other: StateChanges) {
         if other.grid_size.is_some() {
             self.grid_size = other.grid_size
         };
         if other.cells.is_some() {
-            self.cells = other.cells
+            self.cells = other.cells;
+            self.cell_delta = None;
+        };
+        if let Some(delta) = other.cell_delta {
+            match &mut self.cell_delta {
+                Some(d) => d.merge(delta),
+                None => self.cell_delta = Some(delta),
+            }
         };
         if other.offset.is_some() {
             self.offset = other.offset
@@ -633,7 +3212,16 @@ impl std::ops::AddAssign<StateChanges> for StateChanges {
 }
 
 pub enum LoopState {
-    Playing { last_update: Instant },
+    Playing {
+        last_update: Instant,
+        /// Time that has passed without a step being taken for it
+        owed: Duration,
+    },
+    /// Paused partway through an interval
+    Paused {
+        /// How long was left until the next step
+        remaining: Duration,
+    },
     Stopped,
 }
 
@@ -642,91 +3230,526 @@ impl LoopState {
         Self::Stopped
     }
 
+    /// Start playing from now
+    fn playing() -> Self {
+        Self::Playing {
+            last_update: Instant::now(),
+            owed: Duration::ZERO,
+        }
+    }
+
+    /// Start playing again after being paused with `remaining` left until
+    /// the next step. If the interval changed in the meantime, the time that
+    /// had already passed counts towards the new one.
+    fn resumed(remaining: Duration, interval: &Duration) -> Self {
+        let elapsed = interval.saturating_sub(remaining);
+        let now = Instant::now();
+        Self::Playing {
+            last_update: now.checked_sub(elapsed).unwrap_or(now),
+            owed: Duration::ZERO,
+        }
+    }
+
+    /// Stop playing, remembering how long is left until the next step. Does
+    /// nothing if not playing.
+    fn pause(&mut self, interval: &Duration) {
+        if let Self::Playing { last_update, owed } = self {
+            let elapsed = *owed + last_update.elapsed();
+            *self = Self::Paused {
+                remaining: interval.saturating_sub(elapsed),
+            };
+        }
+    }
+
     #[allow(dead_code)]
     fn should_step(&self, interval: &Duration) -> bool {
-        if let Self::Playing { last_update } = self {
+        if let Self::Playing { last_update, .. } = self {
             last_update.elapsed() >= *interval
         } else {
             false
         }
     }
 
-    /// Updates the `last_update` field if playing.
-    /// Otherwise, this is a no-op
-    fn update(&mut self, interval: &Duration) -> bool {
-        if let Self::Playing { last_update } = self {
-            if last_update.elapsed() >= *interval {
-                *self = Self::Playing {
-                    last_update: Instant::now(),
-                };
-                true
-            } else {
-                false
-            }
-        } else {
-            false
+    /// Like `update`, but keeps to a fixed schedule instead of waiting a full
+    /// interval after the last step. Returns the number of steps due, up to
+    /// `max_steps`, and the number past that which were skipped.
+    fn update_strict(&mut self, interval: &Duration, max_steps: u64) -> (u64, u64) {
+        let Self::Playing { last_update, .. } = self else {
+            return (0, 0);
+        };
+        if interval.is_zero() {
+            return (max_steps, 0);
+        }
+        let elapsed = last_update.elapsed();
+        let due = (elapsed.as_secs_f64() / interval.as_secs_f64()) as u64;
+        *last_update += interval.mul_f64(due as f64);
+        let steps = due.min(max_steps);
+        (steps, due - steps)
+    }
+
+    /// Add the time since the last update to the time owed, and take as many
+    /// whole intervals out of it as there are steps due, up to `max_steps`.
+    /// Returns the number of steps to take. Time is never owed for more than
+    /// `MAX_STEPS_PER_FRAME` steps, so the rest is dropped.
+    ///
+    /// If not playing, this is a no-op.
+    fn update(&mut self, interval: &Duration, max_steps: u64) -> u64 {
+        let Self::Playing { last_update, owed } = self else {
+            return 0;
+        };
+        if interval.is_zero() {
+            return max_steps;
         }
+        let now = Instant::now();
+        *owed = (*owed + (now - *last_update)).min(interval.mul_f64(MAX_STEPS_PER_FRAME as f64));
+        *last_update = now;
+
+        let due = (owed.as_secs_f64() / interval.as_secs_f64()) as u64;
+        let steps = due.min(max_steps);
+        *owed = owed.saturating_sub(interval.mul_f64(steps as f64));
+        steps
     }
 
     fn is_playing(&self) -> bool {
         match self {
-            Self::Stopped => false,
+            Self::Stopped | Self::Paused { .. } => false,
             Self::Playing { .. } => true,
         }
     }
 }
 
+/// Where the view is and how far it is zoomed
+#[derive(Clone, Copy, PartialEq)]
+struct Camera {
+    pan_position: Vector2<f64>,
+    grid_size: f32,
+}
+
+impl Camera {
+    /// The point in the center of the window, in cell coordinates
+    fn center(&self) -> Vector2<f64> {
+        (self.pan_position + Vector2::new(0.5, 0.5)) / self.grid_size as f64
+    }
+}
+
+/// The view gliding from one camera to another
+#[derive(Clone, Copy)]
+struct CameraTransition {
+    from: Camera,
+    to: Camera,
+    /// The camera last shown, to tell when something else moves the view
+    shown: Camera,
+    started: Instant,
+}
+
+impl CameraTransition {
+    /// The camera `t` of the way through, from 0 to 1, easing in and out at
+    /// the ends. The zoom changes by the same factor every frame, so that it
+    /// doesn't seem to speed up as the cells get bigger.
+    fn at(&self, t: f64) -> Camera {
+        let t = t * t * (3.0 - 2.0 * t);
+        let (from, to) = (self.from.grid_size as f64, self.to.grid_size as f64);
+        let grid_size = (from * (to / from).powf(t)) as f32;
+        let center = Vector2::<f64>::lerp_unclamped(self.from.center(), self.to.center(), t);
+        Camera {
+            pan_position: pan_centered_on(center, grid_size),
+            grid_size,
+        }
+    }
+}
+
+/// The changes to the living cells that haven't been passed on to the
+/// renderer, either as the cells that were born and died, or as a note that
+/// everything has to be sent again.
+#[derive(Default)]
+struct CellChanges {
+    all: bool,
+    born: LivingList,
+    died: LivingList,
+}
+
+/// How the renderer has to be told about changes to the living cells
+enum CellUpdate {
+    All,
+    Delta { born: LivingList, died: LivingList },
+}
+
+impl CellChanges {
+    /// Note that everything has to be sent again, such as when the board is
+    /// replaced or the grid size changes.
+    fn refresh_all(&mut self) {
+        self.all = true;
+        self.born.clear();
+        self.died.clear();
+    }
+
+    fn born(&mut self, cell: Vector2<i32>) {
+        if !self.all && !self.died.remove(&cell) {
+            self.born.insert(cell);
+        }
+    }
+
+    fn died(&mut self, cell: Vector2<i32>) {
+        if !self.all && !self.born.remove(&cell) {
+            self.died.insert(cell);
+        }
+    }
+
+    /// Record the differences between two generations
+    fn step(&mut self, prev: &LivingList, next: &LivingList) {
+        if self.all {
+            return;
+        }
+        for cell in next.iter().filter(|c| !prev.contains(c)) {
+            self.born(*cell);
+        }
+        for cell in prev.iter().filter(|c| !next.contains(c)) {
+            self.died(*cell);
+        }
+    }
+
+    /// Take the changes, if there are any. When more cells changed than are
+    /// alive, sending everything is cheaper.
+    fn take(&mut self, living_count: usize) -> Option<CellUpdate> {
+        let update = if self.all || self.born.len() + self.died.len() > living_count {
+            Some(CellUpdate::All)
+        } else if !self.born.is_empty() || !self.died.is_empty() {
+            Some(CellUpdate::Delta {
+                born: std::mem::take(&mut self.born),
+                died: std::mem::take(&mut self.died),
+            })
+        } else {
+            None
+        };
+        self.all = false;
+        self.born.clear();
+        self.died.clear();
+        update
+    }
+}
+
 enum DragState {
     Dragging { prev_pos: Vector2<f64> },
     NotDragging,
 }
 
-#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+/// A direction on the board
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+/// What clicking and dragging does to cells
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tool {
+    /// Flip cells between alive and dead. Dragging paints the state the first
+    /// cell was flipped to.
+    Toggle,
+    /// Bring cells to life
+    Draw,
+    /// Kill cells
+    Erase,
+}
+
+/// Which mirror images of a cell are drawn along with it, through the middle
+/// of the view
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Symmetry {
+    /// Only the cell itself
+    None,
+    /// Mirrored left to right
+    X,
+    /// Mirrored top to bottom
+    Y,
+    /// Mirrored both ways, for four cells in all
+    Both,
+}
+
+/// Which mouse buttons drag the view around. The right button can bring up a
+/// context menu on some platforms, so the middle one can be used instead.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PanButton {
+    Right,
+    Middle,
+    /// Both the right and middle buttons
+    Either,
+}
+
+impl PanButton {
+    fn pans_with(self, button: MouseButton) -> bool {
+        matches!(
+            (self, button),
+            (Self::Right | Self::Either, MouseButton::Right)
+                | (Self::Middle | Self::Either, MouseButton::Middle)
+        )
+    }
+}
+
+/// A modifier key that changes what scrolling does
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StepModifier {
+    Control,
+    Shift,
+    Alt,
+}
+
+impl StepModifier {
+    fn is_held(self, modifiers: ModifiersState) -> bool {
+        match self {
+            Self::Control => modifiers.control_key(),
+            Self::Shift => modifiers.shift_key(),
+            Self::Alt => modifiers.alt_key(),
+        }
+    }
+}
+
+/// A pattern waiting to be stamped onto the board
+struct Stamp {
+    pattern: patterns::Pattern,
+    /// The cell under the cursor, where the pattern is previewed
+    cell: Option<Vector2<i32>>,
+}
+
+impl Stamp {
+    /// The cells of the pattern when it's centered on `center`
+    fn cells_at(&self, center: Vector2<i32>) -> impl Iterator<Item = Vector2<i32>> + '_ {
+        let corner = center - Vector2::new(self.pattern.width / 2, self.pattern.height / 2);
+        self.pattern.cells.iter().map(move |c| *c + corner)
+    }
+
+    /// Turn the pattern a quarter turn clockwise, keeping its corner at the
+    /// origin
+    fn rotate(&mut self) {
+        let pattern = &mut self.pattern;
+        for cell in &mut pattern.cells {
+            *cell = Vector2::new(pattern.height - 1 - cell.y, cell.x);
+        }
+        std::mem::swap(&mut pattern.width, &mut pattern.height);
+    }
+}
+
+/// The ways a pattern file can be written, told apart by its extension
+#[cfg(not(target_arch = "wasm32"))]
+enum PatternFormat {
+    Rle,
+    Plaintext,
+    #[cfg(feature = "saving")]
+    Save,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PatternFormat {
+    fn from_path(path: &std::path::Path) -> anyhow::Result<Self> {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "rle" => Ok(Self::Rle),
+            "cells" => Ok(Self::Plaintext),
+            #[cfg(feature = "saving")]
+            "json" => Ok(Self::Save),
+            _ => Err(anyhow::anyhow!(
+                "Unknown pattern format \"{extension}\", expected .rle, .cells or .json"
+            )),
+        }
+    }
+}
+
+/// A left click that changed cells, remembered so that if it turns out to be
+/// the first click of a double-click, the cells can be put back
+struct Click {
+    time: Instant,
+    /// The cell clicked on
+    cell: Vector2<i32>,
+    /// The generation clicked on. Once the board has stepped past it, the
+    /// cells in `before` are out of date and aren't put back.
+    generation: u64,
+    /// The cells the click changed, and whether each was alive before
+    before: Vec<(Vector2<i32>, bool)>,
+}
+
+impl Click {
+    /// Remember a click on the first of `cells` that's about to change them
+    fn new(sim: &simulation::Simulation, cells: &[Vector2<i32>], time: Instant) -> Self {
+        Self {
+            time,
+            cell: cells[0],
+            generation: sim.step_count(),
+            before: cells.iter().map(|c| (*c, sim.is_alive(*c))).collect(),
+        }
+    }
+
+    /// Whether a click on `cell` at `time` makes this one a double-click
+    fn is_doubled_by(
+        &self,
+        cell: Vector2<i32>,
+        time: Instant,
+        double_click_time: Duration,
+    ) -> bool {
+        cell == self.cell && time.saturating_duration_since(self.time) <= double_click_time
+    }
+}
+
+/// What a left click on cells does
+enum ClickOutcome {
+    /// Bring the cells to life or kill them
+    Single { alive: bool },
+    /// It's the second click of a double-click, so the zoom is toggled and the
+    /// cells the first click changed are put back the way they were
+    Double { before: Vec<(Vector2<i32>, bool)> },
+}
+
+/// Work out what a left click with `tool` does to `cells`, the first of which
+/// is the one clicked on and the rest its mirror images, and remember it in
+/// `last_click` unless it finishes a double-click
+fn click_cells(
+    last_click: &mut Option<Click>,
+    sim: &simulation::Simulation,
+    tool: Tool,
+    cells: &[Vector2<i32>],
+    now: Instant,
+    double_click_time: Duration,
+) -> ClickOutcome {
+    if let Some(click) = last_click.take()
+        && click.is_doubled_by(cells[0], now, double_click_time)
+    {
+        let before = if click.generation == sim.step_count() {
+            click.before
+        } else {
+            Vec::new()
+        };
+        return ClickOutcome::Double { before };
+    }
+    let alive = match tool {
+        Tool::Toggle => !sim.is_alive(cells[0]),
+        Tool::Draw => true,
+        Tool::Erase => false,
+    };
+    *last_click = Some(Click::new(sim, cells, now));
+    ClickOutcome::Single { alive }
+}
+
+/// Cells being painted by dragging with the left button held
+#[derive(Clone, Copy)]
+struct PaintStroke {
+    /// Whether the stroke makes cells alive or kills them
+    alive: bool,
+    /// The last cell painted, to draw a line from when the cursor moves
+    last_cell: Vector2<i32>,
+}
+
+#[cfg_attr(
+    any(target_arch = "wasm32", not(feature = "native_threads")),
+    allow(dead_code)
+)]
 enum QueueAction {
     Clear,
-    Toggle(Vector2<i32>),
+    Set(Vector2<i32>, bool),
     #[cfg(feature = "saving")]
     Load(SaveGame),
 }
 
-fn to_cell(cell: Vector2<i32>, grid_size: f32) -> Cell {
+/// Drop generations from the front of `history`, which is the oldest end,
+/// until there are at most `limit` left and they take up no more than
+/// `memory_limit` bytes
+fn trim_history(history: &mut VecDeque<LivingList>, limit: usize, memory_limit: Option<usize>) {
+    let mut memory: usize = history.iter().map(stats::set_memory).sum();
+    while history.len() > limit || memory_limit.is_some_and(|l| !history.is_empty() && memory > l) {
+        if let Some(oldest) = history.pop_front() {
+            memory -= stats::set_memory(&oldest);
+        }
+    }
+}
+
+/// Whether a key is one of the WASD keys that pan
+fn pans(key: &PhysicalKey) -> bool {
+    matches!(key, PhysicalKey::Code(code) if pan_key_direction(*code).is_some())
+}
+
+/// The way a panning key moves the view, or `None` if it isn't one
+fn pan_key_direction(code: KeyCode) -> Option<Vector2<f64>> {
+    match code {
+        KeyCode::KeyW => Some(Vector2::new(0.0, -1.0)),
+        KeyCode::KeyA => Some(Vector2::new(-1.0, 0.0)),
+        KeyCode::KeyS => Some(Vector2::new(0.0, 1.0)),
+        KeyCode::KeyD => Some(Vector2::new(1.0, 0.0)),
+        _ => None,
+    }
+}
+
+fn to_cell(coords: Vector2<i32>, grid_size: f32) -> Cell {
     let cell = Vector2::new(
-        cell.x as f32 * grid_size + grid_size / 2.0,
-        cell.y as f32 * grid_size + grid_size / 2.0,
+        coords.x as f32 * grid_size + grid_size / 2.0,
+        coords.y as f32 * grid_size + grid_size / 2.0,
     );
     Cell {
         // location: [cell.x - pan.x as f32, cell.y - (pan.y as f32)],
         location: [cell.x, cell.y],
+        coords: coords.into(),
+        highlight: Highlight::None,
+        level: 0.0,
     }
 }
 
-fn get_adjacent(coords: &Vector2<i32>) -> [Vector2<i32>; 8] {
-    [
-        [coords.x - 1, coords.y - 1].into(),
-        [coords.x - 1, coords.y + 1].into(),
-        [coords.x - 1, coords.y].into(),
-        [coords.x, coords.y - 1].into(),
-        [coords.x, coords.y + 1].into(),
-        [coords.x + 1, coords.y].into(),
-        [coords.x + 1, coords.y - 1].into(),
-        [coords.x + 1, coords.y + 1].into(),
-    ]
+/// The neighbors of a cell, given the offsets to them from
+/// `rules::Neighborhood::offsets`, wrapped onto the board
+fn get_neighbors<'a>(
+    coords: &'a Vector2<i32>,
+    offsets: &'a [Vector2<i32>],
+    boundary: &'a boundary::BoundaryMode,
+) -> impl Iterator<Item = Vector2<i32>> + 'a {
+    offsets.iter().map(|o| boundary.wrap(*coords + *o))
 }
 
-fn find_cell_num(
-    size: PhysicalSize<u32>,
-    position: Vector2<f64>,
-    offset: Vector2<f64>,
-    grid_size: f32,
-) -> Vector2<i32> {
+/// Find the pan position that puts a point, in cell coordinates, in the center
+/// of the window.
+fn pan_centered_on(point: Vector2<f64>, grid_size: f32) -> Vector2<f64> {
+    // The view is one unit tall with the center of the window at (0.5, 0.5),
+    // and cells are `grid_size` units across.
+    point * grid_size as f64 - Vector2::new(0.5, 0.5)
+}
+
+/// Where a point in the window, in pixels, is in the view, which is one unit
+/// tall with the center of the window at (0.5, 0.5).
+fn view_position(size: PhysicalSize<u32>, position: Vector2<f64>) -> Vector2<f64> {
     let aspect_ratio = size.width as f64 / size.height as f64;
     let shift_amount = (size.width as f64 - size.height as f64) / 2.0;
     let x_shifted = position.x - shift_amount;
     let x_scaled = x_shifted * aspect_ratio;
-    let position_scaled = Vector2::<f64>::scale(
+    Vector2::<f64>::scale(
         Vector2::new(x_scaled, position.y),
         Vector2::new((size.width as f64).recip(), (size.height as f64).recip()),
-    );
+    )
+}
+
+/// Where the view has to be panned to after zooming from `prev_size` to
+/// `grid_size` for the point at `anchor` in the window to stay over the same
+/// spot on the board.
+fn zoomed_pan(
+    size: PhysicalSize<u32>,
+    anchor: Vector2<f64>,
+    pan: Vector2<f64>,
+    prev_size: f32,
+    grid_size: f32,
+) -> Vector2<f64> {
+    // A spot on the board is at (view position + pan) / grid size. The scale
+    // has to come from the sizes that were actually used, which aren't what
+    // was asked for when the zoom is clamped, and be worked out in f64 so
+    // that rounding doesn't make the spot drift when zoomed far in.
+    let scale = grid_size as f64 / prev_size as f64;
+    let anchor = view_position(size, anchor);
+    (anchor + pan) * scale - anchor
+}
+
+fn find_cell_num(
+    size: PhysicalSize<u32>,
+    position: Vector2<f64>,
+    offset: Vector2<f64>,
+    grid_size: f32,
+) -> Vector2<i32> {
+    let position_scaled = view_position(size, position);
     let final_position = (position_scaled / grid_size.into()) + (offset / grid_size as f64);
     Vector2::new(
         final_position.x.floor() as i32,
@@ -734,29 +3757,98 @@ fn find_cell_num(
     )
 }
 
-fn compute_step(prev: &LivingList) -> LivingList {
-    let mut adjacency_rec: FxHashMap<Vector2<i32>, u32> = FxHashMap::default();
-
-    for i in prev.iter() {
-        for j in get_adjacent(i) {
-            if let Some(c) = adjacency_rec.get(&j) {
-                adjacency_rec.insert(j, *c + 1);
-            } else {
-                adjacency_rec.insert(j, 1);
-            }
+/// The cells on a line between two cells, including both ends, in order and
+/// with each one touching the last.
+fn cells_on_line(from: Vector2<i32>, to: Vector2<i32>) -> Vec<Vector2<i32>> {
+    // Bresenham's line algorithm
+    let dx = (to.x - from.x).abs();
+    let dy = -(to.y - from.y).abs();
+    let step = Vector2::new((to.x - from.x).signum(), (to.y - from.y).signum());
+    let mut error = dx + dy;
+    let mut cell = from;
+    let mut cells = vec![cell];
+    while cell != to {
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            cell.x += step.x;
         }
+        if doubled <= dx {
+            error += dx;
+            cell.y += step.y;
+        }
+        cells.push(cell);
     }
+    cells
+}
 
-    adjacency_rec
-        .into_iter()
-        .filter(|(coords, count)| alive_rules(count, prev, coords))
-        .map(|(coords, _count)| coords)
-        .collect()
+/// What computing a step needs besides the board, kept from one step to the
+/// next so that stepping doesn't have to allocate once it's grown to the size
+/// of the board
+#[derive(Default)]
+struct StepBuffers {
+    /// The number of living neighbors of every cell next to a living one
+    adjacency: CellMap<u32>,
+    /// How many more steps to go cell by cell before checking again whether
+    /// the board is crowded enough to step by chunks
+    sparse_steps: u32,
 }
 
-#[inline(always)]
-fn alive_rules(count: &u32, prev: &LivingList, coords: &Vector2<i32>) -> bool {
-    3 == *count || (2 == *count && prev.contains(coords))
+/// Compute the next generation into `next`, which is cleared first and keeps
+/// its capacity so it can be reused without allocating. Walls count as
+/// neighbors of the cells around them, but are never alive themselves.
+fn compute_step_into(
+    prev: &LivingList,
+    walls: &LivingList,
+    ruleset: &rules::Ruleset,
+    boundary: &boundary::BoundaryMode,
+    buffers: &mut StepBuffers,
+    next: &mut LivingList,
+) {
+    let adjacency = &mut buffers.adjacency;
+    adjacency.clear();
+    next.clear();
+
+    // Crowded boards are much faster to step a chunk at a time, even with the
+    // chunks built from scratch every step. Chunks don't line up with the
+    // edges of a wrapping board, and only count the 8 touching neighbors, so
+    // everything else goes cell by cell. Neither do they know about walls.
+    if *boundary == boundary::BoundaryMode::Infinite
+        && ruleset.neighborhood == rules::Neighborhood::default()
+        && walls.is_empty()
+    {
+        if buffers.sparse_steps > 0 {
+            buffers.sparse_steps -= 1;
+        } else if let Some(board) = chunks::ChunkedBoard::from_dense_cells(prev) {
+            board.step(ruleset).write_cells(next);
+            return;
+        } else {
+            // Finding out costs about a ninth of a step, and boards don't get
+            // crowded all of a sudden
+            buffers.sparse_steps = chunks::SPARSE_RECHECK_STEPS;
+        }
+    }
+
+    let offsets = ruleset.neighborhood.offsets();
+    for i in prev.iter().chain(walls) {
+        for j in get_neighbors(i, &offsets, boundary) {
+            *adjacency.entry(j).or_insert(0) += 1;
+        }
+    }
+
+    // Cells with no living neighbors aren't in the map at all, so the ones
+    // that survive that have to be found separately.
+    if ruleset.survive[0] {
+        next.extend(prev.iter().filter(|c| !adjacency.contains_key(c)));
+    }
+    next.extend(
+        adjacency
+            .drain()
+            .filter(|(coords, count)| {
+                ruleset.alive(*count, || prev.contains(coords)) && !walls.contains(coords)
+            })
+            .map(|(coords, _count)| coords),
+    );
 }
 
 impl Drop for GameState {
@@ -764,14 +3856,250 @@ impl Drop for GameState {
         #[cfg(feature = "native_threads")]
         {
             // Terminate the processing thread
-            let mut noti_lock = self.thread_data.shared.notification.lock().unwrap();
-            *noti_lock = StepThreadNotification::Exit;
+            self.thread_data
+                .shared
+                .notify(StepThreadNotification::Exit);
         }
 
-        // Write the save file to the disk
+        // Write anything that hasn't been autosaved yet to the disk. With
+        // autosaving off, only the saves made by hand are kept.
         #[cfg(feature = "saving")]
-        if let Err(e) = std::mem::take(&mut self.save_file).unwrap().write_to_disk() {
-            log::error!("Failed to write saves with error:\n{}", e);
+        {
+            if self.autosave_interval.is_some() {
+                self.autosave();
+            } else {
+                self.write_save_file();
+            }
+            if let Err(e) = self.slot_storage.finish() {
+                log::error!("Failed to finish writing the save slots: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A generation of `size` cells that all have `generation` as their x
+    fn generation(generation: i32, size: i32) -> LivingList {
+        (0..size).map(|y| Vector2::new(generation, y)).collect()
+    }
+
+    fn generations(history: &VecDeque<LivingList>) -> Vec<i32> {
+        history
+            .iter()
+            .map(|cells| cells.iter().next().unwrap().x)
+            .collect()
+    }
+
+    #[cfg(feature = "native_threads")]
+    #[test]
+    fn worker_dying_mid_step() {
+        use StepThreadNotification as STN;
+        let shared = Arc::new(SharedThreadData {
+            condvar: Condvar::new(),
+            notification: Mutex::new(STN::Waiting),
+            computing: AtomicBool::new(false),
+        });
+        let (tx, rx) = mpsc::channel::<LivingList>();
+        let (die_tx, die_rx) = mpsc::channel::<()>();
+        // A worker that takes a step and stops before sending it back
+        let join_handle = {
+            let shared = Arc::clone(&shared);
+            std::thread::spawn(move || {
+                let _tx = tx;
+                let mut notification = shared.lock_notification();
+                while !matches!(*notification, STN::Compute(..)) {
+                    notification = shared.condvar.wait(notification).unwrap();
+                }
+                *notification = STN::Waiting;
+                shared.computing.store(true, atomic::Ordering::Relaxed);
+                drop(notification);
+                die_rx.recv().unwrap();
+            })
         };
+        let thread_data = ThreadData {
+            shared,
+            local: LocalThreadData {
+                join_handle,
+                rx,
+                step_started: None,
+            },
+        };
+
+        thread_data.shared.notify(STN::Compute(
+            generation(0, 3),
+            Arc::default(),
+            LivingList::default(),
+            Box::default(),
+            boundary::BoundaryMode::Infinite,
+        ));
+        assert!(thread_data.is_computing());
+        die_tx.send(()).unwrap();
+        while !thread_data.local.join_handle.is_finished() {
+            std::thread::yield_now();
+        }
+        assert!(!thread_data.is_computing());
+        assert!(!thread_data.shared.computing.load(atomic::Ordering::Relaxed));
+        assert!(thread_data.local.rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn double_click_puts_cells_back() {
+        let cells = [Vector2::new(0, 0), Vector2::new(3, 0), Vector2::new(0, 3)];
+        let double_click_time = Duration::from_millis(300);
+        // Clicks the way `GameState::handle_click` makes them, returning
+        // whether it was a double-click
+        let click = |sim: &mut simulation::Simulation,
+                     last_click: &mut Option<Click>,
+                     tool,
+                     cells: &[Vector2<i32>],
+                     time| {
+            let changes = match click_cells(last_click, sim, tool, cells, time, double_click_time) {
+                ClickOutcome::Single { alive } => cells.iter().map(|c| (*c, alive)).collect(),
+                ClickOutcome::Double { before } => before,
+            };
+            for (cell, alive) in changes {
+                if sim.is_alive(cell) != alive {
+                    sim.toggle(cell);
+                }
+            }
+            last_click.is_none()
+        };
+        for tool in [Tool::Toggle, Tool::Draw, Tool::Erase] {
+            let mut sim = simulation::Simulation::new();
+            sim.toggle(cells[1]);
+            let start = Instant::now();
+
+            // Too slow, then on a different cell, then a double-click
+            let mut last_click = None;
+            assert!(!click(&mut sim, &mut last_click, tool, &cells, start));
+            let late = start + 2 * double_click_time;
+            assert!(!click(&mut sim, &mut last_click, tool, &cells, late));
+            let elsewhere = [Vector2::new(9, 9)];
+            assert!(!click(&mut sim, &mut last_click, tool, &elsewhere, late));
+            // Mix up the cells so that every tool changes some of them
+            sim.toggle(cells[1]);
+            let before = cells.map(|c| sim.is_alive(c));
+            assert!(!click(&mut sim, &mut last_click, tool, &cells, late));
+            let soon = late + double_click_time / 2;
+            assert!(click(&mut sim, &mut last_click, tool, &cells, soon));
+            assert_eq!(cells.map(|c| sim.is_alive(c)), before, "{tool:?}");
+        }
+    }
+
+    #[test]
+    fn double_click_after_a_step_leaves_the_cells() {
+        let blinker = [Vector2::new(-1, 0), Vector2::new(0, 0), Vector2::new(1, 0)];
+        let double_click_time = Duration::from_millis(300);
+        let mut sim = simulation::Simulation::new();
+        for cell in &blinker[1..] {
+            sim.toggle(*cell);
+        }
+        let start = Instant::now();
+        let mut last_click = None;
+        let first = click_cells(
+            &mut last_click,
+            &sim,
+            Tool::Draw,
+            &blinker[..1],
+            start,
+            double_click_time,
+        );
+        assert!(matches!(first, ClickOutcome::Single { alive: true }));
+        sim.toggle(blinker[0]);
+        sim.step();
+        let second = click_cells(
+            &mut last_click,
+            &sim,
+            Tool::Draw,
+            &blinker[..1],
+            start + double_click_time / 2,
+            double_click_time,
+        );
+        // The cell clicked on died in the step, and putting it back as it was
+        // before the first click would be wrong either way
+        assert!(matches!(second, ClickOutcome::Double { before } if before.is_empty()));
+    }
+
+    #[test]
+    fn camera_transition_ends_where_it_should() {
+        let from = Camera {
+            pan_position: Vector2::new(-0.5, -0.5),
+            grid_size: 0.05,
+        };
+        let to = Camera {
+            pan_position: Vector2::new(12.0, 3.5),
+            grid_size: 0.2,
+        };
+        let transition = CameraTransition {
+            from,
+            to,
+            shown: from,
+            started: Instant::now(),
+        };
+        for (t, camera) in [(0.0, from), (1.0, to)] {
+            let at = transition.at(t);
+            assert!((at.grid_size - camera.grid_size).abs() < 1e-6);
+            assert!(Vector2::<f64>::distance(at.pan_position, camera.pan_position) < 1e-6);
+        }
+        // Halfway through, the zoom is halfway by factor
+        assert!((transition.at(0.5).grid_size - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn history_drops_the_oldest_first() {
+        let mut history: VecDeque<_> = (0..8).map(|g| generation(g, 10 * (g + 1))).collect();
+        trim_history(&mut history, 5, None);
+        assert_eq!(generations(&history), [3, 4, 5, 6, 7]);
+
+        let newest_two = stats::set_memory(&history[3]) + stats::set_memory(&history[4]);
+        trim_history(&mut history, 5, Some(newest_two));
+        assert_eq!(generations(&history), [6, 7]);
+
+        // A cap smaller than a generation leaves none
+        trim_history(&mut history, 5, Some(1));
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn strict_rate_only_drops_steps_past_the_cap() {
+        let interval = Duration::from_millis(10);
+        let behind = |intervals: u32| LoopState::Playing {
+            last_update: Instant::now() - interval * intervals - interval / 2,
+            owed: Duration::ZERO,
+        };
+        assert_eq!(behind(5).update_strict(&interval, 8), (5, 0));
+        assert_eq!(behind(5).update_strict(&interval, 2), (2, 3));
+        assert_eq!(behind(0).update_strict(&interval, 8), (0, 0));
+        assert_eq!(LoopState::new().update_strict(&interval, 8), (0, 0));
+    }
+
+    #[test]
+    fn zooming_keeps_the_anchor_in_place() {
+        let size = PhysicalSize::new(1280, 720);
+        let pan = Vector2::new(3.25, -1.5);
+        let prev_size = 0.05;
+        let anchors = [
+            Vector2::new(0.0, 0.0),
+            Vector2::new(640.0, 360.0),
+            Vector2::new(1279.0, 13.5),
+            Vector2::new(-40.0, 900.0),
+        ];
+        for anchor in anchors {
+            for factor in [0.25, 0.9, 1.0, 1.1, 8.0] {
+                let grid_size = (prev_size as f64 * factor) as f32;
+                let new_pan = zoomed_pan(size, anchor, pan, prev_size, grid_size);
+                // The spot on the board under the anchor
+                let view = view_position(size, anchor);
+                let before = (view + pan) / prev_size as f64;
+                let after = (view + new_pan) / grid_size as f64;
+                assert!(
+                    Vector2::<f64>::distance(before, after) < 1e-9,
+                    "zooming by {factor} at {anchor:?} moved {before:?} to {after:?}",
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,430 @@
+//! Reading and writing patterns in the text formats used by other Life
+//! programs.
+
+use anyhow::{anyhow, Result};
+use vec2::Vector2;
+
+use super::rules::Ruleset;
+
+/// A pattern read from a file, with its cells relative to the top-left corner
+/// of its bounding box.
+pub struct Pattern {
+    pub cells: Vec<Vector2<i32>>,
+    /// Cells that never change, in the same frame as `cells`
+    pub walls: Vec<Vector2<i32>>,
+    pub width: i32,
+    pub height: i32,
+    /// The rule the pattern is meant for, if it says
+    pub rule: Option<Ruleset>,
+}
+
+/// The most living cells a pattern can have. Patterns come from files, links
+/// and the clipboard, so this keeps one from using up all of the memory.
+const MAX_PATTERN_CELLS: usize = 1 << 22;
+/// The most cells, living or dead, that a plaintext pattern can be written
+/// with. Plaintext has a character for every cell in the bounding box, so a
+/// few cells far apart would otherwise take more memory than there is.
+const MAX_PLAINTEXT_AREA: u64 = 1 << 24;
+
+/// Parse a pattern in the run length encoded format used by LifeWiki and most
+/// other Life programs.
+///
+/// Lines starting with `#` are comments. The header line gives the size and
+/// optionally the rule, like `x = 3, y = 3, rule = B3/S23`, and is followed by
+/// the body, which can be wrapped over any number of lines and ends with `!`.
+/// Bodies with cells outside the size in the header, or with more than
+/// `MAX_PATTERN_CELLS` cells, are turned down.
+///
+/// Walls are kept in `#W` lines, which hold a body of their own in the same
+/// frame as the cells. Other programs skip them like any other comment.
+pub fn parse_rle(text: &str) -> Result<Pattern> {
+    let lines = || text.lines().map(str::trim).filter(|l| !l.is_empty());
+    let mut body = lines().filter(|l| !l.starts_with('#'));
+
+    let header = body
+        .next()
+        .ok_or_else(|| anyhow!("The RLE has no header"))?;
+    let (width, height, rule) = parse_rle_header(header)?;
+    let cells = parse_rle_body(body, width, height)?;
+    let walls = parse_rle_body(lines().filter_map(|l| l.strip_prefix("#W")), width, height)?;
+
+    Ok(Pattern {
+        cells,
+        walls,
+        width,
+        height,
+        rule,
+    })
+}
+
+/// Parse the lines of an RLE body into the cells in it, checking that they fit
+/// in a pattern of the given size
+fn parse_rle_body<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    width: i32,
+    height: i32,
+) -> Result<Vec<Vector2<i32>>> {
+    let mut cells = Vec::new();
+    let mut position = Vector2::<i32>::new(0, 0);
+    let mut count: Option<i32> = None;
+    'body: for line in lines {
+        for c in line.chars() {
+            match c {
+                '0'..='9' => {
+                    let digit = c as i32 - '0' as i32;
+                    count = Some(
+                        count
+                            .unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|n| n.checked_add(digit))
+                            .ok_or_else(|| anyhow!("Run count is too large"))?,
+                    );
+                }
+                'b' | 'o' | '$' => {
+                    let run = count.take().unwrap_or(1);
+                    let overflow = || anyhow!("The RLE body runs past the size in its header");
+                    match c {
+                        'b' => position.x = position.x.checked_add(run).ok_or_else(overflow)?,
+                        'o' => {
+                            let end = position.x.checked_add(run).ok_or_else(overflow)?;
+                            if end > width || position.y >= height {
+                                return Err(overflow());
+                            }
+                            if cells.len() + run as usize > MAX_PATTERN_CELLS {
+                                return Err(anyhow!(
+                                    "The pattern has more than {MAX_PATTERN_CELLS} cells"
+                                ));
+                            }
+                            cells.extend((position.x..end).map(|x| Vector2::new(x, position.y)));
+                            position.x = end;
+                        }
+                        _ => {
+                            let y = position.y.checked_add(run).ok_or_else(overflow)?;
+                            position = Vector2::new(0, y);
+                        }
+                    }
+                }
+                '!' => break 'body,
+                c if c.is_whitespace() => (),
+                c => {
+                    return Err(anyhow!(
+                        "Unexpected `{c}` on line {} of the RLE body",
+                        position.y + 1
+                    ))
+                }
+            }
+        }
+    }
+    if count.is_some() {
+        return Err(anyhow!("The RLE body ends with a run count but no cell"));
+    }
+    Ok(cells)
+}
+
+/// The most characters on a line of RLE, as recommended by the format
+const RLE_LINE_LENGTH: usize = 70;
+
+/// Encode cells and walls as RLE, with the size of their bounding box and the
+/// given rule in the header. No cells gives a header saying the pattern is
+/// empty followed by an empty body.
+pub fn encode_rle(cells: &[Vector2<i32>], walls: &[Vector2<i32>], rule: &Ruleset) -> String {
+    let Some((min, max)) = bounding_box(cells.iter().chain(walls)) else {
+        return format!("x = 0, y = 0, rule = {rule}\n!\n");
+    };
+    let mut out = String::new();
+    if !walls.is_empty() {
+        push_wrapped(&mut out, "#W ", rle_body(walls, min));
+    }
+    out.push_str(&format!(
+        "x = {}, y = {}, rule = {rule}\n",
+        max.x - min.x + 1,
+        max.y - min.y + 1
+    ));
+    push_wrapped(&mut out, "", rle_body(cells, min));
+    out
+}
+
+/// The runs of an RLE body for `cells`, counted from `corner` and ending with
+/// `!`
+fn rle_body(cells: &[Vector2<i32>], corner: Vector2<i32>) -> Vec<String> {
+    let mut sorted = cells.to_vec();
+    sorted.sort_unstable_by_key(|c| (c.y, c.x));
+
+    // Runs of the same tag, merged as they're added
+    let mut runs: Vec<(i32, char)> = Vec::new();
+    let mut push = |count: i32, tag: char| match runs.last_mut() {
+        Some((n, last)) if *last == tag => *n += count,
+        _ => runs.push((count, tag)),
+    };
+    let mut position = corner;
+    for cell in sorted {
+        if cell.y > position.y {
+            push(cell.y - position.y, '$');
+            position = Vector2::new(corner.x, cell.y);
+        }
+        if cell.x > position.x {
+            push(cell.x - position.x, 'b');
+        }
+        push(1, 'o');
+        position.x = cell.x + 1;
+    }
+    runs.into_iter()
+        .map(|(n, tag)| {
+            if n == 1 {
+                tag.to_string()
+            } else {
+                format!("{n}{tag}")
+            }
+        })
+        .chain(std::iter::once("!".to_string()))
+        .collect()
+}
+
+/// Add the runs of an RLE body to `out`, wrapped into lines that each start
+/// with `prefix`
+fn push_wrapped(out: &mut String, prefix: &str, items: Vec<String>) {
+    out.push_str(prefix);
+    let mut line_length = prefix.len();
+    for item in items {
+        if line_length + item.len() > RLE_LINE_LENGTH {
+            out.push('\n');
+            out.push_str(prefix);
+            line_length = prefix.len();
+        }
+        line_length += item.len();
+        out.push_str(&item);
+    }
+    out.push('\n');
+}
+
+/// Parse a pattern in the plaintext `.cells` format, where each line is a row
+/// with `.` for dead cells and `O` for living ones. Lines starting with `!`
+/// are comments. Rows can be shorter than the pattern is wide, with the rest
+/// of the row dead.
+pub fn parse_plaintext(text: &str) -> Result<Pattern> {
+    let mut cells = Vec::new();
+    let (mut width, mut height) = (0, 0);
+    for line in text.lines().filter(|l| !l.starts_with('!')) {
+        let line = line.trim_end();
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                'O' | 'o' | '*' => cells.push(Vector2::new(x as i32, height)),
+                '.' => (),
+                c => return Err(anyhow!("Unexpected `{c}` on row {} of the pattern", height + 1)),
+            }
+        }
+        width = width.max(line.chars().count() as i32);
+        height += 1;
+    }
+    Ok(Pattern {
+        cells,
+        walls: Vec::new(),
+        width,
+        height,
+        rule: None,
+    })
+}
+
+/// Write cells in the plaintext `.cells` format, framed by their bounding box.
+/// No cells gives an empty string. Bounding boxes bigger than
+/// `MAX_PLAINTEXT_AREA` are turned down.
+pub fn encode_plaintext(cells: &[Vector2<i32>]) -> Result<String> {
+    let Some((min, max)) = bounding_box(cells) else {
+        return Ok(String::new());
+    };
+    // Worked out in 64 bits, since the sides can be wider than an i32
+    let width = (max.x as i64 - min.x as i64 + 1) as u64;
+    let height = (max.y as i64 - min.y as i64 + 1) as u64;
+    if width * height > MAX_PLAINTEXT_AREA {
+        return Err(anyhow!(
+            "The pattern is {width}\u{d7}{height} cells, which is too big to write as plaintext"
+        ));
+    }
+    let (width, height) = (width as usize, height as usize);
+    let mut rows = vec![vec![b'.'; width]; height];
+    for cell in cells {
+        rows[(cell.y - min.y) as usize][(cell.x - min.x) as usize] = b'O';
+    }
+    let mut out = String::with_capacity((width + 1) * height);
+    for row in rows {
+        // The rows are all `.` and `O`, so they're valid UTF-8
+        out.push_str(std::str::from_utf8(&row).unwrap());
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// The smallest and largest coordinates of some cells, or `None` if there are
+/// none.
+pub fn bounding_box<'a>(
+    cells: impl IntoIterator<Item = &'a Vector2<i32>>,
+) -> Option<(Vector2<i32>, Vector2<i32>)> {
+    let mut cells = cells.into_iter();
+    let first = *cells.next()?;
+    Some(cells.fold((first, first), |(min, max), c| {
+        (
+            Vector2::new(min.x.min(c.x), min.y.min(c.y)),
+            Vector2::new(max.x.max(c.x), max.y.max(c.y)),
+        )
+    }))
+}
+
+/// Parse an RLE header like `x = 3, y = 3, rule = B3/S23`
+fn parse_rle_header(header: &str) -> Result<(i32, i32, Option<Ruleset>)> {
+    let (mut width, mut height, mut rule) = (None, None, None);
+    let mut rest = header;
+    while !rest.trim().is_empty() {
+        let (key, after) = rest
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected `key = value` in the RLE header, got `{rest}`"))?;
+        // Rules with other neighborhoods have commas in them, like
+        // `R2,C0,M0,S6-9,B7-8,NM`, so the rule takes up the rest of the line
+        let (value, next) = if key.trim() == "rule" {
+            (after, "")
+        } else {
+            after.split_once(',').unwrap_or((after, ""))
+        };
+        rest = next;
+        let value = value.trim();
+        let size = |v: &str| {
+            v.parse::<i32>()
+                .ok()
+                .filter(|n| *n >= 0)
+                .ok_or_else(|| anyhow!("`{v}` isn't a valid size in the RLE header"))
+        };
+        match key.trim() {
+            "x" => width = Some(size(value)?),
+            "y" => height = Some(size(value)?),
+            "rule" => rule = Some(value.parse()?),
+            // Other programs add their own fields, which don't matter here
+            _ => (),
+        }
+    }
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height, rule)),
+        _ => Err(anyhow!("The RLE header needs both `x` and `y`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(rle: &str) -> Vec<(i32, i32)> {
+        let pattern = parse_rle(rle).unwrap();
+        pattern.cells.iter().map(|c| (c.x, c.y)).collect()
+    }
+
+    #[test]
+    fn glider() {
+        let pattern = parse_rle("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.rule, Some(Ruleset::default()));
+        assert_eq!(
+            pattern.cells.iter().map(|c| (c.x, c.y)).collect::<Vec<_>>(),
+            [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn ranged_rule_in_header() {
+        let pattern = parse_rle("x = 1, y = 1, rule = R2,C0,M0,S6-9,B7-8,NM\no!").unwrap();
+        let rule = pattern.rule.unwrap();
+        assert_eq!(rule.neighborhood.range(), 2);
+        assert_eq!(rule.to_string(), "R2,C0,M0,S6-9,B7-8,NM");
+        assert!(parse_rle("x = 1, y = 1, rule = R2,C0,M0,S6-9,B7-8,NM, z = 1\no!").is_err());
+    }
+
+    #[test]
+    fn multi_digit_runs() {
+        let cells = cells("x = 13, y = 12\n12bo$10$13o!");
+        assert_eq!(cells.len(), 14);
+        assert_eq!(cells[0], (12, 0));
+        assert_eq!(cells[1..], (0..13).map(|x| (x, 11)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn wrapped_lines_and_comments() {
+        let rle = "#N Glider\n#C A comment, with = in it\nx = 3, y = 3\nbo$2b\no$3\n#C Another\no!";
+        assert_eq!(cells(rle), [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn malformed_headers() {
+        for rle in [
+            "",
+            "#C Only a comment",
+            "bo$2bo$3o!",
+            "x = 3\nbo!",
+            "x = 3, y = -1\nbo!",
+            "x = three, y = 3\nbo!",
+            "x = 3, y = 3, rule = nonsense\nbo!",
+        ] {
+            assert!(parse_rle(rle).is_err(), "{rle:?}");
+        }
+    }
+
+    #[test]
+    fn cells_past_the_header() {
+        assert!(parse_rle("x = 2, y = 1\n3o!").is_err());
+        assert!(parse_rle("x = 3, y = 1\n$o!").is_err());
+        // Dead cells past the edge don't matter
+        assert_eq!(cells("x = 1, y = 1\no5b!"), [(0, 0)]);
+    }
+
+    #[test]
+    fn overflowing_runs() {
+        for rle in [
+            "x = 3, y = 3\n2147483647b2b!",
+            "x = 3, y = 3\n2147483647b2o!",
+            "x = 3, y = 3\n2147483647$2$o!",
+            "x = 3, y = 3\n2147483648o!",
+            "x = 2147483647, y = 1\n2147483647o!",
+            "x = 5000000, y = 1\n5000000o!",
+        ] {
+            assert!(parse_rle(rle).is_err(), "{rle:?}");
+        }
+    }
+
+    #[test]
+    fn library_patterns() {
+        for pattern in crate::game::library::LIBRARY {
+            assert!(parse_rle(pattern.rle).is_ok(), "{}", pattern.name);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(|(x, y)| Vector2::new(x, y));
+        let rle = encode_rle(&glider, &[], &Ruleset::default());
+        assert_eq!(cells(&rle), [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn plaintext_too_big_to_write() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(|(x, y)| Vector2::new(x, y));
+        let text = encode_plaintext(&glider).unwrap();
+        assert_eq!(text, ".O.\n..O\nOOO\n");
+        assert_eq!(parse_plaintext(&text).unwrap().cells, glider);
+
+        let far_apart = [Vector2::new(i32::MIN, 0), Vector2::new(i32::MAX, 0)];
+        assert!(encode_plaintext(&far_apart).is_err());
+        let corners = [Vector2::new(0, 0), Vector2::new(5000, 5000)];
+        assert!(encode_plaintext(&corners).is_err());
+    }
+
+    #[test]
+    fn walls_round_trip() {
+        let cells = [Vector2::new(2, 1)];
+        // Enough walls that they wrap onto a second line
+        let walls: Vec<_> = (-1..80).step_by(2).map(|x| Vector2::new(x, 3)).collect();
+        let rle = encode_rle(&cells, &walls, &Ruleset::default());
+        assert!(rle.lines().filter(|l| l.starts_with("#W")).count() > 1);
+        assert!(rle.lines().all(|l| l.len() <= RLE_LINE_LENGTH));
+        let pattern = parse_rle(&rle).unwrap();
+        assert_eq!((pattern.width, pattern.height), (81, 3));
+        assert_eq!(pattern.cells, [Vector2::new(3, 0)]);
+        let expected: Vec<_> = (0..81).step_by(2).map(|x| Vector2::new(x, 2)).collect();
+        assert_eq!(pattern.walls, expected);
+    }
+}
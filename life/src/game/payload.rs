@@ -0,0 +1,92 @@
+//! Data that embedders can attach to living cells.
+
+use std::any::Any;
+
+use vec2::Vector2;
+
+use super::{
+    boundary::BoundaryMode, get_neighbors, rules::Neighborhood, CellMap, LivingList,
+};
+
+/// Data attached to each living cell, for extensions like coloring cells by
+/// some custom value or giving them their own state.
+///
+/// Payloads follow their cells from one generation to the next. A cell that
+/// survives keeps its payload, a cell that dies loses it, and a newborn cell
+/// gets one from `born`, computed from its living neighbors' payloads. Cells
+/// that are placed by hand or loaded from a save start with
+/// `Default::default()`.
+///
+/// # Threading
+/// Payloads are only ever touched on the main thread. With `native_threads`,
+/// the worker thread computes the next generation of cells without them, and
+/// the payloads are carried over once the result is received. Because of
+/// that, `Send` is required so the game state can be moved between threads,
+/// but `Sync` is not.
+pub trait CellPayload: Default + Clone + Send + 'static {
+    /// Compute the payload of a newborn cell from the payloads of the living
+    /// cells around it.
+    fn born(neighbors: &[&Self]) -> Self;
+}
+
+/// Payload storage with the payload type erased, so that the game state
+/// doesn't need a type parameter that everything else would have to carry.
+pub(super) trait PayloadStore: Send {
+    /// Carry the payloads over to the next generation of cells
+    fn step(&mut self, next: &LivingList, neighborhood: &Neighborhood, boundary: &BoundaryMode);
+    /// Give a cell that was placed by hand a payload, or take one away
+    fn set(&mut self, cell: Vector2<i32>, alive: bool);
+    /// Replace every payload with the default for the given cells
+    fn reset(&mut self, cells: &LivingList);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// The payloads of every living cell
+pub(super) struct Payloads<P>(pub CellMap<P>);
+
+impl<P: CellPayload> Payloads<P> {
+    pub fn new(cells: &LivingList) -> Self {
+        Self(cells.iter().map(|c| (*c, P::default())).collect())
+    }
+}
+
+impl<P: CellPayload> PayloadStore for Payloads<P> {
+    fn step(&mut self, next: &LivingList, neighborhood: &Neighborhood, boundary: &BoundaryMode) {
+        let prev = &self.0;
+        let offsets = neighborhood.offsets();
+        let next = next
+            .iter()
+            .map(|cell| {
+                let payload = prev.get(cell).cloned().unwrap_or_else(|| {
+                    let neighbors = get_neighbors(cell, &offsets, boundary)
+                        .filter_map(|n| prev.get(&n))
+                        .collect::<Vec<_>>();
+                    P::born(&neighbors)
+                });
+                (*cell, payload)
+            })
+            .collect();
+        self.0 = next;
+    }
+
+    fn set(&mut self, cell: Vector2<i32>, alive: bool) {
+        if alive {
+            self.0.insert(cell, P::default());
+        } else {
+            self.0.remove(&cell);
+        }
+    }
+
+    fn reset(&mut self, cells: &LivingList) {
+        *self = Self::new(cells);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,505 @@
+//! Life-like rules, which decide which cells are born and which survive from
+//! the number of living neighbors they have, and the Generations rules built
+//! on them, where cells that don't survive take a few generations to die.
+//! The neighbors can be further away than the 8 touching cells, for
+//! exploring rules like Larger than Life.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use vec2::Vector2;
+
+/// The furthest away a neighborhood can reach
+pub const MAX_RANGE: u8 = 5;
+/// The most neighbors a cell can have, which it has in a Moore neighborhood
+/// of `MAX_RANGE`
+pub const MAX_NEIGHBORS: usize = (2 * MAX_RANGE as usize + 1).pow(2) - 1;
+
+/// Which cells around a cell are its neighbors
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Neighborhood {
+    /// The cells in a square reaching `range` cells out. With a range of 1
+    /// these are the 8 cells touching the cell.
+    Moore { range: u8 },
+    /// The cells within `range` steps up, down, left and right. With a range
+    /// of 1 these are the 4 cells sharing an edge with the cell.
+    VonNeumann { range: u8 },
+}
+
+impl Neighborhood {
+    /// How many cells out the neighborhood reaches
+    pub fn range(&self) -> u8 {
+        match *self {
+            Self::Moore { range } | Self::VonNeumann { range } => range,
+        }
+    }
+
+    /// The same kind of neighborhood reaching `range` cells out
+    pub fn with_range(&self, range: u8) -> Self {
+        match self {
+            Self::Moore { .. } => Self::Moore { range },
+            Self::VonNeumann { .. } => Self::VonNeumann { range },
+        }
+    }
+
+    /// The number of neighbors each cell has
+    pub fn size(&self) -> usize {
+        let range = self.range() as usize;
+        match self {
+            Self::Moore { .. } => (2 * range + 1).pow(2) - 1,
+            Self::VonNeumann { .. } => 2 * range * (range + 1),
+        }
+    }
+
+    /// The offsets from a cell to each of its neighbors
+    pub fn offsets(&self) -> Vec<Vector2<i32>> {
+        let range = self.range() as i32;
+        let mut offsets = Vec::with_capacity(self.size());
+        for y in -range..=range {
+            for x in -range..=range {
+                let inside = match self {
+                    Self::Moore { .. } => true,
+                    Self::VonNeumann { .. } => x.abs() + y.abs() <= range,
+                };
+                if inside && (x, y) != (0, 0) {
+                    offsets.push(Vector2::new(x, y));
+                }
+            }
+        }
+        offsets
+    }
+}
+
+impl Default for Neighborhood {
+    /// The 8 cells touching the cell, as in Conway's Game of Life
+    fn default() -> Self {
+        Self::Moore { range: 1 }
+    }
+}
+
+impl fmt::Display for Neighborhood {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Moore { .. } => f.write_str("Moore"),
+            Self::VonNeumann { .. } => f.write_str("von Neumann"),
+        }
+    }
+}
+
+/// A rule in B/S notation, like `B3/S23` for Conway's Game of Life, or in
+/// B/S/C notation for Generations rules, like `B2/S/C3` for Brian's Brain.
+/// Rules with other neighborhoods are written like `R2,C0,M0,S6-9,B7-8,NM`,
+/// giving the range, the states, the counts that survive and are born, and
+/// the kind of neighborhood.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ruleset {
+    /// Whether a dead cell with each number of living neighbors is born
+    pub birth: [bool; MAX_NEIGHBORS + 1],
+    /// Whether a living cell with each number of living neighbors survives
+    pub survive: [bool; MAX_NEIGHBORS + 1],
+    /// The number of states a cell can be in, counting dead and alive. Cells
+    /// that don't survive go through the states past alive one generation at
+    /// a time before they're dead, and can't be born again until then. Life-like
+    /// rules have 2, so their cells die straight away.
+    pub states: u8,
+    /// Which cells are counted as neighbors
+    pub neighborhood: Neighborhood,
+}
+
+impl Ruleset {
+    /// Whether a cell with `count` living neighbors is alive in the next
+    /// generation. `was_alive` is only called when the answer depends on it,
+    /// since looking the cell up is the expensive part.
+    #[inline(always)]
+    pub fn alive(&self, count: u32, was_alive: impl FnOnce() -> bool) -> bool {
+        let count = count as usize;
+        match (self.birth[count], self.survive[count]) {
+            (true, true) => true,
+            (false, false) => false,
+            (birth, _) => was_alive() != birth,
+        }
+    }
+
+    /// Whether cells that don't survive decay instead of dying straight away
+    pub fn is_generations(&self) -> bool {
+        self.states > 2
+    }
+
+    /// The same rule with a different neighborhood. Counts higher than the
+    /// new neighborhood has neighbors are dropped.
+    pub fn with_neighborhood(mut self, neighborhood: Neighborhood) -> Self {
+        let size = neighborhood.size();
+        self.birth[size + 1..].fill(false);
+        self.survive[size + 1..].fill(false);
+        self.neighborhood = neighborhood;
+        self
+    }
+}
+
+impl Default for Ruleset {
+    /// Conway's Game of Life, `B3/S23`
+    fn default() -> Self {
+        let mut birth = [false; MAX_NEIGHBORS + 1];
+        let mut survive = [false; MAX_NEIGHBORS + 1];
+        birth[3] = true;
+        survive[2] = true;
+        survive[3] = true;
+        Self {
+            birth,
+            survive,
+            states: 2,
+            neighborhood: Neighborhood::default(),
+        }
+    }
+}
+
+impl FromStr for Ruleset {
+    type Err = anyhow::Error;
+
+    /// Parse a rule like `B36/S23`. The letters can be either case and the two
+    /// halves can be in either order, but both have to be there. A third part
+    /// like `C3` gives the number of states of a Generations rule. The older
+    /// survival/birth/states notation without letters, like `23/36` or `/2/3`,
+    /// is accepted too. Rules starting with `R` are parsed as ones with other
+    /// neighborhoods.
+    fn from_str(s: &str) -> Result<Self> {
+        if s.trim().starts_with(['R', 'r']) {
+            return parse_ranged(s);
+        }
+        let parts = s.trim().split('/').map(str::trim).collect::<Vec<_>>();
+        if (2..=3).contains(&parts.len())
+            && parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit()))
+        {
+            let mut rule = format!("B{}/S{}", parts[1], parts[0]);
+            if let Some(states) = parts.get(2) {
+                rule += &format!("/C{states}");
+            }
+            return rule.parse();
+        }
+
+        let mut birth = None;
+        let mut survive = None;
+        let mut states = None;
+        for part in parts {
+            let mut chars = part.chars();
+            let target = match chars.next() {
+                Some('B' | 'b') => &mut birth,
+                Some('S' | 's') => &mut survive,
+                Some('C' | 'c' | 'G' | 'g') => {
+                    if states.is_some() {
+                        return Err(anyhow!("`{part}` is given more than once"));
+                    }
+                    let count = chars
+                        .as_str()
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|n| *n >= 2)
+                        .ok_or_else(|| anyhow!("`{part}` isn't a number of states from 2 to 255"))?;
+                    states = Some(count);
+                    continue;
+                }
+                _ => return Err(anyhow!("Expected `B`, `S` or `C` at the start of `{part}`")),
+            };
+            if target.is_some() {
+                return Err(anyhow!("`{part}` is given more than once"));
+            }
+            let mut counts = [false; MAX_NEIGHBORS + 1];
+            for c in chars {
+                let n = c
+                    .to_digit(10)
+                    .filter(|n| *n <= 8)
+                    .ok_or_else(|| anyhow!("`{c}` isn't a neighbor count from 0 to 8"))?;
+                counts[n as usize] = true;
+            }
+            *target = Some(counts);
+        }
+
+        let birth = birth.ok_or_else(|| anyhow!("The rule has no `B` part"))?;
+        let survive = survive.ok_or_else(|| anyhow!("The rule has no `S` part"))?;
+        // Only cells next to living ones are ever considered, so a cell with no
+        // living neighbors can't be born.
+        if birth[0] {
+            return Err(anyhow!("Rules with B0 aren't supported"));
+        }
+        Ok(Self {
+            birth,
+            survive,
+            states: states.unwrap_or(2),
+            neighborhood: Neighborhood::default(),
+        })
+    }
+}
+
+/// Parse a rule with any neighborhood, like `R2,C0,M0,S6-9,B7-8,NM`. The
+/// counts after `S` and `B` are single numbers or ranges, and more of them
+/// can follow after commas. `C0` and `C2` both mean 2 states, and `NN` is a
+/// von Neumann neighborhood.
+fn parse_ranged(s: &str) -> Result<Ruleset> {
+    let mut range = None;
+    let mut states = 2;
+    let mut moore = true;
+    // The counts of the `S` and `B` parts, and which one is being read
+    let mut survive = Vec::new();
+    let mut birth = Vec::new();
+    let mut reading_birth = None;
+    for item in s.trim().split(',').map(str::trim) {
+        let mut chars = item.chars();
+        let letter = chars.next().filter(|c| c.is_ascii_alphabetic());
+        let rest = if letter.is_some() { chars.as_str() } else { item };
+        match letter.map(|c| c.to_ascii_uppercase()) {
+            Some('R') => {
+                range = Some(
+                    rest.parse::<u8>()
+                        .ok()
+                        .filter(|r| (1..=MAX_RANGE).contains(r))
+                        .ok_or_else(|| anyhow!("`{item}` isn't a range from 1 to {MAX_RANGE}"))?,
+                );
+                continue;
+            }
+            Some('C') => {
+                states = match rest.parse::<u8>() {
+                    Ok(0) => 2,
+                    Ok(n) if n >= 2 => n,
+                    _ => return Err(anyhow!("`{item}` isn't a number of states from 2 to 255")),
+                };
+                continue;
+            }
+            Some('M') if rest == "0" => continue,
+            Some('M') => {
+                return Err(anyhow!("Rules where cells count themselves aren't supported"));
+            }
+            Some('N') => {
+                moore = match rest {
+                    "M" | "m" => true,
+                    "N" | "n" => false,
+                    _ => return Err(anyhow!("`{item}` isn't `NM` or `NN`")),
+                };
+                continue;
+            }
+            Some('S') => reading_birth = Some(false),
+            Some('B') => reading_birth = Some(true),
+            Some(_) => return Err(anyhow!("`{item}` isn't part of a rule")),
+            None => (),
+        }
+        if rest.is_empty() {
+            continue;
+        }
+        let (low, high) = rest.split_once('-').unwrap_or((rest, rest));
+        let count = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .map_err(|_| anyhow!("`{item}` isn't a neighbor count or a range of them"))
+        };
+        let (low, high) = (count(low)?, count(high)?);
+        if low > high {
+            return Err(anyhow!("`{item}` is a range that goes backwards"));
+        }
+        let counts = low..=high;
+        match reading_birth {
+            Some(true) => birth.push(counts),
+            Some(false) => survive.push(counts),
+            None => return Err(anyhow!("`{item}` has to come after `S` or `B`")),
+        }
+    }
+
+    let range = range.ok_or_else(|| anyhow!("The rule has no `R` part"))?;
+    let neighborhood = if moore {
+        Neighborhood::Moore { range }
+    } else {
+        Neighborhood::VonNeumann { range }
+    };
+    let size = neighborhood.size();
+    let to_table = |ranges: Vec<std::ops::RangeInclusive<usize>>| {
+        let mut table = [false; MAX_NEIGHBORS + 1];
+        for counts in ranges {
+            if *counts.end() > size {
+                return Err(anyhow!(
+                    "A cell can't have {} neighbors, only up to {size}",
+                    counts.end()
+                ));
+            }
+            table[counts].fill(true);
+        }
+        Ok(table)
+    };
+    let birth = to_table(birth)?;
+    if birth[0] {
+        return Err(anyhow!("Rules with B0 aren't supported"));
+    }
+    Ok(Ruleset {
+        birth,
+        survive: to_table(survive)?,
+        states,
+        neighborhood,
+    })
+}
+
+/// Randomness for noisy versions of a rule, where the cells that the rule says
+/// are born or survive only do so some of the time. The same seed always gives
+/// the same generations from the same board, so noisy runs can be repeated.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Noise {
+    /// The chance, from 0 to 1, that a cell the rule brings to life or keeps
+    /// alive really is alive in the next generation
+    pub probability: f64,
+    pub seed: u64,
+}
+
+impl Noise {
+    pub fn new(probability: f64, seed: u64) -> Self {
+        Self {
+            probability: probability.clamp(0.0, 1.0),
+            seed,
+        }
+    }
+
+    /// Whether this changes anything. With a probability of 1, every cell
+    /// does what the rule says, exactly as if there was no noise.
+    pub fn is_active(&self) -> bool {
+        self.probability < 1.0
+    }
+
+    /// Whether a cell that the rule says is alive in generation `generation`
+    /// is let live. Each cell and generation gets its own roll, worked out from
+    /// the seed alone, so it doesn't matter what order cells are looked at in
+    /// or which thread does it.
+    pub fn keeps(&self, generation: u64, cell: Vector2<i32>) -> bool {
+        let coords = ((cell.x as u32 as u64) << 32) | cell.y as u32 as u64;
+        let roll = split_mix(self.seed ^ split_mix(generation ^ split_mix(coords)));
+        // The top 53 bits, as a number from 0 to 1
+        ((roll >> 11) as f64 / (1u64 << 53) as f64) < self.probability
+    }
+}
+
+/// The SplitMix64 mixing function, which turns similar numbers into very
+/// different ones
+fn split_mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl fmt::Display for Ruleset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.neighborhood != Neighborhood::default() {
+            // Runs of counts, like `2-3,5`
+            let ranges = |counts: &[bool]| {
+                let mut ranges = Vec::new();
+                let mut n = 0;
+                while n < counts.len() {
+                    if !counts[n] {
+                        n += 1;
+                        continue;
+                    }
+                    let start = n;
+                    while n + 1 < counts.len() && counts[n + 1] {
+                        n += 1;
+                    }
+                    ranges.push(if start == n {
+                        start.to_string()
+                    } else {
+                        format!("{start}-{n}")
+                    });
+                    n += 1;
+                }
+                ranges.join(",")
+            };
+            let size = self.neighborhood.size();
+            let kind = match self.neighborhood {
+                Neighborhood::Moore { .. } => 'M',
+                Neighborhood::VonNeumann { .. } => 'N',
+            };
+            return write!(
+                f,
+                "R{},C{},M0,S{},B{},N{kind}",
+                self.neighborhood.range(),
+                if self.is_generations() { self.states } else { 0 },
+                ranges(&self.survive[..=size]),
+                ranges(&self.birth[..=size]),
+            );
+        }
+        let digits = |counts: &[bool; MAX_NEIGHBORS + 1]| {
+            (0..9)
+                .filter(|n| counts[*n])
+                .map(|n| n.to_string())
+                .collect::<String>()
+        };
+        write!(f, "B{}/S{}", digits(&self.birth), digits(&self.survive))?;
+        if self.is_generations() {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(s: &str) -> Ruleset {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn case_and_order() {
+        assert_eq!(rule("b3/s23"), Ruleset::default());
+        assert_eq!(rule("S23/B3"), Ruleset::default());
+        assert_eq!(rule(" s23 / b3 "), Ruleset::default());
+    }
+
+    #[test]
+    fn legacy_notation() {
+        assert_eq!(rule("23/3"), Ruleset::default());
+        assert_eq!(rule("/2/3"), rule("B2/S/C3"));
+    }
+
+    #[test]
+    fn generations() {
+        let brain = rule("B2/S/C3");
+        assert_eq!(brain.states, 3);
+        assert!(brain.is_generations());
+        assert!(brain.birth[2] && !brain.survive.contains(&true));
+        assert!(!Ruleset::default().is_generations());
+    }
+
+    #[test]
+    fn invalid_rules() {
+        for s in [
+            "",
+            "   ",
+            "hello",
+            "B3",
+            "B3/S23/S4",
+            "B3/B3/S23",
+            "B3/S23/C3/C4",
+            "B39/S23",
+            "B3/S29",
+            "B03/S23",
+            "B3/S23/C1",
+            "B3/S2a",
+            "R2,C0,M0,S6-9,B0-8,NM",
+            "R2,C0,M0,S6-25,B7-8,NM",
+        ] {
+            assert!(s.parse::<Ruleset>().is_err(), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for s in [
+            "B3/S23",
+            "B36/S23",
+            "B2/S",
+            "B/S012345678",
+            "B2/S/C3",
+            "B3/S23/C10",
+            "R2,C0,M0,S6-9,B7-8,NM",
+            "R3,C5,M0,S2-4,7,B3,10-12,NN",
+        ] {
+            let parsed = rule(s);
+            assert_eq!(parsed.to_string(), s);
+            assert_eq!(rule(&parsed.to_string()), parsed);
+        }
+    }
+}
@@ -1,60 +1,89 @@
-use super::GameState;
-use rustc_hash::FxHashSet;
+use super::{rules::Ruleset, GameState};
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::Read, path::PathBuf};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use vec2::Vector2;
 
+/// The environment variable that says where the save file is kept. Setting it
+/// to nothing turns the save file and autosaving off. Slots saved by hand are
+/// still kept in `./saves`, and settings like the key bindings in
+/// `./settings`.
+pub const SAVE_PATH_VAR: &str = "LIFE_SAVE_PATH";
+/// Where the save file is kept unless `SAVE_PATH_VAR` says otherwise
+const DEFAULT_SAVE_PATH: &str = "./save.json";
+
+/// Where the save file is kept, going by `SAVE_PATH_VAR`, or `None` if there
+/// shouldn't be one
+pub fn default_save_path() -> Option<PathBuf> {
+    match std::env::var_os(SAVE_PATH_VAR) {
+        Some(path) if path.is_empty() => None,
+        Some(path) => Some(path.into()),
+        None => Some(DEFAULT_SAVE_PATH.into()),
+    }
+}
+
 /// A representation of a game save file. The saves are stored in memory unless
 /// written to disk via `SaveFile::write_to_disk`.
 pub struct SaveFile {
     /// A vector of the saves
-    saves: Vec<SaveGame>,
-    /// A write-only file handle for the saves file
-    file: File,
+    saves: Vec<SaveEntry>,
+    /// Where the file is
+    path: PathBuf,
+    /// Whether the saves changed since they were last written
+    modified: bool,
 }
 
 impl SaveFile {
-    /// Create a new `SaveFile` by reading a file from disk. Returns an error
-    /// if the file does not exist.
-    fn new_from_disk(filepath: PathBuf) -> Result<Self, anyhow::Error> {
-        let data: Vec<SaveGame> = {
-            let mut buf = String::new();
-            File::open(&filepath)?.read_to_string(&mut buf)?;
-            serde_json::from_str(&buf)?
-        };
-        let file = File::create(filepath)?;
-        Ok(Self { saves: data, file })
-    }
-
-    /// Create a new `SaveFile` by creating a new file on the disk. Returns an
-    /// error if the file already exists.
-    fn new_and_new_file(filepath: PathBuf) -> Result<Self, anyhow::Error> {
-        let file = File::create_new(filepath)?;
-        Ok(Self {
-            saves: Vec::new(),
-            file,
-        })
-    }
-
     /// Creates a new `SaveFile`. Uses the existing file on disk if it exists
-    /// or otherwise create a new one.
+    /// or otherwise creates a new one. The file is left as it is until the
+    /// saves are written.
     pub fn new(filepath: PathBuf) -> Result<Self, anyhow::Error> {
-        if let Ok(v) = Self::new_and_new_file(filepath.clone()) {
-            Ok(v)
-        } else {
-            Self::new_from_disk(filepath)
+        match std::fs::read_to_string(&filepath) {
+            Ok(data) => Ok(Self {
+                saves: serde_json::from_str(&data)?,
+                path: filepath,
+                modified: false,
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut save_file = Self {
+                    saves: Vec::new(),
+                    path: filepath,
+                    modified: true,
+                };
+                // Finds out now if the file can't be written
+                save_file.write_to_disk()?;
+                Ok(save_file)
+            }
+            Err(e) => Err(e.into()),
         }
     }
 
-    /// Write the savefile to the disk.
-    pub fn write_to_disk(self) -> Result<(), serde_json::Error> {
-        serde_json::to_writer_pretty(self.file, &self.saves)?;
+    /// Write the savefile to the disk, replacing what was written before. The
+    /// old file is only replaced once the new one is written in full.
+    pub fn write_to_disk(&mut self) -> Result<(), anyhow::Error> {
+        write_replacing(&self.path, &serde_json::to_string_pretty(&self.saves)?)?;
+        self.modified = false;
         Ok(())
     }
 
+    /// Where the file is
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Whether the saves changed since they were last written to the disk
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
     /// Add a game save to the file.
     pub fn add_save(&mut self, save: SaveGame) {
-        self.saves.push(save);
+        self.saves.push(SaveEntry::Valid(Box::new(save)));
+        self.modified = true;
     }
 
     /// Delete a save from the file at a given index. This is safe to perform on
@@ -63,29 +92,267 @@ impl SaveFile {
     pub fn delete_save(&mut self, index: usize) -> bool {
         if self.saves.len() > index {
             self.saves.remove(index);
+            self.modified = true;
             true
         } else {
             false
         }
     }
 
-    /// Get an iterator over the game saves the file contains
-    pub fn saves_iter(&self) -> impl Iterator<Item = SaveGame> {
-        self.saves.clone().into_iter()
+    /// Get the save at a given index, or `None` if it is out of bounds or
+    /// couldn't be read.
+    pub fn get(&self, index: usize) -> Option<SaveGame> {
+        self.saves.get(index)?.valid().cloned()
+    }
+
+    /// Get a summary of the save at a given index without loading it. Returns
+    /// `None` if it is out of bounds or couldn't be read.
+    pub fn info(&self, index: usize) -> Option<SaveInfo> {
+        self.saves.get(index)?.valid().map(SaveGame::info)
     }
 
-    /// Get the number of stored saves
+    /// Whether there is a readable save with the given name
+    pub fn has_save(&self, name: &str) -> bool {
+        self.saves
+            .iter()
+            .filter_map(SaveEntry::valid)
+            .any(|s| s.name == name)
+    }
+
+    /// Get the number of stored saves, including any that couldn't be read
     pub fn save_count(&self) -> usize {
         self.saves.len()
     }
 }
 
+/// Write `data` to the file at `path`, replacing it. It's written to a file
+/// next to it first, which is then renamed over it, so that a crash partway
+/// through leaves the old file as it was.
+fn write_replacing(path: &Path, data: &str) -> Result<(), anyhow::Error> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let mut temp = File::create(&temp_path)?;
+    temp.write_all(data.as_bytes())?;
+    temp.sync_all()?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Somewhere saves can be kept, each under its own key. Keys are names picked
+/// by the player, so each backend has to check that they're usable.
+pub trait DataStorage {
+    /// Read the data under a key, or `None` if nothing is stored there
+    fn read(&self, key: &str) -> Result<Option<String>, anyhow::Error>;
+
+    /// Store data under a key, replacing anything that was there
+    fn write(&mut self, key: &str, data: &str) -> Result<(), anyhow::Error>;
+
+    /// Remove the data under a key. Removing a key that isn't there is fine.
+    fn delete(&mut self, key: &str) -> Result<(), anyhow::Error>;
+
+    /// Every key that has data stored under it, sorted
+    fn keys(&self) -> Result<Vec<String>, anyhow::Error>;
+
+    /// Make sure everything written has been stored, before the game closes
+    fn finish(&mut self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
+
+/// Get a summary of the save kept under a key without loading it. Returns
+/// `None` if nothing is stored there or it couldn't be read.
+pub fn slot_info(storage: &dyn DataStorage, key: &str) -> Option<SaveInfo> {
+    let data = storage.read(key).ok()??;
+    let header: SaveHeader = serde_json::from_str(&data).ok()?;
+    Some(SaveInfo {
+        name: header.name,
+        generation: header.step_count,
+        cell_count: header.living_cells.0,
+        saved_at: header.created,
+    })
+}
+
+/// Where save slots are kept unless the game is given somewhere else: a
+/// `saves` directory on the desktop, and local storage on the web.
+pub fn default_storage() -> Box<dyn DataStorage + Send> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return Box::new(DirectoryStorage::new("./saves".into()));
+    #[cfg(target_arch = "wasm32")]
+    return Box::new(super::web_storage::WebStorage::SAVES);
+}
+
+/// Where settings that aren't part of any save are kept: a `settings`
+/// directory on the desktop, and local storage on the web.
+pub fn settings_storage() -> Box<dyn DataStorage + Send> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return Box::new(DirectoryStorage::new("./settings".into()));
+    #[cfg(target_arch = "wasm32")]
+    return Box::new(super::web_storage::WebStorage::SETTINGS);
+}
+
+/// Storage that keeps each key in its own JSON file in a directory, like
+/// `<dir>/<key>.json`. The directory is created when it's first written to.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DirectoryStorage {
+    dir: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DirectoryStorage {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The file a key is stored in. Keys that could point outside the
+    /// directory aren't allowed.
+    fn path(&self, key: &str) -> Result<PathBuf, anyhow::Error> {
+        let usable = !key.is_empty()
+            && !key.starts_with('.')
+            && !key.contains(['/', '\\', ':'])
+            && !key.chars().any(char::is_control);
+        if !usable {
+            return Err(anyhow::anyhow!("\"{key}\" can't be used as a save name"));
+        }
+        Ok(self.dir.join(format!("{key}.json")))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DataStorage for DirectoryStorage {
+    fn read(&self, key: &str) -> Result<Option<String>, anyhow::Error> {
+        match std::fs::read_to_string(self.path(key)?) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&mut self, key: &str, data: &str) -> Result<(), anyhow::Error> {
+        let path = self.path(key)?;
+        std::fs::create_dir_all(&self.dir)?;
+        write_replacing(&path, data)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), anyhow::Error> {
+        match std::fs::remove_file(self.path(key)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn keys(&self) -> Result<Vec<String>, anyhow::Error> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut keys = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json")
+                && let Some(key) = path.file_stem().and_then(|s| s.to_str())
+            {
+                keys.push(key.to_string());
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+/// A save as it was read from the file. Saves that fail to parse are kept as
+/// they were so that they aren't lost when the file is written back.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+enum SaveEntry {
+    Valid(Box<SaveGame>),
+    Corrupt(serde_json::Value),
+}
+
+impl SaveEntry {
+    fn valid(&self) -> Option<&SaveGame> {
+        match self {
+            Self::Valid(v) => Some(v),
+            Self::Corrupt(_) => None,
+        }
+    }
+}
+
+/// A summary of a save, for showing what's in it before loading it.
+#[derive(Clone, Debug)]
+pub struct SaveInfo {
+    pub name: String,
+    /// The number of steps the game had taken when it was saved
+    pub generation: u64,
+    pub cell_count: usize,
+    pub saved_at: chrono::DateTime<chrono::Local>,
+}
+
+/// The parts of a `SaveGame` that `SaveInfo` needs, read without the rest.
+/// The cells are counted but not kept, and every other field is skipped.
+#[derive(Deserialize)]
+struct SaveHeader {
+    name: String,
+    created: chrono::DateTime<chrono::Local>,
+    #[serde(default)]
+    step_count: u64,
+    living_cells: CellCount,
+}
+
+/// The length of a list in a save, read without keeping what's in it
+struct CellCount(usize);
+
+impl<'de> Deserialize<'de> for CellCount {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CountVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for CountVisitor {
+            type Value = CellCount;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a list of cells")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<CellCount, A::Error> {
+                let mut count = 0;
+                while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {
+                    count += 1;
+                }
+                Ok(CellCount(count))
+            }
+        }
+
+        deserializer.deserialize_seq(CountVisitor)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 /// A record of a game that can be restored.
 pub struct SaveGame {
     living_cells: Vec<Vector2<i32>>,
     grid_size: f32,
     pan_position: Vector2<f64>,
+    #[serde(default)]
+    step_count: u64,
+    /// The time between generations. Older saves don't have one.
+    #[serde(default)]
+    interval: Option<Duration>,
+    /// The rule in B/S notation. Older saves were all made with Conway's
+    /// rule, so that's used when there isn't one.
+    #[serde(default)]
+    rule: Option<String>,
+    /// The population of every generation up to the one saved
+    #[serde(default)]
+    living_count_history: Vec<usize>,
+    /// The name of the color theme. Older saves don't have one.
+    #[serde(default)]
+    theme: Option<String>,
+    /// Cells that never change. Older saves don't have any.
+    #[serde(default)]
+    walls: Vec<Vector2<i32>>,
     pub created: chrono::DateTime<chrono::Local>,
     pub name: String,
 }
@@ -93,20 +360,159 @@ pub struct SaveGame {
 impl SaveGame {
     pub fn new(game_state: &GameState, name: String) -> Self {
         Self {
-            living_cells: game_state.living_cells.iter().cloned().collect(),
+            living_cells: game_state.sim.living_cells.iter().cloned().collect(),
             grid_size: game_state.grid_size,
             pan_position: game_state.pan_position,
+            step_count: game_state.sim.step_count,
+            interval: Some(game_state.interval),
+            rule: Some(game_state.sim.ruleset.to_string()),
+            living_count_history: game_state.living_count_history.clone(),
+            theme: Some(game_state.theme.to_string()),
+            walls: game_state.sim.walls().collect(),
             created: chrono::Local::now(),
             name,
         }
     }
-    pub fn living_cells(&self) -> FxHashSet<Vector2<i32>> {
+    pub fn living_cells(&self) -> super::LivingList {
         self.living_cells.iter().cloned().collect()
     }
+    pub fn walls(&self) -> super::LivingList {
+        self.walls.iter().cloned().collect()
+    }
     pub fn pan_position(&self) -> Vector2<f64> {
         self.pan_position
     }
     pub fn grid_size(&self) -> f32 {
         self.grid_size
     }
+    pub fn step_count(&self) -> u64 {
+        self.step_count
+    }
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+    pub fn theme(&self) -> Option<&str> {
+        self.theme.as_deref()
+    }
+    /// The rule the game was saved with. Rules that can't be read fall back
+    /// to Conway's, like saves without one.
+    pub fn ruleset(&self) -> Ruleset {
+        let Some(rule) = &self.rule else {
+            return Ruleset::default();
+        };
+        rule.parse().unwrap_or_else(|e| {
+            log::warn!("Can't read the rule {rule} of save {}: {e}", self.name);
+            Ruleset::default()
+        })
+    }
+    /// The population of every generation up to the one saved. Older saves
+    /// only have the population of the saved generation.
+    pub fn living_count_history(&self) -> Vec<usize> {
+        if self.living_count_history.is_empty() {
+            vec![self.living_cells.len()]
+        } else {
+            self.living_count_history.clone()
+        }
+    }
+    pub fn info(&self) -> SaveInfo {
+        SaveInfo {
+            name: self.name.clone(),
+            generation: self.step_count,
+            cell_count: self.living_cells.len(),
+            saved_at: self.created,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path in the temporary directory that no other test uses
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("life-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn opening_leaves_the_file_alone() {
+        let path = temp_path("open.json");
+        let data = "[{\"not\": \"a save\"}]";
+        std::fs::write(&path, data).unwrap();
+        let mut save_file = SaveFile::new(path.clone()).unwrap();
+        assert!(!save_file.is_modified());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), data);
+
+        save_file.write_to_disk().unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            written,
+            serde_json::from_str::<serde_json::Value>(data).unwrap()
+        );
+        let mut temp = path.clone().into_os_string();
+        temp.push(".tmp");
+        assert!(!Path::new(&temp).exists());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn new_file_is_written_straight_away() {
+        let path = temp_path("new.json");
+        let _ = std::fs::remove_file(&path);
+        let save_file = SaveFile::new(path.clone()).unwrap();
+        assert_eq!(save_file.save_count(), 0);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[]");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    fn test_save(name: &str) -> SaveGame {
+        SaveGame {
+            living_cells: vec![Vector2::new(0, 0), Vector2::new(1, 0)],
+            grid_size: 10.0,
+            pan_position: Vector2::new(0.0, 0.0),
+            step_count: 3,
+            interval: None,
+            rule: None,
+            living_count_history: Vec::new(),
+            theme: None,
+            walls: Vec::new(),
+            created: chrono::Local::now(),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn corrupt_saves_survive_a_write() {
+        let path = temp_path("corrupt.json");
+        std::fs::write(&path, "[{\"not\": \"a save\"}]").unwrap();
+        let mut save_file = SaveFile::new(path.clone()).unwrap();
+        assert!(save_file.info(0).is_none());
+        save_file.add_save(test_save("kept"));
+        save_file.write_to_disk().unwrap();
+
+        let save_file = SaveFile::new(path.clone()).unwrap();
+        assert_eq!(save_file.save_count(), 2);
+        assert!(save_file.info(0).is_none());
+        assert!(save_file.get(0).is_none());
+        let info = save_file.info(1).unwrap();
+        assert_eq!(info.name, "kept");
+        assert_eq!((info.generation, info.cell_count), (3, 2));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn slot_info_skips_unreadable_slots() {
+        let dir = temp_path("slots");
+        let mut storage = DirectoryStorage::new(dir.clone());
+        storage
+            .write("good", &serde_json::to_string(&test_save("good")).unwrap())
+            .unwrap();
+        storage.write("bad", "{\"not\": \"a save\"}").unwrap();
+        let info = slot_info(&storage, "good").unwrap();
+        assert_eq!(info.name, "good");
+        assert_eq!((info.generation, info.cell_count), (3, 2));
+        assert!(slot_info(&storage, "bad").is_none());
+        assert!(slot_info(&storage, "missing").is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,48 @@
+//! Packing patterns into text short enough to share in a URL.
+//!
+//! A pattern is written as RLE, compressed with deflate and then encoded as
+//! URL-safe base64, so it can go in the fragment of a link to the game.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+/// The most bytes a shared pattern can inflate to, so that a malicious link
+/// can't use up all of the memory
+const MAX_RLE_BYTES: usize = 16 * 1024 * 1024;
+/// How hard deflate tries to compress, from 0 to 10
+const COMPRESSION_LEVEL: u8 = 9;
+
+/// Pack a pattern in RLE format for a URL
+pub fn encode(rle: &str) -> String {
+    let compressed = miniz_oxide::deflate::compress_to_vec(rle.as_bytes(), COMPRESSION_LEVEL);
+    URL_SAFE_NO_PAD.encode(compressed)
+}
+
+/// Unpack a pattern from a URL back to RLE
+pub fn decode(text: &str) -> Result<String> {
+    let compressed = URL_SAFE_NO_PAD.decode(text)?;
+    let rle = miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed, MAX_RLE_BYTES)
+        .map_err(|e| anyhow!("The shared pattern can't be decompressed: {e:?}"))?;
+    Ok(String::from_utf8(rle)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let rle = "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+        let encoded = encode(rle);
+        assert!(encoded
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(decode(&encoded).unwrap(), rle);
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert!(decode("not base64!").is_err());
+        assert!(decode(&URL_SAFE_NO_PAD.encode(b"not deflate")).is_err());
+    }
+}
@@ -0,0 +1,447 @@
+//! The board and its rules on their own, without a window, GPU or worker
+//! thread. `GameState` wraps one of these, but it can also be used by itself
+//! to run patterns from scripts and other tools.
+
+use std::sync::Arc;
+
+use vec2::Vector2;
+
+use super::{
+    boundary::BoundaryMode,
+    compute_step_into,
+    rules::{Noise, Ruleset},
+    CellMap, LivingList, StepBuffers,
+};
+
+/// A board of living cells that can be stepped one generation at a time.
+#[derive(Default)]
+pub struct Simulation {
+    pub(super) living_cells: LivingList,
+    /// The cells that are no longer alive but haven't finished dying under a
+    /// Generations rule, and the state each one is in. Under Life-like rules
+    /// this is always empty.
+    pub(super) decaying: CellMap<u8>,
+    /// Cells that are always there and never change. They count as living
+    /// neighbors, but aren't living cells themselves, so they're never born
+    /// or die. They're shared with the worker thread, since they hardly ever
+    /// change.
+    pub(super) walls: Arc<LivingList>,
+    pub(super) ruleset: Ruleset,
+    /// What happens at the edges of the board
+    pub(super) boundary: BoundaryMode,
+    /// Randomness that keeps some cells from being born or surviving, if the
+    /// rule is a noisy one
+    pub(super) noise: Option<Noise>,
+    /// The number of steps taken
+    pub(super) step_count: u64,
+    /// Buffers for computing steps
+    scratch: StepScratch,
+}
+
+/// Buffers reused from one step to the next, so that stepping doesn't have to
+/// allocate once they've grown to the size of the board.
+#[derive(Default)]
+struct StepScratch {
+    buffers: StepBuffers,
+    /// A set to put the next generation in
+    next: LivingList,
+}
+
+impl Simulation {
+    /// An empty, infinite board running Conway's Game of Life
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compute the next generation and replace the current one with it.
+    pub fn step(&mut self) {
+        let next = self.compute_next();
+        let prev = self.replace(next);
+        self.recycle(prev);
+    }
+
+    /// Flip a cell between alive and dead, returning whether it's alive now.
+    /// Cells off a bounded board or on a wall can't be changed, so they're
+    /// left dead.
+    pub fn toggle(&mut self, cell: Vector2<i32>) -> bool {
+        if !self.boundary.contains(cell) || self.walls.contains(&cell) {
+            return false;
+        }
+        if self.living_cells.remove(&cell) {
+            false
+        } else {
+            self.living_cells.insert(cell);
+            self.decaying.remove(&cell);
+            true
+        }
+    }
+
+    /// Put a wall on a cell or take one away, returning whether there's one
+    /// there now. A living or decaying cell where a wall is put is removed.
+    pub fn toggle_wall(&mut self, cell: Vector2<i32>) -> bool {
+        if !self.boundary.contains(cell) {
+            return false;
+        }
+        let walls = Arc::make_mut(&mut self.walls);
+        if walls.remove(&cell) {
+            false
+        } else {
+            walls.insert(cell);
+            self.living_cells.remove(&cell);
+            self.decaying.remove(&cell);
+            true
+        }
+    }
+
+    pub fn is_wall(&self, cell: Vector2<i32>) -> bool {
+        self.walls.contains(&cell)
+    }
+
+    /// The coordinates of every wall, in no particular order
+    pub fn walls(&self) -> impl Iterator<Item = Vector2<i32>> + '_ {
+        self.walls.iter().copied()
+    }
+
+    pub fn is_alive(&self, cell: Vector2<i32>) -> bool {
+        self.living_cells.contains(&cell)
+    }
+
+    /// The state of a cell: 0 if it's dead, 1 if it's alive, and counting up
+    /// from 2 as it decays under a Generations rule
+    pub fn state(&self, cell: Vector2<i32>) -> u8 {
+        if self.living_cells.contains(&cell) {
+            1
+        } else {
+            self.decaying.get(&cell).copied().unwrap_or(0)
+        }
+    }
+
+    /// The coordinates and states of every decaying cell, in no particular
+    /// order
+    pub fn decaying_cells(&self) -> impl Iterator<Item = (Vector2<i32>, u8)> + '_ {
+        self.decaying.iter().map(|(c, s)| (*c, *s))
+    }
+
+    /// The number of living cells
+    pub fn population(&self) -> usize {
+        self.living_cells.len()
+    }
+
+    /// The coordinates of every living cell, in no particular order
+    pub fn living_cells(&self) -> impl Iterator<Item = Vector2<i32>> + '_ {
+        self.living_cells.iter().copied()
+    }
+
+    /// The number of steps taken
+    pub fn step_count(&self) -> u64 {
+        self.step_count
+    }
+
+    pub fn ruleset(&self) -> Ruleset {
+        self.ruleset
+    }
+
+    /// Change the rule. Decaying cells past the last state of the new rule
+    /// die straight away, and a toroidal board too small for the new rule's
+    /// neighborhood is grown to fit it.
+    pub fn set_ruleset(&mut self, ruleset: Ruleset) {
+        self.ruleset = ruleset;
+        self.decaying.retain(|_, state| *state < ruleset.states);
+        if self.boundary.clamped(ruleset.neighborhood.range()) != self.boundary {
+            self.set_boundary(self.boundary);
+        }
+    }
+
+    pub fn noise(&self) -> Option<Noise> {
+        self.noise
+    }
+
+    /// Make the rule a noisy one, where cells are only born or survive some
+    /// of the time, or a deterministic one again with `None`.
+    pub fn set_noise(&mut self, noise: Option<Noise>) {
+        self.noise = noise;
+    }
+
+    pub fn boundary(&self) -> BoundaryMode {
+        self.boundary
+    }
+
+    /// Change what happens at the edges of the board. Living cells and walls
+    /// that are off the new board are wrapped onto it. Boards that are too
+    /// small for the rule are grown to [`BoundaryMode::min_torus_size`].
+    pub fn set_boundary(&mut self, boundary: BoundaryMode) {
+        let boundary = boundary.clamped(self.ruleset.neighborhood.range());
+        self.boundary = boundary;
+        self.living_cells = boundary.wrap_all(&self.living_cells);
+        self.walls = Arc::new(boundary.wrap_all(&self.walls));
+        self.living_cells.retain(|cell| !self.walls.contains(cell));
+        self.decaying.clear();
+    }
+
+    /// Compute the next generation without applying it
+    pub(super) fn compute_next(&mut self) -> LivingList {
+        let mut next = std::mem::take(&mut self.scratch.next);
+        compute_step_into(
+            &self.living_cells,
+            &self.walls,
+            &self.ruleset,
+            &self.boundary,
+            &mut self.scratch.buffers,
+            &mut next,
+        );
+        next
+    }
+
+    /// Make `next` the current generation, counting it as a step, and give
+    /// back the one it replaced.
+    pub(super) fn replace(&mut self, mut next: LivingList) -> LivingList {
+        self.step_count += 1;
+        // This is done here rather than while computing the step so that the
+        // worker thread doesn't need to know about it
+        if let Some(noise) = self.noise
+            && noise.is_active()
+        {
+            let generation = self.step_count;
+            next.retain(|cell| noise.keeps(generation, *cell));
+        }
+        if self.ruleset.is_generations() {
+            self.decay(&mut next);
+        }
+        std::mem::replace(&mut self.living_cells, next)
+    }
+
+    /// Move the decaying cells on to their next state, and start decaying the
+    /// ones that didn't survive into `next`.
+    ///
+    /// Neighbors are counted without knowing about decaying cells, so this
+    /// also takes back any births on them, which have to wait until they're
+    /// dead.
+    fn decay(&mut self, next: &mut LivingList) {
+        let states = self.ruleset.states;
+        let living = &self.living_cells;
+        // Cells placed by hand on decaying ones are alive again
+        self.decaying.retain(|cell, _| !living.contains(cell));
+        next.retain(|cell| !self.decaying.contains_key(cell));
+        self.decaying.retain(|_, state| {
+            *state += 1;
+            *state < states
+        });
+        self.decaying
+            .extend(living.difference(next).map(|cell| (*cell, 2)));
+    }
+
+    /// Replace every living cell and wall, wrapping them onto the board.
+    /// Cells that end up in the same place are merged, so a rule that needs a
+    /// bigger torus has to be set first.
+    pub(super) fn replace_board(&mut self, cells: &LivingList, walls: &LivingList) {
+        self.living_cells = self.boundary.wrap_all(cells);
+        self.decaying.clear();
+        self.set_walls(walls);
+    }
+
+    /// Replace the walls, wrapping them onto the board. Living cells where a
+    /// wall is put are removed.
+    pub(super) fn set_walls(&mut self, walls: &LivingList) {
+        self.walls = Arc::new(self.boundary.wrap_all(walls));
+        let walls = &self.walls;
+        self.living_cells.retain(|cell| !walls.contains(cell));
+        self.decaying.retain(|cell, _| !walls.contains(cell));
+    }
+
+    /// Kill every decaying cell straight away, for when the board is replaced
+    pub(super) fn clear_decaying(&mut self) {
+        self.decaying.clear();
+    }
+
+    /// Take the set the next generation would have been put in, for when
+    /// it's computed somewhere else
+    #[cfg_attr(not(feature = "native_threads"), allow(dead_code))]
+    pub(super) fn take_spare(&mut self) -> LivingList {
+        std::mem::take(&mut self.scratch.next)
+    }
+
+    /// Hand back a generation that's no longer needed, to hold the next one
+    pub(super) fn recycle(&mut self, spare: LivingList) {
+        self.scratch.next = spare;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sim_with(living: &[(i32, i32)], walls: &[(i32, i32)]) -> Simulation {
+        let mut sim = Simulation::new();
+        for &(x, y) in walls {
+            sim.toggle_wall(Vector2::new(x, y));
+        }
+        for &(x, y) in living {
+            sim.toggle(Vector2::new(x, y));
+        }
+        sim
+    }
+
+    fn sorted(cells: impl Iterator<Item = Vector2<i32>>) -> Vec<(i32, i32)> {
+        let mut cells: Vec<_> = cells.map(|c| (c.x, c.y)).collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    #[test]
+    fn walls_count_as_neighbors() {
+        // A row of three walls has the same neighbor counts as a blinker, so
+        // the cells above and below the middle one are born
+        let mut sim = sim_with(&[], &[(-1, 0), (0, 0), (1, 0)]);
+        sim.step();
+        assert_eq!(sorted(sim.living_cells()), [(0, -1), (0, 1)]);
+
+        // A cell next to two walls survives
+        let mut sim = sim_with(&[(0, 1)], &[(-1, 0), (1, 0)]);
+        sim.step();
+        assert!(sim.is_alive(Vector2::new(0, 1)));
+    }
+
+    #[test]
+    fn walls_never_change() {
+        // One wall with three living neighbors, which would be born, and one
+        // on its own, which would die
+        let walls = [(0, 0), (9, 9)];
+        let mut sim = sim_with(&[(-1, -1), (1, -1), (-1, 1)], &walls);
+        for _ in 0..4 {
+            sim.step();
+            assert_eq!(sorted(sim.walls()), walls);
+            assert!(walls
+                .iter()
+                .all(|&(x, y)| !sim.is_alive(Vector2::new(x, y))));
+        }
+    }
+
+    #[test]
+    fn brians_brain() {
+        let mut sim = sim_with(&[(0, 0), (1, 0)], &[]);
+        sim.set_ruleset("/2/3".parse().unwrap());
+        sim.step();
+        assert_eq!(
+            sorted(sim.living_cells()),
+            [(0, -1), (0, 1), (1, -1), (1, 1)]
+        );
+        assert_eq!(
+            sorted(sim.decaying_cells().map(|(c, _)| c)),
+            [(0, 0), (1, 0)]
+        );
+        assert!(sim.decaying_cells().all(|(_, state)| state == 2));
+
+        sim.step();
+        assert_eq!(
+            sorted(sim.living_cells()),
+            [(-1, 0), (0, -2), (0, 2), (1, -2), (1, 2), (2, 0)]
+        );
+        assert_eq!(
+            sorted(sim.decaying_cells().map(|(c, _)| c)),
+            [(0, -1), (0, 1), (1, -1), (1, 1)]
+        );
+        // The cells that were dying are dead now, having run out of states
+        assert_eq!(sim.state(Vector2::new(0, 0)), 0);
+    }
+
+    #[test]
+    fn new_rules_drop_cells_past_their_states() {
+        let mut sim = sim_with(&[], &[]);
+        sim.set_ruleset("B2/S/C5".parse().unwrap());
+        for state in 2..5 {
+            sim.decaying.insert(Vector2::new(state as i32, 0), state);
+        }
+        sim.set_ruleset("/2/3".parse().unwrap());
+        assert_eq!(
+            sim.decaying_cells().collect::<Vec<_>>(),
+            [(Vector2::new(2, 0), 2)]
+        );
+        sim.set_ruleset(Ruleset::default());
+        assert_eq!(sim.decaying_cells().count(), 0);
+    }
+
+    /// A 32 by 32 square where about half the cells are alive, the same every
+    /// time, with `noise`
+    fn noisy_soup(noise: Option<Noise>) -> Simulation {
+        // A xorshift generator, so the soup doesn't need the `random` feature
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let cells: Vec<_> = (0..32)
+            .flat_map(|y| (0..32).map(move |x| (x, y)))
+            .filter(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state & 1 == 0
+            })
+            .collect();
+        let mut sim = sim_with(&cells, &[]);
+        sim.set_noise(noise);
+        sim
+    }
+
+    fn run(mut sim: Simulation, steps: usize) -> Vec<(i32, i32)> {
+        for _ in 0..steps {
+            sim.step();
+        }
+        sorted(sim.living_cells())
+    }
+
+    #[test]
+    fn noise_is_the_same_for_the_same_seed() {
+        let noise = Noise::new(0.9, 42);
+        let board = run(noisy_soup(Some(noise)), 5);
+        assert!(!board.is_empty());
+        assert_eq!(board, run(noisy_soup(Some(noise)), 5));
+        assert_ne!(board, run(noisy_soup(Some(Noise::new(0.9, 43))), 5));
+        assert_ne!(board, run(noisy_soup(None), 5));
+    }
+
+    #[test]
+    fn certain_noise_changes_nothing() {
+        assert_eq!(
+            run(noisy_soup(Some(Noise::new(1.0, 42))), 5),
+            run(noisy_soup(None), 5)
+        );
+    }
+
+    #[test]
+    fn ranged_pattern_on_small_torus() {
+        let pattern = super::super::patterns::parse_rle(
+            "x = 5, y = 5, rule = R2,C0,M0,S6-9,B7-8,NM\n5o$5o$5o$5o$5o!",
+        )
+        .unwrap();
+        let mut sim = Simulation::new();
+        sim.set_boundary(BoundaryMode::Toroidal {
+            width: 3,
+            height: 3,
+        });
+        // The rule has to be set first so the torus is big enough for the
+        // cells, otherwise they'd wrap onto each other
+        sim.set_ruleset(pattern.rule.unwrap());
+        sim.replace_board(
+            &pattern.cells.iter().copied().collect(),
+            &LivingList::default(),
+        );
+        assert_eq!(
+            sim.boundary(),
+            BoundaryMode::Toroidal {
+                width: 5,
+                height: 5
+            }
+        );
+        assert_eq!(sim.living_cells().count(), 25);
+    }
+
+    #[test]
+    fn lone_cells_under_s0() {
+        // Cells with no living neighbors are never counted when stepping cell
+        // by cell, so they have to be kept separately under S0
+        for (rule, survives) in [("B3/S023", true), ("B3/S23", false)] {
+            let mut sim = sim_with(&[(0, 0), (10, 10)], &[]);
+            sim.set_ruleset(rule.parse().unwrap());
+            sim.step();
+            let expected: &[(i32, i32)] = if survives { &[(0, 0), (10, 10)] } else { &[] };
+            assert_eq!(sorted(sim.living_cells()), expected, "{rule}");
+        }
+    }
+}
@@ -0,0 +1,673 @@
+//! Statistics derived from the state of the game, for the statistics view.
+
+use std::{
+    collections::VecDeque,
+    hash::{Hash, Hasher},
+};
+
+use rustc_hash::FxHasher;
+use vec2::Vector2;
+
+use super::{patterns::bounding_box, CellMap, LivingList};
+
+/// The number of most recent generations that the population trend is
+/// measured over
+pub const TREND_WINDOW: usize = 20;
+/// How fast the population has to be changing, as a fraction of the average
+/// population per generation, to not count as stable.
+pub const TREND_THRESHOLD: f64 = 0.005;
+/// The number of buckets in the age histogram. The last one holds every cell
+/// at least this many generations old.
+pub const AGE_BUCKETS: usize = 16;
+/// The longest period that oscillators are checked for
+pub const MAX_PERIOD: usize = 1000;
+/// The most cells the period detector keeps from past generations. On big
+/// boards, this makes it look back fewer than `MAX_PERIOD` generations.
+pub const PERIOD_MAX_CELLS: usize = 4_000_000;
+/// The most living cells a board can have for the period detector to look at
+/// it. Hashing and sorting every cell each step costs more than the step on
+/// big boards, which hardly ever repeat anyway.
+pub const PERIOD_MAX_POPULATION: usize = 100_000;
+/// The number of generations the drift of the center of mass is averaged
+/// over. Spaceships change shape as they move, so their center of mass only
+/// moves steadily over whole periods, and this is a multiple of the common
+/// ones.
+pub const DRIFT_WINDOW: usize = 12;
+/// The most cells the heatmap keeps counts for. Patterns that spread out
+/// forever would otherwise make it grow without end.
+pub const HEATMAP_MAX_CELLS: usize = 250_000;
+
+/// Which way the population is heading
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PopulationTrend {
+    Growing,
+    Shrinking,
+    Stable,
+}
+
+impl PopulationTrend {
+    /// Find the trend of the last `TREND_WINDOW` entries of a population
+    /// history, by comparing the slope of a line fit to them with their
+    /// average.
+    pub fn of(history: &[usize]) -> Self {
+        let window = &history[history.len().saturating_sub(TREND_WINDOW)..];
+        if window.len() < 2 {
+            return Self::Stable;
+        }
+        let n = window.len() as f64;
+        let mean_x = (n - 1.0) / 2.0;
+        let mean_y = window.iter().sum::<usize>() as f64 / n;
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for (x, y) in window.iter().enumerate() {
+            let dx = x as f64 - mean_x;
+            covariance += dx * (*y as f64 - mean_y);
+            variance += dx * dx;
+        }
+        let slope = covariance / variance;
+        let relative = slope / mean_y.max(1.0);
+        if relative > TREND_THRESHOLD {
+            Self::Growing
+        } else if relative < -TREND_THRESHOLD {
+            Self::Shrinking
+        } else {
+            Self::Stable
+        }
+    }
+}
+
+impl std::fmt::Display for PopulationTrend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Growing => "Growing \u{2197}",
+            Self::Shrinking => "Shrinking \u{2198}",
+            Self::Stable => "Stable \u{2192}",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Whether the simulation has come to an end
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SimStatus {
+    /// The board changed on the last step
+    #[default]
+    Running,
+    /// Every cell has died
+    Extinct,
+    /// The last step didn't change anything
+    Stable,
+}
+
+impl std::fmt::Display for SimStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Running => "Running",
+            Self::Extinct => "Extinct",
+            Self::Stable => "Stable",
+        };
+        f.write_str(text)
+    }
+}
+
+/// The mean position of the centers of some cells, or `None` if there are
+/// none
+pub fn center_of_mass(cells: &LivingList) -> Option<Vector2<f64>> {
+    if cells.is_empty() {
+        return None;
+    }
+    let sum = cells.iter().fold(Vector2::new(0.0, 0.0), |acc, c| {
+        acc + Vector2::new(c.x as f64, c.y as f64)
+    });
+    // Cell coordinates are of their corners, so add half a cell to get to
+    // their centers
+    Some(sum / cells.len() as f64 + Vector2::new(0.5, 0.5))
+}
+
+/// Where the pattern is and how big it is
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ShapeStats {
+    /// The smallest and largest coordinates of the living cells
+    pub bounding_box: Option<(Vector2<i32>, Vector2<i32>)>,
+    /// The mean position of the centers of the living cells
+    pub center_of_mass: Option<Vector2<f64>>,
+}
+
+impl ShapeStats {
+    pub fn of(cells: &LivingList) -> Self {
+        Self {
+            bounding_box: bounding_box(cells),
+            center_of_mass: center_of_mass(cells),
+        }
+    }
+}
+
+/// Follows the center of mass over the last `DRIFT_WINDOW` generations, to
+/// find how fast the pattern is moving.
+#[derive(Default)]
+pub struct DriftTracker {
+    /// The center of mass at each recent generation, oldest first
+    positions: VecDeque<Vector2<f64>>,
+}
+
+impl DriftTracker {
+    /// Note the center of mass of the next generation. An empty board breaks
+    /// the run, since it has no center.
+    pub fn observe(&mut self, center_of_mass: Option<Vector2<f64>>) {
+        let Some(position) = center_of_mass else {
+            self.reset();
+            return;
+        };
+        if self.positions.len() > DRIFT_WINDOW {
+            self.positions.pop_front();
+        }
+        self.positions.push_back(position);
+    }
+
+    /// The average distance the center of mass moved per generation over the
+    /// last `DRIFT_WINDOW` generations, or `None` if it hasn't been followed
+    /// for that long.
+    pub fn velocity(&self) -> Option<Vector2<f64>> {
+        if self.positions.len() <= DRIFT_WINDOW {
+            return None;
+        }
+        let (first, last) = (self.positions.front()?, self.positions.back()?);
+        Some((*last - *first) / DRIFT_WINDOW as f64)
+    }
+
+    /// Forget the positions seen so far
+    pub fn reset(&mut self) {
+        self.positions.clear();
+    }
+}
+
+/// How many generations each living cell has been alive for, along with a
+/// histogram of the ages that is kept up to date as they change.
+///
+/// A cell is 1 generation old when it's born, placed, or loaded, and gets a
+/// generation older every step it survives.
+pub struct AgeMap {
+    ages: CellMap<u64>,
+    /// The number of cells of each age, starting from 1, with the last bucket
+    /// counting all the older ones too
+    histogram: Vec<usize>,
+}
+
+impl Default for AgeMap {
+    fn default() -> Self {
+        Self {
+            ages: CellMap::default(),
+            histogram: vec![0; AGE_BUCKETS],
+        }
+    }
+}
+
+impl AgeMap {
+    /// Age the cells that survived into the next generation and start the
+    /// newborn ones at 1. The map is updated in place, so stepping doesn't
+    /// allocate once it's big enough.
+    pub fn step(&mut self, next: &LivingList) {
+        self.histogram.fill(0);
+        let histogram = &mut self.histogram;
+        self.ages.retain(|cell, age| {
+            let survived = next.contains(cell);
+            if survived {
+                *age += 1;
+                histogram[bucket(*age)] += 1;
+            }
+            survived
+        });
+        for cell in next {
+            self.ages.entry(*cell).or_insert_with(|| {
+                histogram[0] += 1;
+                1
+            });
+        }
+    }
+
+    /// Account for a cell that was placed or removed by hand
+    pub fn set(&mut self, cell: Vector2<i32>, alive: bool) {
+        let old = if alive {
+            self.histogram[0] += 1;
+            self.ages.insert(cell, 1)
+        } else {
+            self.ages.remove(&cell)
+        };
+        if let Some(age) = old {
+            self.histogram[bucket(age)] -= 1;
+        }
+    }
+
+    /// Start over with every cell being new
+    pub fn reset(&mut self, cells: &LivingList) {
+        self.ages.clear();
+        self.ages.extend(cells.iter().map(|c| (*c, 1)));
+        self.histogram.fill(0);
+        self.histogram[0] = self.ages.len();
+    }
+
+    /// The number of cells of each age, starting from 1. The last of the
+    /// `AGE_BUCKETS` entries also counts every cell older than that.
+    pub fn histogram(&self) -> &[usize] {
+        &self.histogram
+    }
+}
+
+/// The histogram bucket cells of an age are counted in
+fn bucket(age: u64) -> usize {
+    (age as usize).min(AGE_BUCKETS) - 1
+}
+
+/// How many generations each cell has been alive for in total, whether or not
+/// it is alive now.
+#[derive(Default)]
+pub struct HeatMap {
+    counts: CellMap<u32>,
+    /// The highest count, which the others are compared to
+    max: u32,
+}
+
+impl HeatMap {
+    /// Count the cells alive in the next generation.
+    ///
+    /// When more than `HEATMAP_MAX_CELLS` cells have been counted, every count
+    /// is halved and the cells left at zero are forgotten, so the places that
+    /// were only briefly alive long ago make room for new ones.
+    pub fn step(&mut self, cells: &LivingList) {
+        for cell in cells {
+            let count = self.counts.entry(*cell).or_default();
+            *count = count.saturating_add(1);
+            self.max = self.max.max(*count);
+        }
+        if self.counts.len() > HEATMAP_MAX_CELLS {
+            self.counts.retain(|_, count| {
+                *count /= 2;
+                *count > 0
+            });
+            self.max = self.counts.values().copied().max().unwrap_or(0);
+        }
+    }
+
+    /// Every cell that has been alive, with how often compared to the one
+    /// alive the most, from 0 to 1. The scale is logarithmic so that cells
+    /// alive only a few times still show up next to ones that are always
+    /// alive.
+    pub fn iter(&self) -> impl Iterator<Item = (Vector2<i32>, f32)> + '_ {
+        let max = (self.max as f32).ln_1p().max(f32::EPSILON);
+        self.counts
+            .iter()
+            .map(move |(cell, count)| (*cell, (*count as f32).ln_1p() / max))
+    }
+
+    /// Whether the cell has been counted
+    pub fn contains(&self, cell: &Vector2<i32>) -> bool {
+        self.counts.contains_key(cell)
+    }
+
+    /// Forget how often every cell has been alive
+    pub fn reset(&mut self) {
+        self.counts.clear();
+        self.max = 0;
+    }
+}
+
+/// The board coming back to a shape it had before
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Repetition {
+    /// The number of steps since the shape was last seen
+    pub period: u64,
+    /// How far the shape moved in that time
+    pub displacement: Vector2<i32>,
+}
+
+impl Repetition {
+    /// How fast the pattern moves, or `None` if it stays where it is
+    pub fn velocity(&self) -> Option<Velocity> {
+        (self.displacement != Vector2::new(0, 0)).then_some(Velocity {
+            displacement: self.displacement,
+            period: self.period,
+        })
+    }
+}
+
+/// The speed and direction of a spaceship, as the distance it moves over its
+/// period
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Velocity {
+    pub displacement: Vector2<i32>,
+    pub period: u64,
+}
+
+impl std::fmt::Display for Velocity {
+    /// Write the velocity in the usual notation for Life, where c is one cell
+    /// per generation, like "c/4 diagonal" or "2c/5 orthogonal". Oblique ones
+    /// give both distances, like "(2,1)c/6 oblique".
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (dx, dy) = (self.displacement.x.unsigned_abs(), self.displacement.y.unsigned_abs());
+        let (far, near) = (dx.max(dy) as u64, dx.min(dy) as u64);
+        if near == 0 || near == far {
+            let divisor = gcd(far, self.period);
+            let (cells, period) = (far / divisor, self.period / divisor);
+            let direction = if near == 0 { "orthogonal" } else { "diagonal" };
+            if cells != 1 {
+                write!(f, "{cells}")?;
+            }
+            write!(f, "c")?;
+            if period != 1 {
+                write!(f, "/{period}")?;
+            }
+            write!(f, " {direction}")
+        } else {
+            let divisor = gcd(gcd(far, near), self.period);
+            write!(
+                f,
+                "({},{})c/{} oblique",
+                far / divisor,
+                near / divisor,
+                self.period / divisor
+            )
+        }
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Finds when the board repeats itself, by remembering each of the last
+/// `MAX_PERIOD` generations, or as many of the most recent ones as fit in
+/// `PERIOD_MAX_CELLS` cells. Boards with more than `PERIOD_MAX_POPULATION`
+/// living cells aren't looked at.
+///
+/// The cells are kept relative to their bounding box, so a spaceship that
+/// comes back to the same shape somewhere else counts as repeating too, and
+/// how far it moved is measured from the corner of the box. Generations are
+/// looked up by a hash of their cells, and only count as the same when the
+/// cells are too, since different boards can have the same hash. Under
+/// Generations rules, the decaying cells and their states have to match as
+/// well.
+#[derive(Default)]
+pub struct PeriodDetector {
+    /// The recent generations, oldest first
+    recent: VecDeque<SeenGeneration>,
+    /// The number of cells kept in `recent` altogether
+    cell_count: usize,
+    /// The number of generations observed. This is kept separately from the
+    /// game's step count, which can be reset from the statistics view.
+    step: u64,
+}
+
+/// A generation remembered by `PeriodDetector`
+struct SeenGeneration {
+    hash: u64,
+    step: u64,
+    /// The corner of the bounding box
+    min: Vector2<i32>,
+    /// The cells relative to `min`, sorted so that boards can be compared
+    cells: Box<[Vector2<i32>]>,
+    /// The decaying cells relative to `min` and their states, sorted the same
+    /// way
+    decaying: Box<[(Vector2<i32>, u8)]>,
+}
+
+impl PeriodDetector {
+    /// Look at the next generation, with the states of its decaying cells,
+    /// and return how long ago and how far away the same pattern was last
+    /// seen, if it was seen recently. An empty board doesn't count as
+    /// repeating, and neither does one with more than `PERIOD_MAX_POPULATION`
+    /// living cells.
+    pub fn observe(&mut self, cells: &LivingList, decaying: &CellMap<u8>) -> Option<Repetition> {
+        self.step += 1;
+        let step = self.step;
+        if cells.len() > PERIOD_MAX_POPULATION {
+            // The generations in between are missing, so the old ones can't
+            // be compared against anymore
+            self.reset();
+            return None;
+        }
+        let (min, _) = bounding_box(cells)?;
+        // Adding the hashes of the cells makes the result independent of the
+        // order the set gives them in.
+        let hash = cells
+            .iter()
+            .map(|cell| hash_one(*cell - min))
+            .chain(
+                decaying
+                    .iter()
+                    .map(|(cell, state)| hash_one((*cell - min, *state))),
+            )
+            .fold(0u64, u64::wrapping_add);
+        let mut normalized: Box<[Vector2<i32>]> = cells.iter().map(|c| *c - min).collect();
+        normalized.sort_unstable_by_key(|c| (c.y, c.x));
+        let mut normalized_decaying: Box<[(Vector2<i32>, u8)]> = decaying
+            .iter()
+            .map(|(c, state)| (*c - min, *state))
+            .collect();
+        normalized_decaying.sort_unstable_by_key(|(c, _)| (c.y, c.x));
+
+        let repetition = self
+            .recent
+            .iter()
+            .rev()
+            .find(|seen| {
+                seen.hash == hash
+                    && seen.cells == normalized
+                    && seen.decaying == normalized_decaying
+            })
+            .map(|seen| Repetition {
+                period: step - seen.step,
+                displacement: min - seen.min,
+            });
+        self.cell_count += normalized.len() + normalized_decaying.len();
+        self.recent.push_back(SeenGeneration {
+            hash,
+            step,
+            min,
+            cells: normalized,
+            decaying: normalized_decaying,
+        });
+        // The newest generation is always kept, however big it is
+        while self.recent.len() > 1
+            && (self.recent.len() > MAX_PERIOD || self.cell_count > PERIOD_MAX_CELLS)
+            && let Some(oldest) = self.recent.pop_front()
+        {
+            self.cell_count -= oldest.cells.len() + oldest.decaying.len();
+        }
+        repetition
+    }
+
+    /// Forget every generation seen so far
+    pub fn reset(&mut self) {
+        self.recent.clear();
+        self.cell_count = 0;
+    }
+
+    /// Roughly how many bytes the recent generations take up
+    pub fn memory(&self) -> usize {
+        self.recent.capacity() * std::mem::size_of::<SeenGeneration>()
+            + self.cell_count * std::mem::size_of::<Vector2<i32>>()
+    }
+}
+
+/// Hash one value on its own
+fn hash_one(value: impl Hash) -> u64 {
+    let mut hasher = FxHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Roughly how many bytes a set of cells takes up
+pub fn set_memory(cells: &LivingList) -> usize {
+    map_memory(cells.capacity(), std::mem::size_of::<Vector2<i32>>())
+}
+
+/// Roughly how many bytes a hash table with room for `capacity` entries of
+/// `entry_size` bytes takes up. Tables keep an eighth of their slots empty and
+/// a byte of bookkeeping for each one.
+fn map_memory(capacity: usize, entry_size: usize) -> usize {
+    capacity * 8 / 7 * (entry_size + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn velocity(dx: i32, dy: i32, period: u64) -> String {
+        Repetition {
+            period,
+            displacement: Vector2::new(dx, dy),
+        }
+        .velocity()
+        .map_or_else(|| "still".to_string(), |v| v.to_string())
+    }
+
+    #[test]
+    fn velocity_names() {
+        assert_eq!(velocity(1, 0, 1), "c orthogonal");
+        assert_eq!(velocity(0, -2, 4), "c/2 orthogonal");
+        assert_eq!(velocity(2, 0, 5), "2c/5 orthogonal");
+        assert_eq!(velocity(1, 1, 4), "c/4 diagonal");
+        assert_eq!(velocity(-1, 1, 4), "c/4 diagonal");
+        assert_eq!(velocity(-3, -3, 12), "c/4 diagonal");
+        assert_eq!(velocity(2, -1, 6), "(2,1)c/6 oblique");
+        assert_eq!(velocity(-2, -4, 12), "(2,1)c/6 oblique");
+        assert_eq!(velocity(0, 0, 3), "still");
+    }
+
+    #[test]
+    fn age_histogram_stays_counted() {
+        let recount = |ages: &AgeMap| {
+            let mut histogram = vec![0; AGE_BUCKETS];
+            for age in ages.ages.values() {
+                histogram[bucket(*age)] += 1;
+            }
+            histogram
+        };
+        let blinker = |vertical: bool| -> LivingList {
+            (-1..=1)
+                .map(|i| {
+                    if vertical {
+                        Vector2::new(0, i)
+                    } else {
+                        Vector2::new(i, 0)
+                    }
+                })
+                .collect()
+        };
+        let mut ages = AgeMap::default();
+        ages.reset(&blinker(false));
+        assert_eq!(ages.histogram()[0], 3);
+        for step in 0..AGE_BUCKETS + 3 {
+            ages.step(&blinker(step % 2 == 1));
+            assert_eq!(ages.histogram(), recount(&ages));
+        }
+        // The middle cell never dies, so it's in the last bucket by now
+        assert_eq!(ages.histogram()[AGE_BUCKETS - 1], 1);
+        ages.set(Vector2::new(5, 5), true);
+        ages.set(Vector2::new(0, 0), true);
+        ages.set(Vector2::new(0, 1), false);
+        ages.set(Vector2::new(9, 9), false);
+        assert_eq!(ages.histogram(), recount(&ages));
+    }
+
+    #[test]
+    fn gcd_with_zero() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(7, 5), 1);
+        assert_eq!(gcd(0, 9), 9);
+        assert_eq!(gcd(9, 0), 9);
+        assert_eq!(gcd(0, 0), 0);
+    }
+
+    fn cells(cells: &[(i32, i32)]) -> LivingList {
+        cells.iter().map(|&(x, y)| Vector2::new(x, y)).collect()
+    }
+
+    /// Step a pattern and return the first repetition the detector finds
+    fn first_repetition(pattern: &[(i32, i32)]) -> Option<Repetition> {
+        let mut sim = super::super::simulation::Simulation::new();
+        for &(x, y) in pattern {
+            sim.toggle(Vector2::new(x, y));
+        }
+        let mut detector = PeriodDetector::default();
+        (0..20).find_map(|_| {
+            let repetition = detector.observe(&sim.living_cells, &sim.decaying);
+            sim.step();
+            repetition
+        })
+    }
+
+    #[test]
+    fn blinker_period() {
+        let repetition = first_repetition(&[(-1, 0), (0, 0), (1, 0)]).expect("It repeats");
+        assert_eq!(repetition.period, 2);
+        assert_eq!(repetition.displacement, Vector2::new(0, 0));
+    }
+
+    #[test]
+    fn glider_period() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let repetition = first_repetition(&glider).expect("It repeats");
+        assert_eq!(repetition.period, 4);
+        assert_eq!(repetition.displacement, Vector2::new(1, 1));
+    }
+
+    #[test]
+    fn same_hash_different_cells() {
+        let mut detector = PeriodDetector::default();
+        let none = CellMap::default();
+        assert!(detector
+            .observe(&cells(&[(0, 1), (0, 4), (1, 0)]), &none)
+            .is_none());
+        assert!(detector
+            .observe(&cells(&[(0, 2), (0, 3), (1, 0)]), &none)
+            .is_none());
+        // The hashes of the cells add up to the same thing for both
+        assert_eq!(detector.recent[0].hash, detector.recent[1].hash);
+    }
+
+    #[test]
+    fn decaying_cells_have_to_match() {
+        let living = cells(&[(0, 0), (1, 0)]);
+        let decaying = |state| CellMap::from_iter([(Vector2::new(0, 1), state)]);
+        let mut detector = PeriodDetector::default();
+        assert!(detector.observe(&living, &decaying(1)).is_none());
+        assert!(detector.observe(&living, &decaying(2)).is_none());
+        assert!(detector.observe(&living, &CellMap::default()).is_none());
+        let repetition = detector.observe(&living, &decaying(1)).expect("It repeats");
+        assert_eq!(repetition.period, 3);
+    }
+
+    #[test]
+    fn crowded_boards_arent_checked() {
+        let small = cells(&[(0, 0)]);
+        let crowded: LivingList = (0..=PERIOD_MAX_POPULATION as i32)
+            .map(|x| Vector2::new(x, 0))
+            .collect();
+        let mut detector = PeriodDetector::default();
+        assert!(detector.observe(&small, &CellMap::default()).is_none());
+        assert!(detector.observe(&crowded, &CellMap::default()).is_none());
+        assert!(detector.observe(&crowded, &CellMap::default()).is_none());
+        assert!(detector.recent.is_empty());
+        // What was seen before the crowded boards is forgotten
+        assert!(detector.observe(&small, &CellMap::default()).is_none());
+    }
+
+    #[test]
+    fn random_soups_dont_repeat() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut detector = PeriodDetector::default();
+        for _ in 0..2 * MAX_PERIOD {
+            let soup: LivingList = (0..8)
+                .flat_map(|y| (0..8).map(move |x| Vector2::new(x, y)))
+                .filter(|_| rng.gen_bool(0.5))
+                .collect();
+            assert!(detector.observe(&soup, &CellMap::default()).is_none());
+        }
+    }
+}
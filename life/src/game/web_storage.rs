@@ -0,0 +1,210 @@
+//! Keeping save slots in the browser, where there's no file system.
+
+use anyhow::{anyhow, Result};
+#[cfg(feature = "indexed_db")]
+use std::{cell::RefCell, collections::BTreeMap};
+#[cfg(feature = "indexed_db")]
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+
+use super::saving::DataStorage;
+
+/// The names of the IndexedDB database and the object store in it
+#[cfg(feature = "indexed_db")]
+const DATABASE_NAME: &str = "life";
+#[cfg(feature = "indexed_db")]
+const STORE_NAME: &str = "saves";
+
+/// Turn an error thrown by a browser API into one that can be passed on
+fn js_error(e: impl std::fmt::Debug) -> anyhow::Error {
+    anyhow!("{e:?}")
+}
+
+/// Storage in the page's `localStorage`. It's read and written synchronously,
+/// but browsers only allow a few megabytes in it.
+pub struct WebStorage {
+    /// What's put in front of every key in local storage, to keep these keys
+    /// apart from anything else the page stores
+    prefix: &'static str,
+}
+
+impl WebStorage {
+    /// Where the save slots are kept
+    pub const SAVES: Self = Self {
+        prefix: "life-save:",
+    };
+    /// Where settings that aren't part of a save are kept
+    pub const SETTINGS: Self = Self {
+        prefix: "life-settings:",
+    };
+
+    fn storage() -> Result<web_sys::Storage> {
+        web_sys::window()
+            .ok_or_else(|| anyhow!("There's no window"))?
+            .local_storage()
+            .map_err(js_error)?
+            .ok_or_else(|| anyhow!("Local storage isn't available"))
+    }
+}
+
+impl DataStorage for WebStorage {
+    fn read(&self, key: &str) -> Result<Option<String>> {
+        Self::storage()?
+            .get_item(&format!("{}{key}", self.prefix))
+            .map_err(js_error)
+    }
+
+    fn write(&mut self, key: &str, data: &str) -> Result<()> {
+        Self::storage()?
+            .set_item(&format!("{}{key}", self.prefix), data)
+            .map_err(js_error)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        Self::storage()?
+            .remove_item(&format!("{}{key}", self.prefix))
+            .map_err(js_error)
+    }
+
+    fn keys(&self) -> Result<Vec<String>> {
+        let storage = Self::storage()?;
+        let len = storage.length().map_err(js_error)?;
+        let mut keys: Vec<String> = (0..len)
+            .filter_map(|i| storage.key(i).ok().flatten())
+            .filter_map(|k| k.strip_prefix(self.prefix).map(str::to_string))
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+#[cfg(feature = "indexed_db")]
+thread_local! {
+    /// The open database. Browser objects can't be sent between threads, so
+    /// it's kept here, on the only thread there is, rather than in
+    /// `IndexedDbStorage`, which the game has to be able to send.
+    static DATABASE: RefCell<Option<web_sys::IdbDatabase>> = const { RefCell::new(None) };
+}
+
+/// Storage in an IndexedDB database, which can hold much more than local
+/// storage.
+///
+/// IndexedDB can only be used asynchronously, so everything in it is read
+/// when it's opened and kept in memory to be read from. Writes update the
+/// copy in memory straight away and are stored in the background.
+#[cfg(feature = "indexed_db")]
+pub struct IndexedDbStorage {
+    cache: BTreeMap<String, String>,
+}
+
+#[cfg(feature = "indexed_db")]
+impl IndexedDbStorage {
+    /// Open the database, creating it if it doesn't exist yet, and read
+    /// everything in it. Fails if the browser doesn't support IndexedDB.
+    pub async fn new() -> Result<Self> {
+        let factory = web_sys::window()
+            .ok_or_else(|| anyhow!("There's no window"))?
+            .indexed_db()
+            .map_err(js_error)?
+            .ok_or_else(|| anyhow!("IndexedDB isn't available"))?;
+        let open = factory.open_with_u32(DATABASE_NAME, 1).map_err(js_error)?;
+        // The store only has to be made when the database is new
+        let on_upgrade = Closure::once(|event: web_sys::Event| {
+            let db = event
+                .target()
+                .and_then(|t| t.dyn_into::<web_sys::IdbOpenDbRequest>().ok())
+                .and_then(|r| r.result().ok())
+                .and_then(|r| r.dyn_into::<web_sys::IdbDatabase>().ok());
+            if let Some(db) = db
+                && let Err(e) = db.create_object_store(STORE_NAME)
+            {
+                log::error!("Couldn't create the save store: {e:?}");
+            }
+        });
+        open.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+        let db: web_sys::IdbDatabase = request_result(&open).await?.unchecked_into();
+        drop(on_upgrade);
+
+        let store = db
+            .transaction_with_str(STORE_NAME)
+            .and_then(|t| t.object_store(STORE_NAME))
+            .map_err(js_error)?;
+        let keys = request_result(&store.get_all_keys().map_err(js_error)?).await?;
+        let values = request_result(&store.get_all().map_err(js_error)?).await?;
+        // Both are in the order of the keys, so they line up
+        let cache = js_sys::Array::from(&keys)
+            .iter()
+            .zip(js_sys::Array::from(&values).iter())
+            .filter_map(|(k, v)| Some((k.as_string()?, v.as_string()?)))
+            .collect();
+
+        DATABASE.with(|d| *d.borrow_mut() = Some(db));
+        Ok(Self { cache })
+    }
+
+    /// Start a transaction to change the store, leaving it to finish in the
+    /// background
+    fn change(
+        &self,
+        change: impl FnOnce(&web_sys::IdbObjectStore) -> Result<(), JsValue>,
+    ) -> Result<()> {
+        DATABASE.with(|d| {
+            let db = d.borrow();
+            let db = db
+                .as_ref()
+                .ok_or_else(|| anyhow!("The database was closed"))?;
+            let transaction = db
+                .transaction_with_str_and_mode(STORE_NAME, web_sys::IdbTransactionMode::Readwrite)
+                .map_err(js_error)?;
+            let store = transaction.object_store(STORE_NAME).map_err(js_error)?;
+            change(&store).map_err(js_error)
+        })
+    }
+}
+
+#[cfg(feature = "indexed_db")]
+impl DataStorage for IndexedDbStorage {
+    fn read(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.cache.get(key).cloned())
+    }
+
+    fn write(&mut self, key: &str, data: &str) -> Result<()> {
+        self.change(|store| {
+            store
+                .put_with_key(&JsValue::from_str(data), &JsValue::from_str(key))
+                .map(drop)
+        })?;
+        self.cache.insert(key.to_string(), data.to_string());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        self.change(|store| store.delete(&JsValue::from_str(key)).map(drop))?;
+        self.cache.remove(key);
+        Ok(())
+    }
+
+    fn keys(&self) -> Result<Vec<String>> {
+        Ok(self.cache.keys().cloned().collect())
+    }
+
+    /// Close the database. Writes that were already started still finish.
+    fn finish(&mut self) -> Result<()> {
+        if let Some(db) = DATABASE.with(|d| d.borrow_mut().take()) {
+            db.close();
+        }
+        Ok(())
+    }
+}
+
+/// Wait for an IndexedDB request to succeed and get its result
+#[cfg(feature = "indexed_db")]
+async fn request_result(request: &web_sys::IdbRequest) -> Result<JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    wasm_bindgen_futures::JsFuture::from(promise)
+        .await
+        .map_err(js_error)?;
+    request.result().map_err(js_error)
+}
@@ -4,38 +4,157 @@
 #![warn(clippy::todo)]
 
 use winit::{
+    dpi::PhysicalSize,
     event::*,
     event_loop::EventLoop,
-    keyboard::{Key, NamedKey},
-    window::{Window, WindowBuilder},
+    window::{Fullscreen, Window, WindowBuilder},
 };
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-use std::sync::{Arc, Mutex};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 
 mod render;
 use render::RenderState;
 
 mod game;
-use game::GameState;
+use game::keys::Action;
+pub use game::{
+    boundary::BoundaryMode,
+    payload::CellPayload,
+    rules::{Neighborhood, Noise, Ruleset, MAX_RANGE},
+    simulation::Simulation,
+    share, GameState, StepCallback, StepInfo,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use game::bench;
 
 struct State<'a> {
     #[allow(dead_code)]
     window: Arc<Window>,
     render_state: RenderState<'a>,
     game_state: Arc<Mutex<GameState>>,
+    /// The size of the window before it went fullscreen, to go back to after
+    windowed_size: Option<PhysicalSize<u32>>,
 }
 
 /// The number of cells that will fit across the height of the window by default
 const DEFAULT_GRID_SIZE: f32 = 10.0;
 
+/// How the game starts, for `run_with`. The default is what `run` uses.
+#[derive(Clone, Debug)]
+pub struct RunConfig {
+    /// The size of the window's contents in physical pixels, or `None` to let
+    /// the platform choose
+    pub window_size: Option<(u32, u32)>,
+    /// The number of cells that fit across the height of the window. It's
+    /// clamped to what the game can zoom to, and numbers that aren't finite
+    /// and positive are replaced with the default.
+    pub grid_size: f32,
+    /// The rule the board starts with
+    pub ruleset: Ruleset,
+    /// Whether autoplay is on from the start
+    pub start_playing: bool,
+    /// What's drawn behind the cells
+    pub background: Background,
+    /// Where the save file is kept, or `None` to not keep one and not
+    /// autosave. By default it's `./save.json`, unless the `LIFE_SAVE_PATH`
+    /// environment variable says otherwise. Slots saved by hand and settings
+    /// are kept apart from it, so they're still written without one. There
+    /// are no files on the web, so this is ignored there.
+    pub save_path: Option<PathBuf>,
+}
+
+/// What's drawn behind the cells, unless a solid color is picked in the
+/// settings
+#[derive(Clone, Debug, Default)]
+pub enum Background {
+    /// The dead cell texture
+    #[default]
+    Texture,
+    /// An image in any format the `image` crate can read, tiled once per cell
+    /// like the dead cell texture
+    Image(Vec<u8>),
+    /// No texture, just a solid color, for clean exports
+    Plain,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            window_size: None,
+            grid_size: DEFAULT_GRID_SIZE,
+            ruleset: Ruleset::default(),
+            start_playing: false,
+            background: Background::Texture,
+            #[cfg(all(feature = "saving", not(target_arch = "wasm32")))]
+            save_path: game::saving::default_save_path(),
+            #[cfg(not(all(feature = "saving", not(target_arch = "wasm32"))))]
+            save_path: None,
+        }
+    }
+}
+
+/// The closest number of cells across the window to `grid_size` that the game
+/// can zoom to, or the default one if it isn't finite and positive
+fn usable_grid_size(grid_size: f32) -> f32 {
+    if grid_size.is_finite() && grid_size > 0.0 {
+        grid_size.clamp(game::MAX_GRID_SIZE.recip(), game::MIN_GRID_SIZE.recip())
+    } else {
+        log::warn!("Starting with the default grid size instead of {grid_size}");
+        DEFAULT_GRID_SIZE
+    }
+}
+
+impl RunConfig {
+    pub fn with_window_size(mut self, width: u32, height: u32) -> Self {
+        self.window_size = Some((width, height));
+        self
+    }
+
+    pub fn with_grid_size(mut self, grid_size: f32) -> Self {
+        self.grid_size = usable_grid_size(grid_size);
+        self
+    }
+
+    pub fn with_ruleset(mut self, ruleset: Ruleset) -> Self {
+        self.ruleset = ruleset;
+        self
+    }
+
+    pub fn with_start_playing(mut self, start_playing: bool) -> Self {
+        self.start_playing = start_playing;
+        self
+    }
+
+    pub fn with_background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn with_save_path(mut self, save_path: Option<PathBuf>) -> Self {
+        self.save_path = save_path;
+        self
+    }
+}
+
 impl<'a> State<'a> {
-    /// Create a new state and get its accompanying event loop
-    pub async fn new() -> (Self, EventLoop<()>) {
-        let event_loop = EventLoop::new().unwrap();
-        let window = WindowBuilder::new().build(&event_loop).unwrap();
+    /// Create a new state and get its accompanying event loop. Fails if there's
+    /// no window, or it can't be drawn to.
+    pub async fn new(config: &RunConfig) -> anyhow::Result<(Self, EventLoop<()>)> {
+        let event_loop = EventLoop::new()
+            .map_err(|e| anyhow::anyhow!("Couldn't create the event loop: {e}"))?;
+        let mut builder = WindowBuilder::new();
+        if let Some((width, height)) = config.window_size {
+            builder = builder.with_inner_size(PhysicalSize::new(width, height));
+        }
+        let window = builder
+            .build(&event_loop)
+            .map_err(|e| anyhow::anyhow!("Couldn't open the window: {e}"))?;
         let window = Arc::new(window);
 
         #[cfg(target_arch = "wasm32")]
@@ -53,33 +172,100 @@ impl<'a> State<'a> {
             //window.request_inner_size(PhysicalSize::new(800, 600)).unwrap();
         }
 
+        let grid_size = usable_grid_size(config.grid_size);
         let game_state = Arc::new(Mutex::new(GameState::new(
             window.clone(),
-            DEFAULT_GRID_SIZE.recip(),
+            grid_size.recip(),
         )));
+        game_state.lock().unwrap().set_ruleset(config.ruleset);
+
+        #[cfg(all(feature = "saving", not(target_arch = "wasm32")))]
+        {
+            let mut game = game_state.lock().unwrap();
+            if config.save_path.is_none() {
+                log::info!("Saving is turned off");
+                game.set_autosave_interval(None);
+            }
+            game.set_save_path(config.save_path.clone());
+        }
+
+        // IndexedDB can hold much bigger saves than local storage, so use it
+        // if the browser has it
+        #[cfg(all(target_arch = "wasm32", feature = "indexed_db"))]
+        match game::web_storage::IndexedDbStorage::new().await {
+            Ok(storage) => game_state.lock().unwrap().set_slot_storage(Box::new(storage)),
+            Err(e) => log::warn!("Saving to local storage, since IndexedDB can't be used: {e}"),
+        }
 
-        let render_state = RenderState::new(
+        // Start with the pattern from a shared link, if the page was opened
+        // from one
+        #[cfg(target_arch = "wasm32")]
+        if let Err(e) = game_state.lock().unwrap().load_from_url() {
+            log::error!("Couldn't load the shared pattern: {e}");
+        }
+
+        let mut render_state = RenderState::new(
             window.clone(),
-            DEFAULT_GRID_SIZE.recip(),
-            DEFAULT_GRID_SIZE.powi(2) as u64,
+            grid_size.recip(),
+            grid_size.powi(2) as u64,
             Arc::clone(&game_state),
         )
-        .await;
+        .await?;
+        match &config.background {
+            Background::Texture => (),
+            Background::Image(bytes) => {
+                if let Err(e) = image::load_from_memory(bytes)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|image| render_state.set_background(Some(&image)))
+                {
+                    log::error!("Couldn't use the background image: {e}");
+                }
+            }
+            Background::Plain => {
+                // Turning the texture off can't fail
+                let _ = render_state.set_background(None);
+            }
+        }
+
+        // Wait until everything is set up, since starting steps right away
+        if config.start_playing {
+            game_state.lock().unwrap().toggle_playing();
+        }
 
-        (
+        Ok((
             Self {
                 window,
                 render_state,
                 game_state,
+                windowed_size: None,
             },
             event_loop,
-        )
+        ))
+    }
+
+    /// Switch between fullscreen and a window of the size it was before
+    fn toggle_fullscreen(&mut self) {
+        if self.window.fullscreen().is_some() {
+            self.window.set_fullscreen(None);
+            // Some platforms shrink the window when leaving fullscreen
+            if let Some(size) = self.windowed_size.take() {
+                let _ = self.window.request_inner_size(size);
+            }
+        } else {
+            self.windowed_size = Some(self.window.inner_size());
+            self.window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
     }
 }
 
 /// Run the game
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub async fn run() {
+    run_with(RunConfig::default()).await
+}
+
+/// Run the game, starting it as `config` says
+pub async fn run_with(config: RunConfig) {
     #[cfg(target_arch = "wasm32")]
     {
         console_error_panic_hook::set_once();
@@ -89,7 +275,18 @@ pub async fn run() {
     #[cfg(not(target_arch = "wasm32"))]
     env_logger::init();
 
-    let (mut state, event_loop) = State::new().await;
+    let (mut state, event_loop) = match State::new(&config).await {
+        Ok(v) => v,
+        Err(e) => {
+            report_startup_error(&e);
+            // So that whatever started the game can tell it failed. The web
+            // page just stays as it is.
+            #[cfg(not(target_arch = "wasm32"))]
+            std::process::exit(1);
+            #[cfg(target_arch = "wasm32")]
+            return;
+        }
+    };
 
     let mut surface_configured = false;
 
@@ -102,12 +299,21 @@ pub async fn run() {
                 if let Some(c) = game_changes.cells {
                     state.render_state.update_cells(c);
                 }
+                if let Some(d) = game_changes.cell_delta {
+                    state.render_state.apply_cell_delta(d);
+                }
                 if let Some(v) = game_changes.grid_size {
                     state.render_state.change_grid_size(v);
                 }
                 if let Some(v) = game_changes.offset {
                     let offset = vec2::Vector2::new(v.x as f32, v.y as f32);
-                    state.render_state.update_offset(offset);
+                    // The grid size isn't smoothed, so the view has to keep up
+                    // with it when zooming
+                    if game_changes.grid_size.is_some() {
+                        state.render_state.jump_offset(offset);
+                    } else {
+                        state.render_state.update_offset(offset);
+                    }
                 }
             }
 
@@ -125,24 +331,70 @@ pub async fn run() {
                 && window_id == state.render_state.window().id()
             {
                 // If the gui didn't capture the event, then hand it to the game
-                // or, if it was the escape key, exit
+                // or, if it was the key for quitting, exit
                 if !egui_captured {
                     let mut game = state.game_state.lock().unwrap();
+                    // The keys that aren't up to the game. A key pressed while
+                    // an action is being rebound only rebinds it.
+                    let action = match event {
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    logical_key,
+                                    repeat: false,
+                                    ..
+                                },
+                            ..
+                        } if game.get_rebinding().is_none() => {
+                            game.key_bindings().action(logical_key)
+                        }
+                        _ => None,
+                    };
+                    // Quitting puts away a pattern being stamped instead
+                    let was_stamping = game.is_stamping();
                     game.handle_window_event(event);
 
-                    if let WindowEvent::KeyboardInput {
-                        event:
-                            KeyEvent {
-                                state: ElementState::Pressed,
-                                logical_key: Key::Named(NamedKey::Escape),
-                                ..
-                            },
-                        ..
-                    } = event
-                    {
+                    if action == Some(Action::Exit) && !was_stamping {
                         #[cfg(not(target_arch = "wasm32"))]
                         control_flow.exit();
                     }
+                    drop(game);
+
+                    // Go in and out of fullscreen (F11 by default). The window is
+                    // resized, which reconfigures the surface.
+                    if action == Some(Action::Fullscreen) {
+                        state.toggle_fullscreen();
+                    }
+
+                    // Save a screenshot (F12 by default)
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if action == Some(Action::Screenshot) {
+                        let name = chrono::Local::now()
+                            .format("screenshot-%Y%m%d-%H%M%S.png")
+                            .to_string();
+                        match state.render_state.capture_screenshot(name.as_ref()) {
+                            Ok(()) => log::info!("Saved a screenshot to {name}"),
+                            Err(e) => log::error!("Failed to save a screenshot: {e}"),
+                        }
+                    }
+
+                    // Start and stop recording a GIF (F9 by default)
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if action == Some(Action::Record) {
+                        if state.render_state.is_recording() {
+                            match state.render_state.stop_recording() {
+                                Ok(path) => log::info!("Saved the recording to {}", path.display()),
+                                Err(e) => log::error!("Failed to save the recording: {e}"),
+                            }
+                        } else {
+                            let name = chrono::Local::now()
+                                .format("recording-%Y%m%d-%H%M%S.gif")
+                                .to_string();
+                            log::info!("Recording to {name}");
+                            state.render_state.start_recording(name.into());
+                        }
+                    }
                 }
 
                 match event {
@@ -185,3 +437,13 @@ pub async fn run() {
         })
         .unwrap();
 }
+
+/// Tell the player why the game couldn't start. On the web the console is
+/// out of sight, so the message is shown in an alert too.
+fn report_startup_error(error: &anyhow::Error) {
+    log::error!("The game couldn't start: {error}");
+    #[cfg(target_arch = "wasm32")]
+    if let Some(window) = web_sys::window() {
+        let _ = window.alert_with_message(&format!("The game couldn't start: {error}"));
+    }
+}
@@ -1,3 +1,36 @@
+/// How long `--bench` runs each workload for when it isn't given a number of
+/// seconds
+const DEFAULT_BENCH_SECONDS: f64 = 3.0;
+
 fn main() {
+    // `life --bench [seconds]` measures how fast the simulation steps instead
+    // of opening a window
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("--bench") {
+        let seconds = match args.next().map(|s| s.parse::<f64>()) {
+            None => DEFAULT_BENCH_SECONDS,
+            Some(Ok(seconds)) if seconds > 0.0 => seconds,
+            Some(_) => {
+                eprintln!("usage: life --bench [seconds]");
+                std::process::exit(2);
+            }
+        };
+        println!("Cells are hashed with {}", life::bench::CELL_HASHER);
+        for &stepper in life::bench::Stepper::ALL {
+            for workload in life::bench::Workload::ALL {
+                let duration = std::time::Duration::from_secs_f64(seconds);
+                let result = life::bench::run(workload, stepper, duration);
+                println!(
+                    "{workload} on the {stepper}: {} steps in {:.2}s: {:.0} steps/s, {} cells at \
+                     the end",
+                    result.steps,
+                    result.elapsed.as_secs_f64(),
+                    result.steps_per_second(),
+                    result.population
+                );
+            }
+        }
+        return;
+    }
     pollster::block_on(life::run());
 }
@@ -0,0 +1,316 @@
+//! Rendering the scene to images instead of the window.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use image::codecs::gif::{GifEncoder, Repeat};
+
+use super::{Cell, RenderState};
+use crate::game::patterns::bounding_box;
+
+/// The most frames a recording can hold, however small the window is
+const MAX_RECORDING_FRAMES: usize = 1000;
+/// How much memory the frames of a recording can take up. Recording stops by
+/// itself once the next frame would go over.
+const MAX_RECORDING_BYTES: usize = 512 * 1024 * 1024;
+
+/// Frames being recorded for a GIF, one for each generation
+pub struct Recording {
+    path: PathBuf,
+    frames: Vec<image::RgbaImage>,
+    /// The generation of the last frame, so that each one is only recorded
+    /// once
+    last_generation: Option<u64>,
+}
+
+impl<'a> RenderState<'a> {
+    /// Save exactly what's in the window, framed the same way, to a PNG. The
+    /// image is `scale` times the size of the window in each direction. The
+    /// GUI isn't included.
+    pub fn export_current_view(&mut self, path: &Path, scale: u32) -> Result<()> {
+        let width = self.size.width * scale;
+        let height = self.size.height * scale;
+        let image = self.render_offscreen(width, height)?;
+        image.save(path)?;
+        Ok(())
+    }
+
+    /// Save the current frame, at the size of the window, to a PNG.
+    pub fn capture_screenshot(&mut self, path: &Path) -> Result<()> {
+        self.export_current_view(path, 1)
+    }
+
+    /// Start recording a frame for every generation, to be saved as a GIF at
+    /// `path` when the recording stops. Any recording that was going on is
+    /// thrown away.
+    pub fn start_recording(&mut self, path: PathBuf) {
+        self.recording = Some(Recording {
+            path,
+            frames: Vec::new(),
+            last_generation: None,
+        });
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Stop recording and save the frames as a looping GIF, played at the
+    /// game's current speed. Returns where it was saved.
+    pub fn stop_recording(&mut self) -> Result<PathBuf> {
+        let recording = self
+            .recording
+            .take()
+            .ok_or_else(|| anyhow!("Nothing is being recorded"))?;
+        if recording.frames.is_empty() {
+            return Err(anyhow!("No frames were recorded"));
+        }
+        let interval = self.game_state.lock().unwrap().get_interval();
+        let delay = image::Delay::from_saturating_duration(interval);
+
+        let file = std::io::BufWriter::new(std::fs::File::create(&recording.path)?);
+        let mut encoder = GifEncoder::new(file);
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(
+            recording
+                .frames
+                .into_iter()
+                .map(|f| image::Frame::from_parts(f, 0, 0, delay)),
+        )?;
+        Ok(recording.path)
+    }
+
+    /// Add the current frame to the recording if there is one and the
+    /// generation changed since the last frame. Once the recording is full,
+    /// it's stopped and saved.
+    pub(super) fn record_frame(&mut self) -> Result<()> {
+        let Some(recording) = &self.recording else {
+            return Ok(());
+        };
+        let generation = self.game_state.lock().unwrap().step_count();
+        if recording.last_generation == Some(generation) {
+            return Ok(());
+        }
+
+        let frame = self.render_offscreen(self.size.width, self.size.height)?;
+        let frame_bytes = frame.as_raw().len().max(1);
+        let max_frames = (MAX_RECORDING_BYTES / frame_bytes).min(MAX_RECORDING_FRAMES);
+        let Some(recording) = &mut self.recording else {
+            return Ok(());
+        };
+        recording.frames.push(frame);
+        recording.last_generation = Some(generation);
+
+        if recording.frames.len() >= max_frames {
+            log::warn!("Stopping the recording at its limit of {max_frames} frames");
+            let path = self.stop_recording()?;
+            log::info!("Saved the recording to {}", path.display());
+        }
+        Ok(())
+    }
+
+    /// Step the game `frames - 1` times and save every generation, starting
+    /// with the current one, to a PNG sprite sheet with `cols` tiles per row.
+    ///
+    /// Every tile frames the bounding box of the living cells across all of the
+    /// generations, with each cell `cell_px` pixels across, so the tiles line
+    /// up with each other. The game is left at the last generation.
+    pub fn export_flipbook(
+        &mut self,
+        frames: u32,
+        cols: u32,
+        cell_px: u32,
+        path: &Path,
+    ) -> Result<()> {
+        if frames == 0 || cols == 0 || cell_px == 0 {
+            return Err(anyhow!("A flipbook needs at least one frame, column and pixel"));
+        }
+
+        let generations = {
+            let mut game = self.game_state.lock().unwrap();
+            let mut generations = Vec::with_capacity(frames as usize);
+            for i in 0..frames {
+                if i > 0 {
+                    game.step_n_blocking(1);
+                }
+                generations.push(game.living_cells().collect::<Vec<_>>());
+            }
+            generations
+        };
+
+        let (min, max) = bounding_box(generations.iter().flatten())
+            .ok_or_else(|| anyhow!("There are no living cells to export"))?;
+        let width = (max.x - min.x + 1) as u32;
+        let height = (max.y - min.y + 1) as u32;
+        let tile_width = width * cell_px;
+        let tile_height = height * cell_px;
+
+        // The view is one unit tall, so fit the bounding box to that and pan
+        // its corner to the corner of the tile.
+        let grid_size = 1.0 / height as f32;
+        let aspect_ratio = width as f32 / height as f32;
+        let offset = vec2::Vector2::new(
+            min.x as f32 * grid_size - 0.5 + aspect_ratio / 2.0,
+            min.y as f32 * grid_size,
+        );
+
+        let rows = frames.div_ceil(cols);
+        let mut sheet = image::RgbaImage::new(tile_width * cols.min(frames), tile_height * rows);
+
+        let old_cells = std::mem::take(&mut self.cells);
+        let old_grid_size = self.grid_size;
+        let old_offset = self.offset;
+        self.change_grid_size(grid_size);
+        self.write_offset(offset);
+
+        let mut result = Ok(());
+        for (i, generation) in generations.iter().enumerate() {
+            let cells = generation
+                .iter()
+                .map(|c| Cell::at((*c).into(), grid_size))
+                .collect();
+            self.update_cells(cells);
+            self.dots.update(
+                &self.core,
+                &self.cell_index,
+                offset,
+                grid_size,
+                winit::dpi::PhysicalSize::new(tile_width, tile_height),
+            );
+            match self.render_offscreen(tile_width, tile_height) {
+                Ok(tile) => {
+                    let x = (i as u32 % cols) * tile_width;
+                    let y = (i as u32 / cols) * tile_height;
+                    image::imageops::replace(&mut sheet, &tile, x as i64, y as i64);
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+
+        self.change_grid_size(old_grid_size);
+        self.write_offset(old_offset);
+        self.update_cells(old_cells);
+
+        result?;
+        sheet.save(path)?;
+        Ok(())
+    }
+
+    /// Render the scene to a texture of the given size and read it back. The
+    /// scene is framed the same way as in the window.
+    pub(super) fn render_offscreen(&mut self, width: u32, height: u32) -> Result<image::RgbaImage> {
+        let max = self.core.device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max || height > max {
+            return Err(anyhow!(
+                "Can't render a {width}x{height} image, the limit is {max}x{max}"
+            ));
+        }
+        let device = &self.core.device;
+        let format = self.core.config.format;
+        if !matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            return Err(anyhow!("Can't read back images in the {format:?} format"));
+        }
+
+        // The pipelines were made for the surface's format, so the texture has
+        // to use it too.
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Rows of a buffer copy have to be aligned, so there may be padding at
+        // the end of each one.
+        let unpadded_row = width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row = unpadded_row.div_ceil(align) * align;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen Readback Buffer"),
+            size: (padded_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        // The shaders only use the resolution for the aspect ratio, but keep it
+        // honest for the size we're rendering at and put it back afterwards.
+        self.write_resolution(width, height);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Offscreen Encoder"),
+        });
+        let msaa = super::create_msaa_view(device, format, width, height, self.sample_count);
+        self.draw_scene(&mut encoder, &view, msaa.as_ref());
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.core.queue.submit(std::iter::once(encoder.finish()));
+        self.write_resolution(self.size.width, self.size.height);
+
+        // Wait for the copy to finish and read the buffer
+        let slice = buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |r| {
+            let _ = tx.send(r);
+        });
+        self.core.device.poll(wgpu::Maintain::Wait);
+        rx.recv()??;
+
+        let mut pixels = Vec::with_capacity((unpadded_row * height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_row as usize]);
+            }
+        }
+        buffer.unmap();
+
+        if matches!(
+            format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        image::RgbaImage::from_raw(width, height, pixels)
+            .ok_or_else(|| anyhow!("Rendered image has the wrong size"))
+    }
+}
@@ -0,0 +1,71 @@
+// Faint dots in the middle of dead cells
+
+struct InstanceInput {
+    @location(1) offset: vec2<f32>,
+    @location(2) center: vec2<f32>,
+}
+
+struct Res {
+    data: vec2<f32>,
+    padding: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> res: Res;
+
+struct Dot {
+    color: vec4<f32>,
+    // The diameter of the dot as a fraction of the cell
+    size: f32,
+    padding: f32,
+    padding2: vec2<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> dot: Dot;
+
+struct Pan {
+    data: vec2<f32>,
+    padding: vec2<f32>,
+}
+
+@group(2) @binding(0)
+var<uniform> pan: Pan;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(3) tex_coords: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(3) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let res = res.data;
+    let pan = pan.data;
+    let aspect_ratio = f32(res.x) / f32(res.y);
+
+    // The same placement as the cells
+    let position = (model.position) / vec3<f32>(aspect_ratio, 1.0, 1.0);
+    let offset = (instance.offset - (vec2<f32>((pan.x * 2), -((pan.y * 2))))) / vec2<f32>(aspect_ratio, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(offset, 0.0, 0.0) + vec4<f32>(position, 1.0);
+    out.tex_coords = model.tex_coords;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let radius = dot.size / 2;
+    let dist = length(in.tex_coords - vec2<f32>(0.5, 0.5));
+    // Soften the edge a little so small dots don't shimmer
+    let alpha = 1 - smoothstep(radius * 0.7, radius, dist);
+    return vec4<f32>(dot.color.rgb, dot.color.a * alpha);
+}
@@ -0,0 +1,282 @@
+//! Faint dots drawn in the middle of dead cells, so the grid can be seen
+//! without drawing lines over everything.
+
+use rustc_hash::FxHashMap;
+use wgpu::util::DeviceExt;
+
+use super::{write_instances, Cell, Instance, RenderCore, Vertex};
+
+/// How the dead cell dots are drawn
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DotSettings {
+    pub enabled: bool,
+    /// The diameter of a dot as a fraction of the cell
+    pub size: f32,
+    pub opacity: f32,
+    /// Below this grid size there would be too many dots to be useful, so
+    /// none are drawn
+    pub min_grid_size: f32,
+}
+
+impl Default for DotSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            size: 0.15,
+            opacity: 0.3,
+            min_grid_size: 0.02,
+        }
+    }
+}
+
+impl DotSettings {
+    /// The data for the dot uniform buffer
+    fn uniform(&self) -> [f32; 8] {
+        [1.0, 1.0, 1.0, self.opacity, self.size, 0.0, 0.0, 0.0]
+    }
+}
+
+pub struct DotLayer {
+    pipeline: wgpu::RenderPipeline,
+    /// What the pipeline is made from, for making it again
+    layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    instance_buffer: wgpu::Buffer,
+    instance_buffer_capacity: u64,
+    /// The number of dots in the instance buffer
+    count: u32,
+    settings: DotSettings,
+    /// Whether the visible dots have to be worked out again
+    dirty: bool,
+}
+
+impl DotLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        res_bind_group_layout: &wgpu::BindGroupLayout,
+        offset_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let settings = DotSettings::default();
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Dot Buffer"),
+            contents: bytemuck::cast_slice(&settings.uniform()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let params_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Dot Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Dot Bind Group"),
+            layout: &params_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+        });
+
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Dot Instance Buffer"),
+            size: 0,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let shader_string = include_str!("./dot.wgsl");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Dot Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_string.into()),
+        });
+        #[cfg(all(debug_assertions, feature = "validate_shaders"))]
+        if let Err(e) = super::locations::validate(
+            shader_string,
+            "vs_main",
+            &[Vertex::desc(), Instance::desc()],
+        ) {
+            panic!("Dot vertex layout doesn't match dot.wgsl: {e}");
+        }
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Dot Render Pipeline Layout"),
+            bind_group_layouts: &[
+                res_bind_group_layout,
+                &params_bind_group_layout,
+                offset_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let pipeline = create_pipeline(device, &layout, &shader, format, 1);
+
+        Self {
+            pipeline,
+            layout,
+            shader,
+            format,
+            params_buffer,
+            params_bind_group,
+            instance_buffer,
+            instance_buffer_capacity: 0,
+            count: 0,
+            settings,
+            dirty: true,
+        }
+    }
+
+    /// Note that the cells or the view changed, so the dots have to be worked
+    /// out again before the next frame.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn set_settings(&mut self, core: &RenderCore, settings: DotSettings) {
+        self.settings = settings;
+        core.queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::cast_slice(&settings.uniform()),
+        );
+        self.dirty = true;
+    }
+
+    /// Put a dot in every dead cell that's on the screen, if anything changed
+    /// since the last time. `alive` has the coordinates of every living cell,
+    /// like the renderer's index of its cells.
+    pub fn update(
+        &mut self,
+        core: &RenderCore,
+        alive: &FxHashMap<[i32; 2], usize>,
+        offset: vec2::Vector2<f32>,
+        grid_size: f32,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        self.count = 0;
+        if !self.settings.enabled
+            || grid_size < self.settings.min_grid_size
+            || size.width == 0
+            || size.height == 0
+        {
+            return;
+        }
+
+        // The view is one unit tall and centered on (0.5, 0.5), shifted by the
+        // pan position.
+        let aspect_ratio = size.width as f32 / size.height as f32;
+        let min_x = ((0.5 - aspect_ratio / 2.0 + offset.x) / grid_size).floor() as i32;
+        let max_x = ((0.5 + aspect_ratio / 2.0 + offset.x) / grid_size).floor() as i32;
+        let min_y = (offset.y / grid_size).floor() as i32;
+        let max_y = ((1.0 + offset.y) / grid_size).floor() as i32;
+
+        let mut instances = Vec::new();
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                if alive.contains_key(&[x, y]) {
+                    continue;
+                }
+                instances.push(Cell::at([x, y], grid_size).as_instance(grid_size));
+            }
+        }
+
+        write_instances(
+            core,
+            &mut self.instance_buffer,
+            &mut self.instance_buffer_capacity,
+            &instances,
+        );
+        self.count = instances.len() as u32;
+    }
+
+    /// Make the pipeline again to draw to targets with this many samples per
+    /// pixel
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.pipeline = create_pipeline(
+            device,
+            &self.layout,
+            &self.shader,
+            self.format,
+            sample_count,
+        );
+    }
+
+    /// Draw the dots, underneath anything drawn afterwards in the same pass
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        res_bind_group: &'a wgpu::BindGroup,
+        offset_bind_group: &'a wgpu::BindGroup,
+        vertex_buffer: &'a wgpu::Buffer,
+        num_vertices: u32,
+    ) {
+        if self.count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, res_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.params_bind_group, &[]);
+        render_pass.set_bind_group(2, offset_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.draw(0..num_vertices, 0..self.count);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Dot Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc(), Instance::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
@@ -0,0 +1,188 @@
+//! Lines along the edges of the cells, drawn over everything else so it's
+//! clear which cell a click will hit.
+
+use wgpu::util::DeviceExt;
+
+use super::{RenderCore, Vertex};
+
+/// How the grid lines are drawn
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GridSettings {
+    pub enabled: bool,
+    pub opacity: f32,
+    pub color: [f32; 3],
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.25,
+            color: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl GridSettings {
+    /// The data for the grid uniform buffer
+    fn uniform(&self) -> [f32; 4] {
+        let [r, g, b] = self.color;
+        [r, g, b, self.opacity]
+    }
+}
+
+pub struct GridLayer {
+    pipeline: wgpu::RenderPipeline,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    settings: GridSettings,
+}
+
+impl GridLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        res_bind_group_layout: &wgpu::BindGroupLayout,
+        grid_size_bind_group_layout: &wgpu::BindGroupLayout,
+        offset_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let settings = GridSettings::default();
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Grid Buffer"),
+            contents: bytemuck::cast_slice(&settings.uniform()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let params_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Grid Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Grid Bind Group"),
+            layout: &params_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader_string = include_str!("./grid.wgsl");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grid Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_string.into()),
+        });
+        #[cfg(all(debug_assertions, feature = "validate_shaders"))]
+        if let Err(e) = super::locations::validate(shader_string, "vs_main", &[Vertex::desc()]) {
+            panic!("Grid vertex layout doesn't match grid.wgsl: {e}");
+        }
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid Render Pipeline Layout"),
+            bind_group_layouts: &[
+                res_bind_group_layout,
+                grid_size_bind_group_layout,
+                offset_bind_group_layout,
+                &params_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Grid Render Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            params_buffer,
+            params_bind_group,
+            settings,
+        }
+    }
+
+    pub fn set_settings(&mut self, core: &RenderCore, settings: GridSettings) {
+        self.settings = settings;
+        core.queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::cast_slice(&settings.uniform()),
+        );
+    }
+
+    /// Draw the lines in their own pass over whatever is already in `view`.
+    /// The shader fades them out by itself once the cells are too small for
+    /// them to be drawn cleanly.
+    pub fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        res_bind_group: &wgpu::BindGroup,
+        grid_size_bind_group: &wgpu::BindGroup,
+        offset_bind_group: &wgpu::BindGroup,
+        vertex_buffer: &wgpu::Buffer,
+    ) {
+        if !self.settings.enabled {
+            return;
+        }
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Grid Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, res_bind_group, &[]);
+        render_pass.set_bind_group(1, grid_size_bind_group, &[]);
+        render_pass.set_bind_group(2, offset_bind_group, &[]);
+        render_pass.set_bind_group(3, &self.params_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(0..6, 0..1);
+    }
+}
@@ -0,0 +1,69 @@
+// Lines along the edges of the cells, over the whole screen
+
+struct Res {
+    data: vec2<f32>,
+    padding: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> res: Res;
+
+struct Size {
+    data: f32,
+    padding: f32,
+    padding2: vec2<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> grid_size: Size;
+
+struct Offset {
+    data: vec2<f32>,
+    padding: vec2<f32>,
+}
+
+@group(2) @binding(0)
+var<uniform> offset: Offset;
+
+@group(3) @binding(0)
+var<uniform> color: vec4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(3) tex_coords: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.clip_position = vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let res = res.data;
+    let grid_size = grid_size.data;
+    let a = res.x / res.y;
+    // The same mapping from the screen to cells as the background
+    let cells = (vec2<f32>(0.5 + ((2 * in.tex_coords.x * a) - a) / 2, in.tex_coords.y) + offset.data) / grid_size;
+
+    // How far this pixel is from the nearest cell edge, in pixels
+    let cell_px = grid_size * res.y;
+    let edge = min(fract(cells), 1 - fract(cells)) * cell_px;
+    let dist = min(edge.x, edge.y);
+    let line = 1 - smoothstep(0.5, 1.0, dist);
+
+    // Lines a few pixels apart would just cover the screen in moire, so fade
+    // them out well before the cells get that small
+    let fade = smoothstep(3.0, 8.0, cell_px);
+    return vec4<f32>(color.rgb, color.a * line * fade);
+}
@@ -13,7 +13,7 @@ use std::time::Instant;
 use web_time::Instant;
 
 use ::egui::FontDefinitions;
-use egui_plot::{Line, Plot, VLine};
+use egui_plot::{Bar, BarChart, Line, Plot, VLine};
 use egui_wgpu_backend::{RenderPass, ScreenDescriptor};
 use egui_winit_platform::{Platform, PlatformDescriptor};
 use wgpu::Device;
@@ -22,10 +22,42 @@ use winit::{
     event::{ElementState, Event},
 };
 
-use crate::game::GameState;
+use super::{theme, Msaa, RenderRequest, RenderSettings, CELL_COLOR};
+use crate::game::{
+    boundary::BoundaryMode,
+    keys::Action,
+    library::LIBRARY,
+    minimap::Minimap,
+    rules::{self, Neighborhood},
+    stats::PERIOD_MAX_POPULATION,
+    GameState, PanButton, StepModifier, Symmetry, Tool, DEFAULT_TITLE_FORMAT, MAX_GRID_SIZE,
+    MIN_GRID_SIZE,
+};
 
 #[cfg(feature = "saving")]
-use crate::game::saving::SaveGame;
+use crate::game::{
+    saving::{SaveGame, SaveInfo},
+    DEFAULT_AUTOSAVE_INTERVAL,
+};
+
+/// The slowest speed that can be picked on the slider, in generations per
+/// second
+const MIN_SPEED: f64 = 0.5;
+/// The fastest speed that can be picked on the slider, in generations per
+/// second
+const MAX_SPEED: f64 = 60.0;
+
+/// The most points drawn in the population plot. Longer histories are
+/// downsampled so that drawing the plot doesn't slow down over long runs.
+const MAX_PLOT_POINTS: usize = 2000;
+
+/// The number of squares along the longer side of the minimap
+const MINIMAP_RESOLUTION: usize = 48;
+/// The length of the longer side of the minimap, in points
+const MINIMAP_SIZE: f32 = 160.0;
+/// How many frames the minimap's thumbnail is kept for before it's made again.
+/// Making it goes through every living cell, so it isn't done every frame.
+const MINIMAP_UPDATE_FRAMES: u32 = 10;
 
 pub struct GuiState {
     platform: Platform,
@@ -101,7 +133,7 @@ impl GuiState {
         self.platform.begin_frame();
 
         // Draw the demo application.
-        self.app.ui(&self.platform.context());
+        self.app.ui(&self.platform.context(), surface_config.height);
         // End the UI frame. We could now handle the output and draw the UI with
         // the backend.
         let full_output = self.platform.end_frame(Some(&self.window));
@@ -137,6 +169,22 @@ impl GuiState {
         (encoder, tdelta)
     }
 
+    /// Take the requests the GUI has made of the renderer since this was last
+    /// called.
+    pub fn take_requests(&mut self) -> Vec<RenderRequest> {
+        std::mem::take(&mut self.app.render_requests)
+    }
+
+    /// Set the frame rate shown in the overlay
+    pub fn set_frame_rate(&mut self, fps: f64) {
+        self.app.frame_rate = fps;
+    }
+
+    /// The render settings as they have been set in the GUI
+    pub fn render_settings(&self) -> &RenderSettings {
+        &self.app.render_settings
+    }
+
     pub fn remove_textures(&mut self, tdelta: TexturesDelta) {
         self.render_pass
             .remove_textures(tdelta)
@@ -150,8 +198,41 @@ impl From<Arc<Mutex<GameState>>> for Gui {
             game_state: from,
             #[cfg(feature = "saving")]
             new_save_name: String::new(),
+            #[cfg(feature = "saving")]
+            new_slot_name: String::new(),
+            #[cfg(feature = "saving")]
+            slots: None,
+            #[cfg(all(
+                feature = "saving",
+                not(feature = "file_dialog"),
+                not(target_arch = "wasm32")
+            ))]
+            pattern_path: String::new(),
+            #[cfg(all(feature = "saving", not(target_arch = "wasm32")))]
+            pattern_path_error: None,
             intro_text_open: true,
             commonmark_cache: CommonMarkCache::default(),
+            render_settings: RenderSettings::default(),
+            render_requests: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            export_scale: 1,
+            #[cfg(not(target_arch = "wasm32"))]
+            flipbook: (8, 4, 8),
+            #[cfg(not(target_arch = "wasm32"))]
+            sprite_path: String::new(),
+            rule_text: String::new(),
+            rule_error: None,
+            goto_text: String::new(),
+            goto_error: false,
+            torus_size: (200, 200),
+            status: None,
+            overlay_open: true,
+            frame_rate: 0.0,
+            minimap_open: true,
+            minimap: None,
+            minimap_age: 0,
+            theme: theme::THEMES[0].name,
+            run_count: 100,
         }
     }
 }
@@ -162,13 +243,82 @@ struct Gui {
     game_state: Arc<Mutex<GameState>>,
     #[cfg(feature = "saving")]
     new_save_name: String,
+    #[cfg(feature = "saving")]
+    new_slot_name: String,
+    /// The names of the save slots and what's saved in them, read again
+    /// whenever they might have changed instead of every frame
+    #[cfg(feature = "saving")]
+    slots: Option<Vec<(String, Option<SaveInfo>)>>,
+    /// The path of the pattern file to load or save, when there's no file
+    /// dialog to pick it in, and why the last try failed if it did
+    #[cfg(all(
+        feature = "saving",
+        not(feature = "file_dialog"),
+        not(target_arch = "wasm32")
+    ))]
+    pattern_path: String,
+    #[cfg(all(feature = "saving", not(target_arch = "wasm32")))]
+    pattern_path_error: Option<String>,
     intro_text_open: bool,
     commonmark_cache: CommonMarkCache,
+    render_settings: RenderSettings,
+    render_requests: Vec<RenderRequest>,
+    /// How many times the size of the window exported images are
+    #[cfg(not(target_arch = "wasm32"))]
+    export_scale: u32,
+    /// The number of generations, columns and pixels per cell of exported
+    /// flipbooks
+    #[cfg(not(target_arch = "wasm32"))]
+    flipbook: (u32, u32, u32),
+    /// The path of an image to draw living cells with
+    #[cfg(not(target_arch = "wasm32"))]
+    sprite_path: String,
+    /// The rule being typed into the settings, and why it couldn't be used if
+    /// it couldn't
+    rule_text: String,
+    rule_error: Option<String>,
+    /// The coordinates being typed in to go to, and whether they couldn't be
+    /// read
+    goto_text: String,
+    goto_error: bool,
+    /// The size of the board to use when wrapping around is turned on
+    torus_size: (i32, i32),
+    /// The text of the status bar, along with what it was made from so that
+    /// it's only rebuilt when that changes
+    status: Option<(StatusKey, String)>,
+    /// Whether the generation, population and frame rate are shown in the
+    /// corner
+    overlay_open: bool,
+    /// The average frame rate, as measured by the renderer
+    frame_rate: f64,
+    /// Whether the minimap is shown in the corner
+    minimap_open: bool,
+    /// The minimap's thumbnail, and how many frames ago it was made
+    minimap: Option<Minimap>,
+    minimap_age: u32,
+    /// The name of the theme whose colors are in `render_settings`
+    theme: &'static str,
+    /// The number of generations the run button runs
+    run_count: u64,
+}
+
+/// Everything shown in the status bar
+#[derive(PartialEq)]
+struct StatusKey {
+    rule: String,
+    boundary: String,
+    grid_size: f32,
+    visible: (vec2::Vector2<i32>, vec2::Vector2<i32>),
+    /// The cell under the cursor
+    cursor: Option<vec2::Vector2<i32>>,
+    window_height: u32,
 }
 
 impl Gui {
     const PLAYING_TEXT: &'static str = "Playing \u{23F5}";
     const NOT_PLAYING_TEXT: &'static str = "Stopped \u{23F8}";
+    /// How long a step has to take before the computing indicator is shown
+    const COMPUTING_INDICATOR_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
 
     /// Render the top panel's UI elements within some `Ui`.
     fn top_panel_ui(&mut self, ui: &mut Ui) {
@@ -181,9 +331,7 @@ impl Gui {
                     .strong()
                 );
             if reset_button.clicked() {
-                game.clear();
-                game.living_count_history = vec![0];
-                game.toggle_record.clear();
+                game.request_clear();
             }
             let button_text = if game.is_playing() {
                 Self::PLAYING_TEXT
@@ -194,21 +342,247 @@ impl Gui {
             if play_button.clicked() {
                 game.toggle_playing();
             }
-            // This is needed for two reasons:
-            // - We need to lie to the GUI slider for it to feel natural
-            // - We can only set and get the interval through methods
+            // Quick steps would just make it flicker, so only show it for ones
+            // that are taking a while
+            if game
+                .computing_time()
+                .is_some_and(|t| t >= Self::COMPUTING_INDICATOR_DELAY)
+            {
+                ui.add(egui::Spinner::new());
+                ui.label("Computing...");
+            }
+            if game.worker_died() {
+                ui.label(
+                    RichText::new("The simulation thread stopped, stepping on the main one")
+                        .color(Color32::RED),
+                );
+            }
+            // The interval can only be set and read through methods, and the
+            // slider picks a rate in generations per second instead. Reading it
+            // back every frame keeps it in sync with the arrow keys.
             let speed_get_set = |set: Option<f64>| {
                 if let Some(v) = set {
-                    game.set_interval(std::time::Duration::from_secs_f64(v.powi(2)));
+                    game.set_interval(std::time::Duration::from_secs_f64(v.recip()));
                 }
-                game.get_interval().as_secs_f64().sqrt()
+                game.get_interval().as_secs_f64().recip()
             };
             ui.label("Speed: ");
-            let speed_slider =
-                Slider::from_get_set(1f64..=0.01f64, speed_get_set)
+            let speed_slider = Slider::from_get_set(MIN_SPEED..=MAX_SPEED, speed_get_set)
+                .logarithmic(true)
                 .show_value(false)
                 .clamp_to_range(true);
             ui.add(speed_slider);
+            // The arrow keys can go past the ends of the slider, so show the
+            // real rate
+            let rate = game.get_interval().as_secs_f64().recip();
+            ui.label(format!("{rate:.1} gen/s"));
+            ui.add(egui::DragValue::new(&mut self.run_count).clamp_range(1..=1_000_000));
+            if ui.button(format!("Run {}", self.run_count)).clicked() {
+                game.run_generations(self.run_count);
+            }
+            let mut run_fast = game.get_run_fast();
+            if ui
+                .checkbox(&mut run_fast, "Fast")
+                .on_hover_text("Ignore the speed while running")
+                .changed()
+            {
+                game.set_run_fast(run_fast);
+            }
+            if let Some(left) = game.generations_left() {
+                ui.label(format!("{left} to go"));
+            }
+            if game.is_turbo() {
+                ui.label("Fast forwarding \u{23E9}");
+            }
+            let mut tool = game.get_tool();
+            ui.label("Tool: ");
+            ui.selectable_value(&mut tool, Tool::Toggle, "Toggle");
+            ui.selectable_value(&mut tool, Tool::Draw, "Draw");
+            ui.selectable_value(&mut tool, Tool::Erase, "Erase");
+            if tool != game.get_tool() {
+                game.set_tool(tool);
+            }
+            let symmetries = [
+                (Symmetry::None, "None"),
+                (Symmetry::X, "Left to right"),
+                (Symmetry::Y, "Top to bottom"),
+                (Symmetry::Both, "Both"),
+            ];
+            let mut symmetry = game.get_symmetry();
+            ui.label("Symmetry: ");
+            egui::ComboBox::from_id_source("symmetry")
+                .selected_text(
+                    symmetries
+                        .iter()
+                        .find(|(s, _)| *s == symmetry)
+                        .map_or("", |(_, name)| name),
+                )
+                .show_ui(ui, |ui| {
+                    for (value, name) in symmetries {
+                        ui.selectable_value(&mut symmetry, value, name);
+                    }
+                });
+            if symmetry != game.get_symmetry() {
+                game.set_symmetry(symmetry);
+            }
+            #[cfg(target_arch = "wasm32")]
+            if ui
+                .button("Share link")
+                .on_hover_text("Put the board in the page's URL")
+                .clicked()
+                && let Err(e) = game.share_to_url()
+            {
+                log::error!("Couldn't share the board: {e}");
+            }
+            let stamp_text = if game.is_stamping() {
+                "Click to place, R to rotate, Esc to cancel"
+            } else {
+                "Stamp a pattern"
+            };
+            egui::ComboBox::from_id_source("stamp")
+                .selected_text(stamp_text)
+                .show_ui(ui, |ui| {
+                    for pattern in LIBRARY {
+                        if ui.selectable_label(false, pattern.name).clicked()
+                            && let Err(e) = game.start_stamp(pattern.rle)
+                        {
+                            log::error!("Couldn't read the {} pattern: {e}", pattern.name);
+                        }
+                    }
+                });
+            let mut auto_pause = game.get_auto_pause();
+            if ui.checkbox(&mut auto_pause, "Pause when finished").changed() {
+                game.set_auto_pause(auto_pause);
+            }
+            let mut strict = game.is_strict_rate();
+            if ui.checkbox(&mut strict, "Strict timing").changed() {
+                let sps = game.get_interval().as_secs_f64().recip();
+                game.set_strict_rate(strict.then_some(sps));
+            }
+        });
+    }
+
+    /// Render the overlay's numbers within some `Ui`.
+    fn overlay_ui(&mut self, ui: &mut Ui) {
+        let game = self.game_state.lock().unwrap();
+        ui.label(format!("Generation: {}", game.step_count()));
+        ui.label(format!("Cells: {}", game.get_living_count()));
+        ui.label(format!("FPS: {:.0}", self.frame_rate));
+    }
+
+    /// Render the minimap within some `Ui`. Clicking on it moves the view to
+    /// the spot clicked.
+    fn minimap_ui(&mut self, ui: &mut Ui) {
+        let mut game = self.game_state.lock().unwrap();
+        if self.minimap.is_none() || self.minimap_age >= MINIMAP_UPDATE_FRAMES {
+            self.minimap = Some(game.minimap(MINIMAP_RESOLUTION));
+            self.minimap_age = 0;
+        }
+        self.minimap_age += 1;
+        let Some(map) = &self.minimap else {
+            return;
+        };
+
+        let size = map.size();
+        let points_per_cell = MINIMAP_SIZE / size.x.max(size.y) as f32;
+        let (response, painter) = ui.allocate_painter(
+            egui::vec2(size.x as f32, size.y as f32) * points_per_cell,
+            egui::Sense::click(),
+        );
+        let rect = response.rect;
+        painter.rect_filled(rect, 0.0, Color32::from_black_alpha(160));
+        let square = map.scale as f32 * points_per_cell;
+        for (i, density) in map.density.iter().enumerate() {
+            if *density == 0.0 {
+                continue;
+            }
+            let corner =
+                rect.min + egui::vec2((i % map.width) as f32, (i / map.width) as f32) * square;
+            // Squares with only a few cells still have to be easy to see
+            let alpha = 0.3 + 0.7 * density.sqrt();
+            let [r, g, b, _] = CELL_COLOR;
+            painter.rect_filled(
+                egui::Rect::from_min_size(corner, egui::vec2(square, square)),
+                0.0,
+                egui::Rgba::from_rgba_unmultiplied(r, g, b, alpha),
+            );
+        }
+
+        // The view moves every frame, so it's drawn from where it is now
+        // rather than where it was when the thumbnail was made
+        let to_screen = |p: vec2::Vector2<f64>| {
+            rect.min + egui::vec2((p.x - map.min.x) as f32, (p.y - map.min.y) as f32) * points_per_cell
+        };
+        let (view_min, view_max) = game.view_rect();
+        painter.rect_stroke(
+            egui::Rect::from_two_pos(to_screen(view_min), to_screen(view_max)).intersect(rect),
+            0.0,
+            egui::Stroke::new(1.0, Color32::WHITE),
+        );
+
+        if response.clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            let fraction = (pos - rect.min) / rect.size();
+            let point = map.point_at(vec2::Vector2::new(fraction.x as f64, fraction.y as f64));
+            game.goto(point.x.floor() as i32, point.y.floor() as i32);
+        }
+    }
+
+    /// Render the status bar's text within some `Ui`.
+    fn status_bar_ui(&mut self, ui: &mut Ui, window_height: u32) {
+        let game = self.game_state.lock().unwrap();
+        let key = StatusKey {
+            rule: game.rule_string(),
+            boundary: game.boundary().to_string(),
+            grid_size: game.grid_size(),
+            visible: game.visible_cells(),
+            cursor: game.cursor_cell(),
+            window_height,
+        };
+        drop(game);
+
+        if self.status.as_ref().map_or(true, |(k, _)| *k != key) {
+            let (min, max) = key.visible;
+            let mut text = format!(
+                "{}  |  {}  |  Cell size: {:.4} ({:.1} px)  |  Visible: ({}, {}) to ({}, {})",
+                key.rule,
+                key.boundary,
+                key.grid_size,
+                key.grid_size * key.window_height as f32,
+                min.x,
+                min.y,
+                max.x,
+                max.y,
+            );
+            if let Some(cursor) = key.cursor {
+                text.push_str(&format!("  |  Cursor: ({}, {})", cursor.x, cursor.y));
+            }
+            self.status = Some((key, text));
+        }
+        ui.horizontal(|ui| {
+            if let Some((_, text)) = &self.status {
+                ui.label(text);
+            }
+            ui.separator();
+            ui.label("Go to: ");
+            let response = egui::TextEdit::singleline(&mut self.goto_text)
+                .hint_text("x, y")
+                .desired_width(80.0)
+                .show(ui)
+                .response;
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                match parse_coordinates(&self.goto_text) {
+                    Some((x, y)) => {
+                        self.game_state.lock().unwrap().goto(x, y);
+                        self.goto_error = false;
+                    }
+                    None => self.goto_error = true,
+                }
+            }
+            if self.goto_error {
+                ui.label(RichText::new("Type two whole numbers, like 10, -5").color(Color32::RED));
+            }
         });
     }
 
@@ -216,6 +590,37 @@ impl Gui {
     fn simulation_stats_ui(&mut self, ui: &mut Ui) {
         let mut game = self.game_state.lock().unwrap();
         ui.label(format!("Living Cells: {}", game.get_living_count()));
+        ui.label(format!("Status: {}", game.status));
+        ui.label(format!("Trend: {}", game.population_trend()));
+        match game.detected_period {
+            None if game.get_living_count() > PERIOD_MAX_POPULATION => {
+                ui.label("Period: not checked, too many cells")
+            }
+            Some(1) => ui.label("Still life"),
+            Some(period) => ui.label(format!("Period: {period}")),
+            None => ui.label("Period: none found"),
+        };
+        if let Some(velocity) = game.detected_velocity {
+            ui.label(format!("Spaceship: {velocity}"));
+        }
+        let shape = game.shape_stats();
+        if let Some((min, max)) = shape.bounding_box {
+            ui.label(format!(
+                "Bounding box: ({}, {}) to ({}, {}), {}\u{d7}{}",
+                min.x,
+                min.y,
+                max.x,
+                max.y,
+                max.x - min.x + 1,
+                max.y - min.y + 1,
+            ));
+        }
+        if let Some(center) = shape.center_of_mass {
+            ui.label(format!("Center of mass: ({:.2}, {:.2})", center.x, center.y));
+        }
+        if let Some(drift) = game.center_of_mass_drift() {
+            ui.label(format!("Drift: ({:.3}, {:.3}) cells/gen", drift.x, drift.y));
+        }
         ui.vertical_centered(|ui| {
             let reset_button = ui.button(
                 RichText::new("Reset stats and graph")
@@ -223,19 +628,30 @@ impl Gui {
                     .strong(),
             );
             if reset_button.clicked() {
-                game.step_count = 0;
+                game.reset_step_count();
                 game.living_count_history = vec![0];
                 game.toggle_record.clear();
             }
+            if ui.button("Export history as CSV").clicked() {
+                let name = chrono::Local::now()
+                    .format("history-%Y%m%d-%H%M%S.csv")
+                    .to_string();
+                match save_text(&name, &game.export_history_csv()) {
+                    Ok(()) => log::info!("Saved the history to {name}"),
+                    Err(e) => log::error!("Failed to save the history: {e}"),
+                }
+            }
         });
-        ui.label(format!("Total Steps: {} ", game.step_count));
-        let line_values = game
-            .living_count_history
-            .iter()
-            .enumerate()
-            .map(|(i, j)| [i as f64, *j as f64])
-            .collect::<Vec<[f64; 2]>>();
-        let line = Line::new(line_values);
+        ui.label(format!("Total Steps: {} ", game.step_count()));
+        if game.is_strict_rate() {
+            ui.label(format!("Dropped Steps: {}", game.dropped_generations()));
+        }
+        let line = Line::new(downsample(
+            &game.living_count_history,
+            game.first_history_generation(),
+            MAX_PLOT_POINTS,
+        ))
+        .name("Population");
         Plot::new("living_cell_count_plot")
             .show_axes(false) // This was causing annoying margins
             .show(ui, |plot_ui| {
@@ -247,21 +663,478 @@ impl Gui {
                     }
                 }
             });
+
+        ui.label("Cell ages:");
+        let histogram = game.age_histogram();
+        let bars = histogram
+            .iter()
+            .enumerate()
+            .map(|(i, count)| {
+                let age = i + 1;
+                let name = if age == histogram.len() {
+                    format!("{age}+")
+                } else {
+                    age.to_string()
+                };
+                Bar::new(age as f64, *count as f64).name(name)
+            })
+            .collect();
+        Plot::new("age_histogram_plot")
+            .show_axes(false)
+            .height(100.0)
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(bars));
+            });
+    }
+
+    /// Render the settings within some `Ui`.
+    fn settings_ui(&mut self, ui: &mut Ui) {
+        let mut game = self.game_state.lock().unwrap();
+        let double_click_get_set = |set: Option<f64>| {
+            if let Some(v) = set {
+                game.set_double_click_time(std::time::Duration::from_millis(v as u64));
+            }
+            game.get_double_click_time().as_millis() as f64
+        };
+        ui.horizontal(|ui| {
+            ui.label("Double-click time (ms): ");
+            ui.add(Slider::from_get_set(100f64..=1000f64, double_click_get_set).integer());
+        });
+        let mut animate_zoom = game.get_animate_zoom();
+        if ui.checkbox(&mut animate_zoom, "Animate double-click zoom").changed() {
+            game.set_animate_zoom(animate_zoom);
+        }
+        let scroll_get_set = |set: Option<f64>| {
+            if let Some(v) = set {
+                game.set_scroll_sensitivity(v);
+            }
+            game.get_scroll_sensitivity()
+        };
+        ui.horizontal(|ui| {
+            ui.label("Scroll sensitivity: ");
+            ui.add(Slider::from_get_set(0.1..=5.0, scroll_get_set).logarithmic(true));
+        });
+        ui.horizontal(|ui| {
+            let mut pan_button = game.get_pan_button();
+            ui.label("Pan with: ");
+            ui.selectable_value(&mut pan_button, PanButton::Right, "Right button");
+            ui.selectable_value(&mut pan_button, PanButton::Middle, "Middle button");
+            ui.selectable_value(&mut pan_button, PanButton::Either, "Either");
+            if pan_button != game.get_pan_button() {
+                game.set_pan_button(pan_button);
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut modifier = game.get_scroll_step_modifier();
+            ui.label("Scroll through generations while paused with: ")
+                .on_hover_text("Scrolling up steps forwards and scrolling down steps back");
+            ui.selectable_value(&mut modifier, Some(StepModifier::Control), "Ctrl");
+            ui.selectable_value(&mut modifier, Some(StepModifier::Shift), "Shift");
+            ui.selectable_value(&mut modifier, Some(StepModifier::Alt), "Alt");
+            ui.selectable_value(&mut modifier, None, "Off");
+            if modifier != game.get_scroll_step_modifier() {
+                game.set_scroll_step_modifier(modifier);
+            }
+        });
+
+        let history_get_set = |set: Option<f64>| {
+            if let Some(v) = set {
+                game.set_history_limit(v as usize);
+            }
+            game.get_history_limit() as f64
+        };
+        ui.horizontal(|ui| {
+            ui.label("Generations to keep for stepping back: ");
+            ui.add(Slider::from_get_set(0f64..=1024f64, history_get_set).integer());
+        });
+        let mut memory_limit = game.get_history_memory_limit();
+        ui.horizontal(|ui| {
+            let mut enabled = memory_limit.is_some();
+            if ui.checkbox(&mut enabled, "Limit the memory for stepping back to").changed() {
+                memory_limit = enabled.then_some(64 << 20);
+            }
+            if let Some(limit) = &mut memory_limit {
+                let mut mib = *limit >> 20;
+                ui.add(Slider::new(&mut mib, 1..=1024).suffix(" MiB").logarithmic(true));
+                *limit = mib << 20;
+            }
+        });
+        if memory_limit != game.get_history_memory_limit() {
+            game.set_history_memory_limit(memory_limit);
+        }
+        let step_back_memory = game.history_memory();
+        ui.label(format!(
+            "Generations for stepping back take up about {:.1} MiB",
+            step_back_memory as f64 / (1 << 20) as f64,
+        ));
+        ui.label(format!(
+            "The population graph and finding oscillators take up about {:.1} MiB more, \
+             which isn't limited",
+            (game.total_history_memory() - step_back_memory) as f64 / (1 << 20) as f64,
+        ));
+
+        #[cfg(feature = "saving")]
+        {
+            let mut autosave = game.get_autosave_interval();
+            ui.horizontal(|ui| {
+                let mut enabled = autosave.is_some();
+                if ui.checkbox(&mut enabled, "Autosave every").changed() {
+                    autosave = enabled.then_some(DEFAULT_AUTOSAVE_INTERVAL);
+                }
+                if let Some(interval) = &mut autosave {
+                    let mut secs = interval.as_secs();
+                    ui.add(Slider::new(&mut secs, 5..=600).suffix(" s").logarithmic(true));
+                    *interval = std::time::Duration::from_secs(secs);
+                }
+            });
+            if autosave != game.get_autosave_interval() {
+                game.set_autosave_interval(autosave);
+            }
+        }
+
+        ui.separator();
+        if self.rule_text.is_empty() {
+            self.rule_text = game.rule_string();
+        }
+        ui.horizontal(|ui| {
+            ui.label("Rule: ");
+            let response = ui.text_edit_singleline(&mut self.rule_text);
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                match self.rule_text.parse() {
+                    Ok(ruleset) => {
+                        game.set_ruleset(ruleset);
+                        self.rule_text = game.rule_string();
+                        self.rule_error = None;
+                    }
+                    Err(e) => self.rule_error = Some(e.to_string()),
+                }
+            }
+        });
+        if let Some(e) = &self.rule_error {
+            ui.label(RichText::new(e).color(Color32::RED));
+        }
+
+        let ruleset = game.ruleset();
+        let mut neighborhood = ruleset.neighborhood;
+        let mut range = neighborhood.range();
+        ui.horizontal(|ui| {
+            ui.label("Neighborhood: ");
+            egui::ComboBox::from_id_source("neighborhood")
+                .selected_text(neighborhood.to_string())
+                .show_ui(ui, |ui| {
+                    for choice in [
+                        Neighborhood::Moore { range },
+                        Neighborhood::VonNeumann { range },
+                    ] {
+                        ui.selectable_value(&mut neighborhood, choice, choice.to_string());
+                    }
+                });
+            ui.label("Range: ");
+            ui.add(egui::DragValue::new(&mut range).clamp_range(1..=rules::MAX_RANGE));
+        });
+        let neighborhood = neighborhood.with_range(range);
+        if neighborhood != ruleset.neighborhood {
+            game.set_ruleset(ruleset.with_neighborhood(neighborhood));
+            self.rule_text = game.rule_string();
+            self.rule_error = None;
+        }
+
+        let noise = game.noise();
+        let mut noisy = noise.is_some();
+        let rules::Noise {
+            mut probability,
+            mut seed,
+        } = noise.unwrap_or(rules::Noise::new(0.9, 0));
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut noisy, "Noisy")
+                .on_hover_text("Cells are only born or survive some of the time");
+            ui.add_enabled(
+                noisy,
+                egui::DragValue::new(&mut probability)
+                    .clamp_range(0.0..=1.0)
+                    .speed(0.01),
+            );
+            ui.label("Seed: ");
+            ui.add_enabled(noisy, egui::DragValue::new(&mut seed));
+        });
+        let new_noise = noisy.then(|| rules::Noise::new(probability, seed));
+        if new_noise != noise {
+            game.set_noise(new_noise);
+        }
+
+        let mut wrapping = game.boundary() != BoundaryMode::Infinite;
+        let mut changed = false;
+        let min = BoundaryMode::min_torus_size(game.ruleset().neighborhood.range());
+        ui.horizontal(|ui| {
+            changed |= ui.checkbox(&mut wrapping, "Wrap around").changed();
+            let (width, height) = &mut self.torus_size;
+            let sizes = min..=10000;
+            // Every size on the way would wrap the cells onto it for good, so
+            // a size is only used once it's been dragged to
+            let settled = |response: egui::Response| {
+                response.drag_stopped()
+                    || response.lost_focus()
+                    || (response.changed() && !response.dragged())
+            };
+            changed |= settled(ui.add(egui::DragValue::new(width).clamp_range(sizes.clone())));
+            ui.label("\u{d7}");
+            changed |= settled(ui.add(egui::DragValue::new(height).clamp_range(sizes)));
+        });
+        let (width, height) = self.torus_size;
+        let boundary = if wrapping {
+            BoundaryMode::Toroidal { width, height }
+        } else {
+            BoundaryMode::Infinite
+        };
+        if changed && boundary != game.boundary() {
+            game.set_boundary(boundary);
+        }
+
+        let mut pause_on_unfocus = game.get_pause_on_unfocus();
+        if ui
+            .checkbox(&mut pause_on_unfocus, "Pause when the window isn't focused")
+            .changed()
+        {
+            game.set_pause_on_unfocus(pause_on_unfocus);
+        }
+
+        let mut confirm_clear = game.get_confirm_clear();
+        if ui
+            .checkbox(&mut confirm_clear, "Ask before clearing the board")
+            .changed()
+        {
+            game.set_confirm_clear(confirm_clear);
+        }
+
+        let mut title = game.get_title_format().is_some();
+        if ui
+            .checkbox(&mut title, "Show the generation and population in the title")
+            .changed()
+        {
+            game.set_title_format(title.then(|| DEFAULT_TITLE_FORMAT.to_string()));
+        }
+
+        let mut highlight = game.get_highlight_changes();
+        if ui.checkbox(&mut highlight, "Highlight births and deaths").changed() {
+            game.set_highlight_changes(highlight);
+        }
+        ui.horizontal(|ui| {
+            let fade_out = game.get_fade_out();
+            let mut fade = fade_out.is_some();
+            let mut fraction = fade_out.unwrap_or(0.5);
+            ui.checkbox(&mut fade, "Fade out dying cells");
+            if fade {
+                ui.add(Slider::new(&mut fraction, 0.05..=1.0).text("of a step"));
+            }
+            let new_fade_out = fade.then_some(fraction);
+            if new_fade_out != fade_out {
+                game.set_fade_out(new_fade_out);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let mut heatmap = game.get_show_heatmap();
+            if ui
+                .checkbox(&mut heatmap, "Heatmap of how often cells were alive")
+                .changed()
+            {
+                game.set_show_heatmap(heatmap);
+            }
+            if ui.button("Reset").clicked() {
+                game.reset_heatmap();
+            }
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Theme: ");
+            egui::ComboBox::from_id_source("theme")
+                .selected_text(game.theme_name())
+                .show_ui(ui, |ui| {
+                    for theme in theme::THEMES {
+                        if ui
+                            .selectable_label(theme.name == game.theme_name(), theme.name)
+                            .clicked()
+                        {
+                            game.set_theme(theme.name);
+                        }
+                    }
+                });
+        });
+        drop(game);
+
+        ui.separator();
+        let settings = &mut self.render_settings;
+        let mut solid_bg = settings.clear_color.is_some();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut solid_bg, "Solid background");
+            let color = settings.clear_color.unwrap_or(wgpu::Color::BLACK);
+            let mut rgba = [color.r as f32, color.g as f32, color.b as f32, color.a as f32];
+            if solid_bg {
+                ui.color_edit_button_rgba_unmultiplied(&mut rgba);
+            }
+            settings.clear_color = solid_bg.then_some(wgpu::Color {
+                r: rgba[0] as f64,
+                g: rgba[1] as f64,
+                b: rgba[2] as f64,
+                a: rgba[3] as f64,
+            });
+        });
+
+        ui.checkbox(&mut self.overlay_open, "Show the stats overlay");
+        ui.checkbox(&mut self.minimap_open, "Show the minimap");
+        ui.add(Slider::new(&mut settings.cell_fill, 0.1..=1.0).text("Cell size"));
+        ui.checkbox(&mut settings.uncap_frame_rate, "Uncap the frame rate");
+        ui.checkbox(&mut settings.smooth_panning, "Smooth panning")
+            .on_hover_text("Glide to where the view is dragged instead of following the mouse");
+        ui.horizontal(|ui| {
+            ui.label("Anti-aliasing: ");
+            egui::ComboBox::from_id_source("msaa")
+                .selected_text(settings.msaa.to_string())
+                .show_ui(ui, |ui| {
+                    for msaa in Msaa::ALL {
+                        ui.selectable_value(&mut settings.msaa, msaa, msaa.to_string());
+                    }
+                });
+        });
+
+        let dots = &mut settings.dots;
+        ui.checkbox(&mut dots.enabled, "Dots in dead cells");
+        if dots.enabled {
+            ui.add(Slider::new(&mut dots.size, 0.05..=0.5).text("Dot size"));
+            ui.add(Slider::new(&mut dots.opacity, 0.05..=1.0).text("Dot opacity"));
+            // The grid size is the height of a cell as a fraction of the window
+            ui.add(
+                Slider::new(&mut dots.min_grid_size, MIN_GRID_SIZE..=MAX_GRID_SIZE)
+                    .logarithmic(true)
+                    .text("Smallest cells with dots"),
+            );
+        }
+        let grid = &mut settings.grid;
+        ui.checkbox(&mut grid.enabled, "Grid lines");
+        if grid.enabled {
+            ui.add(Slider::new(&mut grid.opacity, 0.05..=1.0).text("Grid opacity"));
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(Slider::new(&mut self.export_scale, 1..=8).text("x"));
+                if ui.button("Export view").clicked() {
+                    self.render_requests.push(RenderRequest::ExportView {
+                        scale: self.export_scale,
+                    });
+                }
+            });
+            let (frames, cols, cell_px) = &mut self.flipbook;
+            ui.add(Slider::new(frames, 1..=64).text("Generations"));
+            ui.add(Slider::new(cols, 1..=16).text("Columns"));
+            ui.add(Slider::new(cell_px, 1..=32).text("Pixels per cell"));
+            if ui.button("Export flipbook").clicked() {
+                self.render_requests.push(RenderRequest::ExportFlipbook {
+                    frames: *frames,
+                    cols: *cols,
+                    cell_px: *cell_px,
+                });
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                egui::TextEdit::singleline(&mut self.sprite_path)
+                    .hint_text("sprite.png")
+                    .show(ui);
+                if ui
+                    .button("Use for cells")
+                    .on_hover_text("Draw living cells with this image")
+                    .clicked()
+                {
+                    self.render_requests
+                        .push(RenderRequest::LoadCellTexture(self.sprite_path.clone().into()));
+                }
+            });
+        }
+    }
+
+    /// Show the key for every action, with buttons to pick new ones
+    fn keys_ui(&mut self, ui: &mut Ui) {
+        let mut game = self.game_state.lock().unwrap();
+        let rebinding = game.get_rebinding();
+        egui::Grid::new("key_bindings").striped(true).show(ui, |ui| {
+            for &action in Action::ALL {
+                ui.label(action.to_string());
+                let key = if rebinding == Some(action) {
+                    "Press a key...".to_string()
+                } else {
+                    match game.key_bindings().keys(action) {
+                        [] => "None".to_string(),
+                        keys => keys.join(" or "),
+                    }
+                };
+                let button = ui
+                    .button(key)
+                    .on_hover_text("Click, then press the new key. Escape keeps the old one.");
+                if button.clicked() {
+                    // Otherwise Space or Enter would click the button again
+                    // instead of being picked
+                    button.surrender_focus();
+                    game.set_rebinding(Some(action));
+                }
+                if ui.small_button("Unbind").clicked() {
+                    game.unbind_key(action);
+                }
+                ui.end_row();
+            }
+        });
+        if ui.button("Reset to defaults").clicked() {
+            game.reset_key_bindings();
+        }
     }
 
     /// Render the interface for saving and loading within some `Ui`.
     #[cfg(feature = "saving")]
     fn saving_ui(&mut self, ui: &mut Ui) {
-        let mut game = self.game_state.lock().unwrap();
+        // There's no save file on the web or when it's turned off, so only the
+        // slots and pattern files are shown then
+        if self.game_state.lock().unwrap().save_file.is_some() {
+            self.save_file_ui(ui);
+            ui.separator();
+        }
+        self.slots_ui(ui);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.separator();
+            self.pattern_file_ui(ui);
+        }
+    }
 
+    /// Render the saves in the save file, with buttons to load and delete
+    /// them and to add new ones
+    #[cfg(feature = "saving")]
+    fn save_file_ui(&mut self, ui: &mut Ui) {
+        let mut game = self.game_state.lock().unwrap();
         let save_file = game.save_file.as_ref().expect("Expected save file.");
+        ui.label(format!("Saves are kept in {}", save_file.path().display()));
         let save_count = save_file.save_count();
-        for (i, save) in save_file.saves_iter().enumerate() {
+        for i in 0..save_count {
+            let info = game.save_file.as_ref().unwrap().info(i);
             ui.horizontal(|ui| {
-                ui.label(&save.name);
-                ui.label(&save.created.format("%B %e").to_string());
-                if ui.button("Load").clicked() {
-                    game.load_save(&save);
+                if let Some(info) = info {
+                    ui.label(&info.name);
+                    ui.label(info.saved_at.format("%B %e").to_string());
+                    ui.label(format!(
+                        "gen {}, {} cells",
+                        info.generation, info.cell_count
+                    ));
+                    if ui.button("Load").clicked()
+                        && let Some(save) = game.save_file.as_ref().unwrap().get(i)
+                    {
+                        game.load_save(&save);
+                    }
+                } else {
+                    ui.label(RichText::new("Unreadable save").italics());
                 }
                 if ui.button(RichText::new("Delete").color(Color32::RED)).clicked() {
                     let _ = game.save_file.as_mut().unwrap().delete_save(i);
@@ -274,6 +1147,9 @@ impl Gui {
         TextEdit::singleline(&mut self.new_save_name)
             .hint_text("Save Name")
             .show(ui);
+        if game.save_file.as_ref().unwrap().has_save(&self.new_save_name) {
+            ui.label("A save with this name already exists.");
+        }
         if ui.button("Save").clicked() && !self.new_save_name.is_empty() {
             let new_save = SaveGame::new(
                 game.deref_mut(),
@@ -283,15 +1159,171 @@ impl Gui {
         }
     }
 
+    /// Render the interface for loading and saving pattern files within some
+    /// `Ui`.
+    #[cfg(all(feature = "saving", not(target_arch = "wasm32")))]
+    fn pattern_file_ui(&mut self, ui: &mut Ui) {
+        ui.label(RichText::new("Files").strong());
+        #[cfg(not(feature = "file_dialog"))]
+        TextEdit::singleline(&mut self.pattern_path)
+            .hint_text("pattern.rle, .cells or .json")
+            .show(ui);
+        ui.horizontal(|ui| {
+            let mut result = None;
+            if ui.button("Load…").clicked()
+                && let Some(path) = self.pattern_file(false)
+            {
+                result = Some(self.game_state.lock().unwrap().load_pattern_file(&path));
+            }
+            if ui.button("Save As…").clicked()
+                && let Some(path) = self.pattern_file(true)
+            {
+                result = Some(self.game_state.lock().unwrap().save_pattern_file(&path));
+            }
+            if let Some(result) = result {
+                self.pattern_path_error = result.err().map(|e| e.to_string());
+            }
+        });
+        if let Some(error) = &self.pattern_path_error {
+            ui.label(RichText::new(error).color(Color32::RED));
+        }
+    }
+
+    /// The pattern file to load, or to save to with `save`, picked in the
+    /// system's file dialog. `None` if the dialog was closed without one.
+    #[cfg(all(
+        feature = "saving",
+        feature = "file_dialog",
+        not(target_arch = "wasm32")
+    ))]
+    fn pattern_file(&self, save: bool) -> Option<std::path::PathBuf> {
+        let dialog = rfd::FileDialog::new().add_filter("Patterns", &["rle", "cells", "json"]);
+        if save {
+            dialog.set_file_name("pattern.rle").save_file()
+        } else {
+            dialog.pick_file()
+        }
+    }
+
+    /// The pattern file typed into the path field
+    #[cfg(all(
+        feature = "saving",
+        not(feature = "file_dialog"),
+        not(target_arch = "wasm32")
+    ))]
+    fn pattern_file(&self, _save: bool) -> Option<std::path::PathBuf> {
+        Some(self.pattern_path.clone().into())
+    }
+
+    /// Render the list of save slots, each saved separately, within some
+    /// `Ui`.
+    #[cfg(feature = "saving")]
+    fn slots_ui(&mut self, ui: &mut Ui) {
+        let mut game = self.game_state.lock().unwrap();
+        ui.label(RichText::new("Slots").strong());
+        if self.slots.is_none() {
+            let names = game.slot_names().unwrap_or_else(|e| {
+                log::error!("Failed to list the save slots: {e}");
+                Vec::new()
+            });
+            self.slots = Some(
+                names
+                    .into_iter()
+                    .map(|name| {
+                        let info = game.slot_info(&name);
+                        (name, info)
+                    })
+                    .collect(),
+            );
+        }
+        let mut changed = false;
+        for (name, info) in self.slots.iter().flatten() {
+            ui.horizontal(|ui| {
+                ui.label(name);
+                if let Some(info) = info {
+                    ui.label(info.saved_at.format("%B %e").to_string());
+                    ui.label(format!(
+                        "gen {}, {} cells",
+                        info.generation, info.cell_count
+                    ));
+                } else {
+                    ui.label(RichText::new("Unreadable save").italics());
+                }
+                if ui.button("Load").clicked()
+                    && let Err(e) = game.load_from_slot(name)
+                {
+                    log::error!("Failed to load the slot {name}: {e}");
+                }
+                if ui.button(RichText::new("Delete").color(Color32::RED)).clicked() {
+                    if let Err(e) = game.delete_slot(name) {
+                        log::error!("Failed to delete the slot {name}: {e}");
+                    }
+                    changed = true;
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            TextEdit::singleline(&mut self.new_slot_name)
+                .hint_text("Slot Name")
+                .show(ui);
+            if ui.button("Save to slot").clicked() && !self.new_slot_name.is_empty() {
+                match game.save_to_slot(&self.new_slot_name) {
+                    Ok(()) => self.new_slot_name.clear(),
+                    Err(e) => log::error!("Failed to save to the slot: {e}"),
+                }
+                changed = true;
+            }
+        });
+        if changed {
+            self.slots = None;
+        }
+    }
+
     /// Render the interface to an `Egui::Context`.
-    fn ui(&mut self, ctx: &Context) {
+    fn ui(&mut self, ctx: &Context, window_height: u32) {
         use egui_commonmark::commonmark_str;
 
+        // The theme can be changed from the keyboard or by loading a save too,
+        // so its colors are applied whenever it's different
+        let theme = self.game_state.lock().unwrap().theme_name();
+        if theme != self.theme {
+            if let Some(t) = theme::find(theme) {
+                t.apply(&mut self.render_settings);
+            }
+            self.theme = theme;
+        }
+
         // Top panel with some controls
         egui::containers::panel::TopBottomPanel::top(Id::new("top_panel"))
             .show(ctx, |ui| {
                 self.top_panel_ui(ui);
             });
+        // Status bar with the rule and where the view is
+        egui::containers::panel::TopBottomPanel::bottom(Id::new("status_bar"))
+            .show(ctx, |ui| {
+                self.status_bar_ui(ui, window_height);
+            });
+        // The most important numbers, out of the way in a corner
+        if self.overlay_open {
+            egui::Window::new("Overlay")
+                .title_bar(false)
+                .resizable(false)
+                .interactable(false)
+                .anchor(egui::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+                .show(ctx, |ui| {
+                    self.overlay_ui(ui);
+                });
+        }
+        // Where the view is compared to the pattern, in the other corner
+        if self.minimap_open {
+            egui::Window::new("Minimap")
+                .title_bar(false)
+                .resizable(false)
+                .anchor(egui::Align2::LEFT_BOTTOM, [8.0, -8.0])
+                .show(ctx, |ui| {
+                    self.minimap_ui(ui);
+                });
+        }
         // Collapsible window with statistics shown
         egui::Window::new("Simulation Stats")
             .show(ctx, |ui| {
@@ -299,6 +1331,18 @@ impl Gui {
             })
             .expect("Expected open window");
 
+        egui::Window::new("Settings")
+            .default_open(false)
+            .show(ctx, |ui| {
+                self.settings_ui(ui);
+            });
+
+        egui::Window::new("Keys")
+            .default_open(false)
+            .show(ctx, |ui| {
+                self.keys_ui(ui);
+            });
+
         // Collapsible window with a game saving menu.
         #[cfg(feature = "saving")]
         egui::Window::new("Game Saves")
@@ -306,6 +1350,39 @@ impl Gui {
                 self.saving_ui(ui);
             });
 
+        // Messages for the player, such as why pasting didn't work
+        let toast = self.game_state.lock().unwrap().get_toast().map(str::to_string);
+        if let Some(toast) = toast {
+            egui::Window::new("Toast")
+                .title_bar(false)
+                .resizable(false)
+                .interactable(false)
+                .anchor(egui::Align2::CENTER_TOP, [0.0, 48.0])
+                .show(ctx, |ui| {
+                    ui.label(RichText::new(toast).color(Color32::RED));
+                });
+        }
+
+        // Clearing can't be undone, so it has to be confirmed
+        if self.game_state.lock().unwrap().is_clear_pending() {
+            egui::Window::new("Clear the board?")
+                .resizable(false)
+                .collapsible(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    let mut game = self.game_state.lock().unwrap();
+                    ui.label("Every cell will be removed, and this can't be undone.");
+                    ui.horizontal(|ui| {
+                        if ui.button(RichText::new("Yes").color(Color32::RED)).clicked() {
+                            game.confirm_clear();
+                        }
+                        if ui.button("No").clicked() {
+                            game.cancel_clear();
+                        }
+                    });
+                });
+        }
+
         egui::Window::new("Introduction").open(&mut self.intro_text_open)
             .resizable(false)
             .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
@@ -316,3 +1393,104 @@ impl Gui {
             });
     }
 }
+
+/// Save some text to a file in the working directory
+#[cfg(not(target_arch = "wasm32"))]
+fn save_text(name: &str, contents: &str) -> anyhow::Result<()> {
+    std::fs::write(name, contents)?;
+    Ok(())
+}
+
+/// Have the browser download some text as a file
+#[cfg(target_arch = "wasm32")]
+fn save_text(name: &str, contents: &str) -> anyhow::Result<()> {
+    use wasm_bindgen::JsCast;
+
+    let error = |e: wasm_bindgen::JsValue| anyhow::anyhow!("{e:?}");
+    let parts = js_sys::Array::of1(&contents.into());
+    let mut options = web_sys::BlobPropertyBag::new();
+    options.type_("text/plain");
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options).map_err(error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(error)?;
+
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| anyhow::anyhow!("There's no document to download from"))?;
+    let link = document
+        .create_element("a")
+        .map_err(error)?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|e| error(e.into()))?;
+    link.set_href(&url);
+    link.set_download(name);
+    link.click();
+    web_sys::Url::revoke_object_url(&url).map_err(error)?;
+    Ok(())
+}
+
+/// Read a pair of coordinates typed like `10, -5` or `10 -5`
+fn parse_coordinates(text: &str) -> Option<(i32, i32)> {
+    let mut numbers = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(str::parse);
+    match (numbers.next(), numbers.next(), numbers.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Some((x, y)),
+        _ => None,
+    }
+}
+
+/// Turn a history of values, the first from generation `first`, into points
+/// of (generation, value), with at most about `max_points` of them. The
+/// history is split into equal buckets, and the lowest and highest value of
+/// each one are kept so that spikes still show up.
+fn downsample(history: &[usize], first: u64, max_points: usize) -> Vec<[f64; 2]> {
+    let point = |i: usize| [(first + i as u64) as f64, history[i] as f64];
+    if history.len() <= max_points {
+        return (0..history.len()).map(point).collect();
+    }
+    let bucket = history.len().div_ceil(max_points / 2);
+    let mut points = Vec::with_capacity(max_points + 1);
+    for start in (0..history.len()).step_by(bucket) {
+        let end = (start + bucket).min(history.len());
+        let (mut min, mut max) = (start, start);
+        for i in start..end {
+            if history[i] < history[min] {
+                min = i;
+            }
+            if history[i] > history[max] {
+                max = i;
+            }
+        }
+        points.push(point(min.min(max)));
+        if min != max {
+            points.push(point(min.max(max)));
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_histories_are_kept_whole() {
+        let points = downsample(&[3, 1, 4], 10, 100);
+        assert_eq!(points, [[10.0, 3.0], [11.0, 1.0], [12.0, 4.0]]);
+    }
+
+    #[test]
+    fn downsampling_keeps_spikes() {
+        let mut history = vec![50; 1000];
+        history[123] = 900;
+        history[777] = 0;
+        let points = downsample(&history, 5, 100);
+        assert!(points.len() <= 100);
+        // In order, starting from the first generation
+        assert_eq!(points[0][0], 5.0);
+        assert!(points.windows(2).all(|p| p[0][0] < p[1][0]));
+        assert!(points.contains(&[128.0, 900.0]));
+        assert!(points.contains(&[782.0, 0.0]));
+    }
+}
@@ -0,0 +1,110 @@
+//! The `@location` slots used by the vertex stage of the shaders.
+//!
+//! Every vertex and instance attribute handed to a pipeline is assigned one of
+//! these, and they have to line up with the `@location(n)` attributes in
+//! `shader.wgsl`, `bg.wgsl`, `dot.wgsl` and `grid.wgsl`. A mismatch doesn't
+//! produce an error from wgpu, just garbage on the screen, so debug builds
+//! with the `validate_shaders` feature check the layouts against the shader
+//! source with `validate` when the pipelines are created.
+
+/// `Vertex::position`, used by both shaders.
+pub const POSITION: u32 = 0;
+/// `Instance::offset`, used by the cell shader.
+pub const INSTANCE_OFFSET: u32 = 1;
+/// `Instance::center`, used by the cell shader.
+pub const INSTANCE_CENTER: u32 = 2;
+/// `Vertex::tex_coords`, used by both shaders.
+pub const TEX_COORDS: u32 = 3;
+/// `Instance::highlight`, used by the cell shader.
+pub const INSTANCE_HIGHLIGHT: u32 = 5;
+/// `Instance::level`, used by the cell shader.
+pub const INSTANCE_LEVEL: u32 = 6;
+
+/// Check that the vertex buffer layouts given to a pipeline provide every
+/// `@location` input of the shader's vertex entry point exactly once, and with
+/// the right number of components.
+///
+/// Returns a description of the first problem found.
+#[cfg(all(debug_assertions, feature = "validate_shaders"))]
+pub fn validate(
+    source: &str,
+    entry_point: &str,
+    layouts: &[wgpu::VertexBufferLayout],
+) -> Result<(), String> {
+    use naga::{Binding, ShaderStage, TypeInner};
+
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| e.emit_to_string(source))?;
+    let entry = module
+        .entry_points
+        .iter()
+        .find(|e| e.name == entry_point && e.stage == ShaderStage::Vertex)
+        .ok_or_else(|| format!("no vertex entry point named `{entry_point}`"))?;
+
+    // Collect the (location, component count) of every input, looking inside
+    // structs since that's how our shaders declare them.
+    let components = |ty: naga::Handle<naga::Type>| match module.types[ty].inner {
+        TypeInner::Scalar(_) => 1,
+        TypeInner::Vector { size, .. } => size as u32,
+        _ => 0,
+    };
+    let mut inputs = Vec::new();
+    for arg in entry.function.arguments.iter() {
+        match &arg.binding {
+            Some(Binding::Location { location, .. }) => inputs.push((*location, components(arg.ty))),
+            Some(Binding::BuiltIn(_)) => (),
+            None => {
+                if let TypeInner::Struct { members, .. } = &module.types[arg.ty].inner {
+                    for member in members {
+                        if let Some(Binding::Location { location, .. }) = member.binding {
+                            inputs.push((location, components(member.ty)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let attributes = layouts
+        .iter()
+        .flat_map(|l| l.attributes.iter())
+        .collect::<Vec<_>>();
+    for (i, attr) in attributes.iter().enumerate() {
+        if attributes[..i]
+            .iter()
+            .any(|a| a.shader_location == attr.shader_location)
+        {
+            return Err(format!(
+                "location {} is assigned to more than one attribute",
+                attr.shader_location
+            ));
+        }
+    }
+
+    for (location, count) in inputs {
+        let Some(attr) = attributes.iter().find(|a| a.shader_location == location) else {
+            return Err(format!("location {location} is not provided by any buffer"));
+        };
+        let provided = format_components(attr.format);
+        if provided.is_some_and(|p| p != count) {
+            return Err(format!(
+                "location {location} expects {count} components, but the buffer provides {:?}",
+                attr.format
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The number of components in a vertex format, for the formats we use.
+#[cfg(all(debug_assertions, feature = "validate_shaders"))]
+fn format_components(format: wgpu::VertexFormat) -> Option<u32> {
+    use wgpu::VertexFormat as F;
+    match format {
+        F::Float32 | F::Uint32 | F::Sint32 => Some(1),
+        F::Float32x2 | F::Uint32x2 | F::Sint32x2 => Some(2),
+        F::Float32x3 | F::Uint32x3 | F::Sint32x3 => Some(3),
+        F::Float32x4 | F::Uint32x4 | F::Sint32x4 => Some(4),
+        _ => None,
+    }
+}
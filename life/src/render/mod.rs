@@ -3,15 +3,44 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
 use wgpu::util::DeviceExt;
 use winit::window::Window;
 
 use crate::game::GameState;
 
+/// How much each new frame counts towards the average frame rate. Smaller
+/// values give a steadier number that's slower to follow changes.
+const FRAME_RATE_SMOOTHING: f64 = 0.05;
+
 /// The color of living cells when using solid coloring instead of a texture
 pub const CELL_COLOR: [f32; 4] = [0.17, 0.65, 0.22, 1.0]; // #2CA738
 
+/// How long smooth panning takes to get most of the way to where the view is
+/// going, in seconds. The view covers about 63% of the remaining distance in
+/// this time, however often frames are drawn.
+const PAN_SMOOTHING_TIME: f32 = 0.05;
+
+/// The color behind the background texture, which shows through wherever
+/// it's transparent, and is all there is when it's turned off
+const BACKGROUND_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod capture;
+mod dots;
+mod grid;
+mod locations;
 mod texture;
+pub mod theme;
 
 /// A cell that will be rendered to the screen.
 ///
@@ -23,9 +52,84 @@ pub struct Cell {
     /// is the top-left and formatted as x, y. This is the position of the
     /// top-left corner of it's bounding box.
     pub location: [f32; 2],
+    /// The grid coordinates of the cell
+    pub coords: [i32; 2],
+    pub highlight: Highlight,
+    /// A value from 0 to 1 that some highlights shade the cell by: how often
+    /// it has been alive for `Highlight::Heat`, how far it has decayed for
+    /// `Highlight::Decaying`, and how far it has faded for
+    /// `Highlight::Fading`
+    pub level: f32,
+}
+
+/// A tint showing what happened to a cell in the last generation
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Highlight {
+    #[default]
+    None,
+    /// The cell was just born
+    Born,
+    /// The cell just died, and is only drawn for this generation
+    Died,
+    /// The cell isn't alive, but is part of a pattern about to be stamped
+    Ghost,
+    /// The cell is colored by how often it has been alive instead of whether
+    /// it is now
+    Heat,
+    /// The cell is decaying under a Generations rule, so it's no longer alive
+    /// but can't be born yet
+    Decaying,
+    /// The cell is a wall, which never changes
+    Wall,
+    /// The cell just died and is fading out, `level` of the way
+    Fading,
+}
+
+/// Cells that were born and died since the renderer was last updated, so
+/// that only they have to be sent to the GPU
+#[derive(Debug, Default)]
+pub struct CellDelta {
+    pub added: Vec<Cell>,
+    /// The coordinates of the cells that died
+    pub removed: Vec<[i32; 2]>,
+}
+
+impl CellDelta {
+    /// Add the changes in `later` to these ones, so that applying the result
+    /// is the same as applying both in order.
+    pub fn merge(&mut self, later: CellDelta) {
+        for coords in later.removed {
+            if let Some(i) = self.added.iter().position(|c| c.coords == coords) {
+                self.added.swap_remove(i);
+            } else {
+                self.removed.push(coords);
+            }
+        }
+        for cell in later.added {
+            if let Some(i) = self.removed.iter().position(|c| *c == cell.coords) {
+                // It was there all along
+                self.removed.swap_remove(i);
+            } else {
+                self.added.push(cell);
+            }
+        }
+    }
 }
 
 impl Cell {
+    /// The cell at the given grid coordinates.
+    fn at(coords: [i32; 2], grid_size: f32) -> Self {
+        Self {
+            location: [
+                coords[0] as f32 * grid_size + grid_size / 2.0,
+                coords[1] as f32 * grid_size + grid_size / 2.0,
+            ],
+            coords,
+            highlight: Highlight::None,
+            level: 0.0,
+        }
+    }
+
     fn as_instance(&self, _radius: f32) -> Instance {
         let normalized_location = [
             self.location[0] * 2.0 - 1.0,
@@ -35,10 +139,14 @@ impl Cell {
         Instance {
             offset: normalized_location,
             center,
+            highlight: self.highlight as u32,
+            level: self.level,
         }
     }
 }
 
+/// The corners of the square a cell is drawn in, centered on the origin.
+/// `radius` is half its width, as a fraction of the view's height.
 fn cell_vertices(radius: f32) -> [Vertex; 6] {
     [
         Vertex {
@@ -73,6 +181,8 @@ fn cell_vertices(radius: f32) -> [Vertex; 6] {
 struct Instance {
     offset: [f32; 2],
     center: [f32; 2],
+    highlight: u32,
+    level: f32,
 }
 
 impl Instance {
@@ -85,15 +195,27 @@ impl Instance {
                 // The offset
                 wgpu::VertexAttribute {
                     offset: 0,
-                    shader_location: 1,
+                    shader_location: locations::INSTANCE_OFFSET,
                     format: wgpu::VertexFormat::Float32x2,
                 },
                 // The center
                 wgpu::VertexAttribute {
                     offset: mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
-                    shader_location: 2,
+                    shader_location: locations::INSTANCE_CENTER,
                     format: wgpu::VertexFormat::Float32x2,
                 },
+                // The highlight
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: locations::INSTANCE_HIGHLIGHT,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                // The level
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: locations::INSTANCE_LEVEL,
+                    format: wgpu::VertexFormat::Float32,
+                },
             ],
         }
     }
@@ -116,12 +238,12 @@ impl Vertex {
             attributes: &[
                 wgpu::VertexAttribute {
                     offset: 0,
-                    shader_location: 0,
+                    shader_location: locations::POSITION,
                     format: wgpu::VertexFormat::Float32x3,
                 },
                 wgpu::VertexAttribute {
                     offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 3,
+                    shader_location: locations::TEX_COORDS,
                     format: wgpu::VertexFormat::Float32x2,
                 },
             ],
@@ -164,6 +286,99 @@ struct RenderCore<'a> {
     device: Arc<wgpu::Device>,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
+    /// The alpha modes supported by the surface, in order of preference
+    alpha_modes: Vec<wgpu::CompositeAlphaMode>,
+    /// The present modes supported by the surface
+    present_modes: Vec<wgpu::PresentMode>,
+    /// The numbers of samples per pixel that the surface's format can be
+    /// drawn with, from fewest to most
+    sample_counts: Vec<u32>,
+}
+
+/// One-off things the GUI asks the renderer to do
+pub enum RenderRequest {
+    /// Save what's in the window to an image, scaled up by some factor
+    #[cfg(not(target_arch = "wasm32"))]
+    ExportView { scale: u32 },
+    /// Step the game and save each generation to one sprite sheet
+    #[cfg(not(target_arch = "wasm32"))]
+    ExportFlipbook { frames: u32, cols: u32, cell_px: u32 },
+    /// Draw living cells with the image in a file instead of `live.png`
+    #[cfg(not(target_arch = "wasm32"))]
+    LoadCellTexture(std::path::PathBuf),
+}
+
+/// Settings for how things are drawn, which the GUI can change. The renderer
+/// checks them every frame and applies any that changed.
+#[derive(Clone, PartialEq)]
+pub struct RenderSettings {
+    /// A solid color to draw behind the cells instead of the textured
+    /// background
+    pub clear_color: Option<wgpu::Color>,
+    /// A solid color to draw the cells in instead of their texture
+    pub cell_color: Option<[f32; 4]>,
+    /// How to draw dots in dead cells
+    pub dots: dots::DotSettings,
+    /// How to draw lines between the cells
+    pub grid: grid::GridSettings,
+    /// Whether frames are shown as soon as they're drawn instead of waiting
+    /// for the display to refresh, if the platform allows it
+    pub uncap_frame_rate: bool,
+    /// How much the edges of the cells are smoothed
+    pub msaa: Msaa,
+    /// How much of its square a cell fills, from 0 to 1. Below 1 there are
+    /// gaps between neighboring cells.
+    pub cell_fill: f32,
+    /// Whether the view glides to where it's panned to instead of following
+    /// the mouse exactly
+    pub smooth_panning: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            clear_color: None,
+            cell_color: None,
+            dots: Default::default(),
+            grid: Default::default(),
+            uncap_frame_rate: false,
+            msaa: Msaa::Off,
+            cell_fill: 1.0,
+            smooth_panning: true,
+        }
+    }
+}
+
+/// Multisample anti-aliasing, which smooths edges by drawing several samples
+/// per pixel
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Msaa {
+    #[default]
+    Off,
+    X2,
+    X4,
+}
+
+impl Msaa {
+    pub const ALL: [Self; 3] = [Self::Off, Self::X2, Self::X4];
+
+    /// The number of samples per pixel
+    pub fn sample_count(self) -> u32 {
+        match self {
+            Self::Off => 1,
+            Self::X2 => 2,
+            Self::X4 => 4,
+        }
+    }
+}
+
+impl std::fmt::Display for Msaa {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Off => f.write_str("Off"),
+            _ => write!(f, "{}x", self.sample_count()),
+        }
+    }
 }
 
 /// The buffers, bind groups, and textures that the renderer requires
@@ -171,12 +386,17 @@ struct BuffersAndGroups {
     vertex_buffer: wgpu::Buffer,
     instance_buffer: wgpu::Buffer,
     instance_buffer_capacity: u64,
+    /// The capacity the instance buffer started with, which it never shrinks
+    /// below
+    instance_buffer_min_capacity: u64,
+    /// How many updates in a row have used less than a quarter of the
+    /// instance buffer
+    instance_buffer_underused: u32,
 
     #[allow(dead_code)]
     radius_buffer: wgpu::Buffer,
     radius_bind_group: wgpu::BindGroup,
 
-    #[allow(dead_code)]
     color_buffer: wgpu::Buffer,
     color_bind_group: wgpu::BindGroup,
 
@@ -190,6 +410,8 @@ struct BuffersAndGroups {
     #[allow(dead_code)]
     bg_texture: texture::Texture,
     bg_texture_bind_group: wgpu::BindGroup,
+    /// The layout of the texture bind groups, for when a texture is replaced
+    texture_bind_group_layout: wgpu::BindGroupLayout,
 
     #[allow(dead_code)]
     offset_buffer: wgpu::Buffer,
@@ -197,6 +419,234 @@ struct BuffersAndGroups {
     bg_vertex_buffer: wgpu::Buffer,
 }
 
+/// What the pipelines that draw the background and the cells are made from,
+/// so that they can be made again for a different number of samples per
+/// pixel
+struct ScenePipelines {
+    cells_layout: wgpu::PipelineLayout,
+    cells_shader: wgpu::ShaderModule,
+    bg_layout: wgpu::PipelineLayout,
+    bg_shader: wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+}
+
+impl ScenePipelines {
+    fn cells(&self, device: &wgpu::Device, sample_count: u32) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&self.cells_layout),
+            vertex: wgpu::VertexState {
+                module: &self.cells_shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc(), Instance::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.cells_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                // Setting this to anything other than Fill requires Features::POLYGON_MODE_LINE
+                // or Features::POLYGON_MODE_POINT
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            // If the pipeline will be used with a multiview render pass, this
+            // indicates how many array layers the attachments will have.
+            multiview: None,
+        })
+    }
+
+    fn bg(&self, device: &wgpu::Device, sample_count: u32) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("BG Render Pipeline"),
+            layout: Some(&self.bg_layout),
+            vertex: wgpu::VertexState {
+                module: &self.bg_shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.bg_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+}
+
+/// Make a multisampled texture to draw the scene to before it's resolved to a
+/// view of the given size, or `None` if there's only one sample per pixel and
+/// the scene is drawn straight to the view.
+fn create_msaa_view(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> Option<wgpu::TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Multisampled Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+/// Make a bind group for drawing with a texture, laid out the way the cell and
+/// background shaders expect
+fn texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture: &texture::Texture,
+    label: &str,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            },
+        ],
+        label: Some(label),
+    })
+}
+
+/// How many updates in a row the instance buffer has to be less than a
+/// quarter full before it's replaced with a smaller one. Waiting stops it from
+/// being reallocated over and over when the population swings back and forth.
+const SHRINK_AFTER_UPDATES: u32 = 60;
+
+/// The capacity an instance buffer holding `capacity` instances should be
+/// replaced with when `needed` of them are used, or `None` if it's fine as it
+/// is. Buffers that are too small grow exponentially to get amortized O(1)
+/// insertions, and ones that are less than a quarter full shrink to leave the
+/// same room to grow, but never below `min_capacity`.
+fn resized_capacity(capacity: u64, needed: u64, min_capacity: u64) -> Option<u64> {
+    let roomy = (needed as f32 * 1.5) as u64;
+    if needed > capacity {
+        Some(roomy)
+    } else if needed * 4 < capacity && capacity > min_capacity {
+        Some(roomy.max(min_capacity))
+    } else {
+        None
+    }
+}
+
+/// The capacity to shrink an instance buffer to after an update that used
+/// `used` instances of it, or `None` if it should stay as it is for now.
+/// `underused` counts the updates in a row that would have shrunk it, and is
+/// reset whenever the population no longer fits in a smaller buffer and after
+/// the buffer shrinks.
+fn delayed_shrink(
+    capacity: u64,
+    used: u64,
+    min_capacity: u64,
+    underused: &mut u32,
+) -> Option<u64> {
+    let Some(new_size) =
+        resized_capacity(capacity, used, min_capacity).filter(|new| *new < capacity)
+    else {
+        *underused = 0;
+        return None;
+    };
+    *underused += 1;
+    if *underused < SHRINK_AFTER_UPDATES {
+        return None;
+    }
+    *underused = 0;
+    Some(new_size)
+}
+
+/// Write instances to an instance buffer, replacing it with a bigger one if
+/// its capacity is insufficient.
+fn write_instances(
+    core: &RenderCore,
+    buffer: &mut wgpu::Buffer,
+    capacity: &mut u64,
+    instances: &[Instance],
+) {
+    let instance_count = instances.len() as u64;
+
+    // Create a new buffer and replace the old one if needed. Shrinking is
+    // left to the callers that want it, since it should wait to be sure the
+    // buffer stays mostly empty.
+    if let Some(new_size) =
+        resized_capacity(*capacity, instance_count, 0).filter(|new| *new > *capacity)
+    {
+        *buffer = core.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: std::mem::size_of::<Instance>() as u64 * new_size,
+            usage: wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        *capacity = new_size;
+    }
+    // Write the data
+    core.queue
+        .write_buffer(buffer, 0, bytemuck::cast_slice(instances));
+}
+
 mod gui;
 
 /// The state of the renderer. It contains the graphical user interface as well
@@ -205,13 +655,102 @@ pub struct RenderState<'a> {
     core: RenderCore<'a>,
     size: winit::dpi::PhysicalSize<u32>,
     render_pipeline: wgpu::RenderPipeline,
+    /// What the cell and background pipelines are made from
+    pipelines: ScenePipelines,
+    /// The number of samples per pixel the scene is drawn with
+    sample_count: u32,
+    /// The multisampled texture the scene is drawn to before it's resolved to
+    /// the window, if there's more than one sample per pixel
+    msaa_view: Option<wgpu::TextureView>,
     window: Arc<Window>,
     num_vertices: u32,
     cells: Vec<Cell>,
+    /// Where each cell is in `cells` and the instance buffer, by coordinates
+    cell_index: rustc_hash::FxHashMap<[i32; 2], usize>,
     grid_size: f32,
+    /// How much of its square each cell is drawn over. Only the drawing
+    /// changes, so the grid size is still what cells are picked by.
+    cell_fill: f32,
     rsc: BuffersAndGroups,
     bg_render_pipeline: wgpu::RenderPipeline,
     egui: gui::GuiState,
+    /// The color to clear the frame to instead of drawing the background
+    clear_color: Option<wgpu::Color>,
+    /// Whether the background texture is drawn. Without it, the frame is
+    /// cleared to `BACKGROUND_COLOR`.
+    draw_background: bool,
+    /// Whether the cells are drawn in a solid color instead of their texture
+    solid_cells: bool,
+    /// The settings from the GUI as of when they were last applied
+    settings: RenderSettings,
+    /// The current pan position
+    offset: vec2::Vector2<f32>,
+    /// The pan position the view is gliding to, when panning is smoothed
+    target_offset: vec2::Vector2<f32>,
+    /// Whether the view glides to `target_offset` instead of going straight
+    /// there
+    smooth_panning: bool,
+    /// The dots drawn in dead cells
+    dots: dots::DotLayer,
+    /// The lines drawn between cells
+    grid: grid::GridLayer,
+    /// The game, for exports that need to step it
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    game_state: Arc<Mutex<GameState>>,
+    /// The frames of the GIF being recorded, if there is one
+    #[cfg(not(target_arch = "wasm32"))]
+    recording: Option<capture::Recording>,
+    /// When the last frame was rendered, for measuring the frame rate
+    last_frame: Option<Instant>,
+    /// The moving average of the frame rate, in frames per second
+    frame_rate: f64,
+}
+
+/// Find a GPU that can draw to the window, trying the backends that work best
+/// first. On the desktop, GL is only used if Vulkan, Metal and DirectX 12 all
+/// can't be, since it's slower but works on a lot of older machines.
+async fn find_adapter(
+    window: &Arc<Window>,
+) -> anyhow::Result<(wgpu::Surface<'static>, wgpu::Adapter)> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let backend_sets = [wgpu::Backends::PRIMARY, wgpu::Backends::all()];
+    #[cfg(target_arch = "wasm32")]
+    let backend_sets = [wgpu::Backends::GL];
+
+    let mut surface_error = None;
+    for backends in backend_sets {
+        // The instance is a handle to our GPU
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        let surface = match instance.create_surface(window.clone()) {
+            Ok(surface) => surface,
+            Err(e) => {
+                log::warn!("Couldn't create a surface with {backends:?}: {e}");
+                surface_error = Some(e);
+                continue;
+            }
+        };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            })
+            .await;
+        match adapter {
+            Some(adapter) => return Ok((surface, adapter)),
+            None => log::warn!("No GPU adapter found with {backends:?}"),
+        }
+    }
+    Err(match surface_error {
+        Some(e) => anyhow::anyhow!("Couldn't create a surface to draw on: {e}"),
+        None => anyhow::anyhow!(
+            "No compatible GPU adapter found. A GPU with Vulkan, Metal, DirectX 12 or OpenGL \
+             support is needed."
+        ),
+    })
 }
 
 impl<'a> RenderState<'a> {
@@ -223,35 +762,22 @@ impl<'a> RenderState<'a> {
     ///
     /// grid_size:
     /// The size of each grid cell as a fraction of the viewport's height.
+    ///
+    /// Returns an error saying what went wrong if there's no GPU that can be
+    /// used to draw to the window.
     pub async fn new(
         window: Arc<Window>,
         grid_size: f32,
         start_capacity: u64,
         game_state: Arc<Mutex<GameState>>,
-    ) -> RenderState<'a> {
+    ) -> anyhow::Result<RenderState<'a>> {
         let size = window.inner_size();
 
-        // The instance is a handle to our GPU
-        // BackendBit::PRIMARY => Vulkan + Metal + DX12 + Browser WebGPU
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            #[cfg(not(target_arch = "wasm32"))]
-            backends: wgpu::Backends::PRIMARY,
-            #[cfg(target_arch = "wasm32")]
-            backends: wgpu::Backends::GL,
-            ..Default::default()
-        });
-
-        let surface = instance.create_surface(window.clone()).unwrap();
-
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
-        let limits = if cfg!(target_arch = "wasm32") {
+        let (surface, adapter) = find_adapter(&window).await?;
+        let info = adapter.get_info();
+        log::info!("Drawing with {} on {:?}", info.name, info.backend);
+        // GL can't do everything the other backends can, wherever it's used
+        let limits = if cfg!(target_arch = "wasm32") || info.backend == wgpu::Backend::Gl {
             wgpu::Limits {
                 max_bind_groups: 5,
                 max_storage_textures_per_shader_stage: 0,
@@ -282,7 +808,7 @@ impl<'a> RenderState<'a> {
                 None, // Trace path
             )
             .await
-            .unwrap();
+            .map_err(|e| anyhow::anyhow!("Couldn't open the GPU {}: {e}", info.name))?;
 
         let surface_caps = surface.get_capabilities(&adapter);
         // Shader code in this assumes an Srgb surface texture. Using a different
@@ -294,6 +820,12 @@ impl<'a> RenderState<'a> {
             .copied()
             .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
+        let sample_flags = adapter.get_texture_format_features(surface_format).flags;
+        let sample_counts = Msaa::ALL
+            .map(Msaa::sample_count)
+            .into_iter()
+            .filter(|c| *c == 1 || sample_flags.sample_count_supported(*c))
+            .collect();
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
@@ -369,7 +901,7 @@ impl<'a> RenderState<'a> {
         let color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Color Buffer"),
             contents: bytemuck::cast_slice(&CELL_COLOR),
-            usage: wgpu::BufferUsages::UNIFORM,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
         let color_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -434,38 +966,22 @@ impl<'a> RenderState<'a> {
                 label: Some("texture_bind_group_layout"),
             });
 
-        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-            label: Some("diffuse_bind_group"),
-        });
+        let diffuse_bind_group = texture_bind_group(
+            &device,
+            &texture_bind_group_layout,
+            &diffuse_texture,
+            "diffuse_bind_group",
+        );
 
         let bg_texture_bytes = include_bytes!("../../rsc/dead.png");
         let bg_texture =
             texture::Texture::from_bytes(&device, &queue, bg_texture_bytes, "dead.png").unwrap();
-        let bg_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&bg_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&bg_texture.sampler),
-                },
-            ],
-            label: Some("bg_texture_bind_group"),
-        });
+        let bg_texture_bind_group = texture_bind_group(
+            &device,
+            &texture_bind_group_layout,
+            &bg_texture,
+            "bg_texture_bind_group",
+        );
 
         let vertices = cell_vertices(grid_size);
 
@@ -520,6 +1036,12 @@ impl<'a> RenderState<'a> {
             label: Some("Shader"),
             source: wgpu::ShaderSource::Wgsl(shader_string.into()),
         });
+        #[cfg(all(debug_assertions, feature = "validate_shaders"))]
+        if let Err(e) =
+            locations::validate(shader_string, "vs_main", &[Vertex::desc(), Instance::desc()])
+        {
+            panic!("Cell vertex layout doesn't match shader.wgsl: {e}");
+        }
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -534,54 +1056,15 @@ impl<'a> RenderState<'a> {
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc(), Instance::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                // Setting this to anything other than Fill requires Features::POLYGON_MODE_LINE
-                // or Features::POLYGON_MODE_POINT
-                polygon_mode: wgpu::PolygonMode::Fill,
-                // Requires Features::DEPTH_CLIP_CONTROL
-                unclipped_depth: false,
-                // Requires Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            // If the pipeline will be used with a multiview render pass, this
-            // indicates how many array layers the attachments will have.
-            multiview: None,
-        });
-
         let bg_shader_string = include_str!("./bg.wgsl");
         let bg_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("BG Shader"),
             source: wgpu::ShaderSource::Wgsl(bg_shader_string.into()),
         });
+        #[cfg(all(debug_assertions, feature = "validate_shaders"))]
+        if let Err(e) = locations::validate(bg_shader_string, "vs_main", &[Vertex::desc()]) {
+            panic!("Background vertex layout doesn't match bg.wgsl: {e}");
+        }
         let bg_render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("BG Render Pipeline Layout"),
@@ -593,42 +1076,29 @@ impl<'a> RenderState<'a> {
                 ],
                 push_constant_ranges: &[],
             });
-        let bg_render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("BG Render Pipeline"),
-            layout: Some(&bg_render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &bg_shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &bg_shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
+        let pipelines = ScenePipelines {
+            cells_layout: render_pipeline_layout,
+            cells_shader: shader,
+            bg_layout: bg_render_pipeline_layout,
+            bg_shader,
+            format: config.format,
+        };
+        let render_pipeline = pipelines.cells(&device, 1);
+        let bg_render_pipeline = pipelines.bg(&device, 1);
+
+        let dots = dots::DotLayer::new(
+            &device,
+            &res_bind_group_layout,
+            &offset_bind_group_layout,
+            config.format,
+        );
+        let grid = grid::GridLayer::new(
+            &device,
+            &res_bind_group_layout,
+            &grid_size_bind_group_layout,
+            &offset_bind_group_layout,
+            config.format,
+        );
 
         let surface = Arc::new(surface);
         let device = Arc::new(device);
@@ -638,12 +1108,17 @@ impl<'a> RenderState<'a> {
             device,
             queue,
             config,
+            alpha_modes: surface_caps.alpha_modes,
+            present_modes: surface_caps.present_modes,
+            sample_counts,
         };
 
         let bag = BuffersAndGroups {
             vertex_buffer,
             instance_buffer,
             instance_buffer_capacity: start_capacity,
+            instance_buffer_min_capacity: start_capacity,
+            instance_buffer_underused: 0,
 
             radius_buffer: grid_size_buffer,
             radius_bind_group: grid_size_bind_group,
@@ -664,6 +1139,7 @@ impl<'a> RenderState<'a> {
 
             bg_texture,
             bg_texture_bind_group,
+            texture_bind_group_layout,
         };
 
         let egui = gui::GuiState::new(
@@ -671,21 +1147,40 @@ impl<'a> RenderState<'a> {
             Arc::clone(&window),
             core.device.clone(),
             surface_format,
-            game_state,
+            Arc::clone(&game_state),
         );
 
-        Self {
+        Ok(Self {
             core,
             size,
             render_pipeline,
+            pipelines,
+            sample_count: 1,
+            msaa_view: None,
             window,
             num_vertices: vertices.len() as u32,
             cells: Vec::new(),
+            cell_index: Default::default(),
             grid_size,
+            cell_fill: 1.0,
             rsc: bag,
             bg_render_pipeline,
             egui,
-        }
+            clear_color: None,
+            draw_background: true,
+            solid_cells: false,
+            settings: RenderSettings::default(),
+            offset: vec2::Vector2::new(0.0, 0.0),
+            target_offset: vec2::Vector2::new(0.0, 0.0),
+            smooth_panning: true,
+            dots,
+            grid,
+            game_state,
+            #[cfg(not(target_arch = "wasm32"))]
+            recording: None,
+            last_frame: None,
+            frame_rate: 0.0,
+        })
     }
 
     /// Update the cells to be rendered.
@@ -694,7 +1189,45 @@ impl<'a> RenderState<'a> {
     pub fn update_cells(&mut self, cells: Vec<Cell>) {
         // Update internal record of the cells
         self.cells = cells;
+        self.cell_index = self
+            .cells
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.coords, i))
+            .collect();
+        self.write_all_instances();
+        self.shrink_instance_buffer();
+        self.dots.mark_dirty();
+    }
+
+    /// Replace the instance buffer with a smaller one if it has been mostly
+    /// empty for a while, so that a population that once was huge doesn't
+    /// hold on to its memory forever.
+    fn shrink_instance_buffer(&mut self) {
+        let rsc = &mut self.rsc;
+        let Some(new_size) = delayed_shrink(
+            rsc.instance_buffer_capacity,
+            self.cells.len() as u64,
+            rsc.instance_buffer_min_capacity,
+            &mut rsc.instance_buffer_underused,
+        ) else {
+            return;
+        };
 
+        rsc.instance_buffer = self.core.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: std::mem::size_of::<Instance>() as u64 * new_size,
+            usage: wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        rsc.instance_buffer_capacity = new_size;
+        self.write_all_instances();
+    }
+
+    /// Write every cell to the instance buffer, growing it if needed
+    fn write_all_instances(&mut self) {
         // Convert the cells to instances for the shader
         let new_instances = self
             .cells
@@ -702,34 +1235,70 @@ impl<'a> RenderState<'a> {
             .map(|c| c.as_instance(self.grid_size))
             .collect::<Vec<_>>();
 
-        // Determine the required size of the buffer to hold all the cells
-        let instance_count = new_instances.len();
-        let new_size = (instance_count as f32 * 1.5) as u64;
-
-        // Create a new buffer and replace the old one if needed. The new buffer
-        // grows exponentially to get amortized O(1) insertions.
-        if instance_count as u64 > self.rsc.instance_buffer_capacity {
-            let instance_buffer = self.core.device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some("Instance Buffer"),
-                // size: std::mem::size_of::<Instance>() as u64 * 80u64,
-                size: std::mem::size_of::<Instance>() as u64 * new_size,
-                usage: wgpu::BufferUsages::VERTEX
-                    | wgpu::BufferUsages::COPY_DST
-                    | wgpu::BufferUsages::COPY_SRC,
-                mapped_at_creation: false,
-            });
-            // Write the data
-            self.core
-                .queue
-                .write_buffer(&instance_buffer, 0, bytemuck::cast_slice(&new_instances));
-            self.rsc.instance_buffer_capacity = new_size;
-            self.rsc.instance_buffer = instance_buffer;
-        } else {
-            // Write the data
+        write_instances(
+            &self.core,
+            &mut self.rsc.instance_buffer,
+            &mut self.rsc.instance_buffer_capacity,
+            &new_instances,
+        );
+    }
+
+    /// Add and remove cells without sending all of them to the GPU again.
+    ///
+    /// Removed cells are replaced by the last one in the buffer, and added
+    /// ones go on the end. If the buffer is too small for them, or the delta
+    /// is bigger than the buffer, everything is written again instead.
+    pub fn apply_cell_delta(&mut self, delta: CellDelta) {
+        let full_rebuild = (delta.added.len() + delta.removed.len()) as u64
+            > self.rsc.instance_buffer_capacity;
+        let mut moved = Vec::new();
+        for coords in delta.removed {
+            let Some(i) = self.cell_index.remove(&coords) else {
+                continue;
+            };
+            self.cells.swap_remove(i);
+            if let Some(cell) = self.cells.get(i) {
+                self.cell_index.insert(cell.coords, i);
+                moved.push(i);
+            }
+        }
+        let first_added = self.cells.len();
+        for cell in delta.added {
+            self.cell_index.insert(cell.coords, self.cells.len());
+            self.cells.push(cell);
+        }
+        self.dots.mark_dirty();
+
+        if full_rebuild || self.cells.len() as u64 > self.rsc.instance_buffer_capacity {
+            self.write_all_instances();
+            self.shrink_instance_buffer();
+            return;
+        }
+        self.write_changed_instances(moved, first_added);
+        self.shrink_instance_buffer();
+    }
+
+    /// Write the instances of the cells that were moved to fill gaps, and of
+    /// the ones from `first_added` on
+    fn write_changed_instances(&mut self, moved: Vec<usize>, first_added: usize) {
+        let instance_size = std::mem::size_of::<Instance>() as u64;
+        for i in moved.into_iter().filter(|i| *i < first_added) {
+            let instance = self.cells[i].as_instance(self.grid_size);
+            self.core.queue.write_buffer(
+                &self.rsc.instance_buffer,
+                i as u64 * instance_size,
+                bytemuck::cast_slice(&[instance]),
+            );
+        }
+        if first_added < self.cells.len() {
+            let instances = self.cells[first_added..]
+                .iter()
+                .map(|c| c.as_instance(self.grid_size))
+                .collect::<Vec<_>>();
             self.core.queue.write_buffer(
                 &self.rsc.instance_buffer,
-                0,
-                bytemuck::cast_slice(&new_instances),
+                first_added as u64 * instance_size,
+                bytemuck::cast_slice(&instances),
             );
         }
     }
@@ -739,8 +1308,26 @@ impl<'a> RenderState<'a> {
         self.window.clone()
     }
 
-    /// Update the panning value used in the shader.
+    /// Pan the view to `new_offset`, gliding there over the next few frames
+    /// if panning is smoothed
     pub fn update_offset(&mut self, new_offset: vec2::Vector2<f32>) {
+        self.target_offset = new_offset;
+        if !self.smooth_panning {
+            self.write_offset(new_offset);
+        }
+    }
+
+    /// Pan the view straight to `new_offset`, even if panning is smoothed
+    pub fn jump_offset(&mut self, new_offset: vec2::Vector2<f32>) {
+        self.target_offset = new_offset;
+        self.write_offset(new_offset);
+    }
+
+    /// Draw the view at `new_offset` from now on, without changing where it's
+    /// gliding to
+    fn write_offset(&mut self, new_offset: vec2::Vector2<f32>) {
+        self.offset = new_offset;
+        self.dots.mark_dirty();
         let offset: [f32; 2] = new_offset.into();
         let mut data = Vec::with_capacity(4);
         data.extend(offset);
@@ -750,19 +1337,80 @@ impl<'a> RenderState<'a> {
             .write_buffer(&self.rsc.offset_buffer, 0, bytemuck::cast_slice(&data[..]));
     }
 
+    /// Move the view part of the way to where it's being panned to, by however
+    /// much time has passed since the last frame
+    fn glide_offset(&mut self) {
+        if self.offset == self.target_offset {
+            return;
+        }
+        let seconds = self.last_frame.map_or(0.0, |t| t.elapsed().as_secs_f32());
+        let t = 1.0 - (-seconds / PAN_SMOOTHING_TIME).exp();
+        let remaining = self.target_offset - self.offset;
+        // Stop once the rest of the way is too small to see
+        let offset = if remaining.x.abs().max(remaining.y.abs()) < 1e-5 {
+            self.target_offset
+        } else {
+            self.offset + remaining * t
+        };
+        self.write_offset(offset);
+    }
+
+    /// Turn smooth panning on or off. Turning it off puts the view straight
+    /// where it's going.
+    pub fn set_smooth_panning(&mut self, smooth: bool) {
+        self.smooth_panning = smooth;
+        if !smooth {
+            self.write_offset(self.target_offset);
+        }
+    }
+
     /// Change the grid size used for rendering.
-    pub fn change_grid_size(&self, new: f32) {
+    pub fn change_grid_size(&mut self, new: f32) {
         if new <= 0.0 {
             return;
         }
-        let vertices = cell_vertices(new);
+        self.grid_size = new;
+        self.dots.mark_dirty();
+        self.write_cell_size();
+    }
+
+    /// Draw the cells over `fill` of their squares, from 0 to 1, leaving gaps
+    /// between them when it's less than 1
+    pub fn set_cell_fill(&mut self, fill: f32) {
+        self.cell_fill = fill.clamp(0.01, 1.0);
+        self.write_cell_size();
+    }
+
+    /// Resize the square the cells are drawn in to the grid size and fill
+    fn write_cell_size(&self) {
+        let vertices = cell_vertices(self.grid_size * self.cell_fill);
         self.core
             .queue
             .write_buffer(&self.rsc.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.write_radius();
+    }
 
-        self.core
-            .queue
-            .write_buffer(&self.rsc.radius_buffer, 0, bytemuck::cast_slice(&[new, 0.0, 0.0, 0.0]));
+    /// Draw the cells in a solid color instead of their texture, or go back
+    /// to the texture with `None`. The texture still fades to `CELL_COLOR`
+    /// when zoomed far out.
+    pub fn set_cell_color(&mut self, color: Option<[f32; 4]>) {
+        self.solid_cells = color.is_some();
+        self.core.queue.write_buffer(
+            &self.rsc.color_buffer,
+            0,
+            bytemuck::cast_slice(&color.unwrap_or(CELL_COLOR)),
+        );
+        self.write_radius();
+    }
+
+    /// Update the grid size and whether the cells are solid in the shaders.
+    fn write_radius(&self) {
+        let solid = if self.solid_cells { 1.0 } else { 0.0 };
+        self.core.queue.write_buffer(
+            &self.rsc.radius_buffer,
+            0,
+            bytemuck::cast_slice(&[self.grid_size, solid, self.cell_fill, 0.0]),
+        );
     }
 
     /// Reconfigure and update the renderer for a new resolution
@@ -776,11 +1424,24 @@ impl<'a> RenderState<'a> {
         self.core
             .surface
             .configure(&self.core.device, &self.core.config);
+        self.msaa_view = create_msaa_view(
+            &self.core.device,
+            self.core.config.format,
+            new_size.width,
+            new_size.height,
+            self.sample_count,
+        );
+
+        self.write_resolution(new_size.width, new_size.height);
+        self.dots.mark_dirty();
+    }
 
+    /// Update the resolution used in the shaders.
+    fn write_resolution(&self, width: u32, height: u32) {
         self.core.queue.write_buffer(
             &self.rsc.res_buffer,
             0 as wgpu::BufferAddress,
-            bytemuck::cast_slice(&[new_size.width as f32, new_size.height as f32, 0.0, 0.0]),
+            bytemuck::cast_slice(&[width as f32, height as f32, 0.0, 0.0]),
         );
     }
 
@@ -794,10 +1455,243 @@ impl<'a> RenderState<'a> {
         self.egui.handle_event(event)
     }
 
-    pub fn update(&mut self) {}
+    /// Carry out anything the GUI asked for and apply any settings that were
+    /// changed through it.
+    pub fn update(&mut self) {
+        for request in self.egui.take_requests() {
+            self.handle_request(request);
+        }
+        self.glide_offset();
+        self.dots.update(
+            &self.core,
+            &self.cell_index,
+            self.offset,
+            self.grid_size,
+            self.size,
+        );
+
+        let settings = self.egui.render_settings();
+        if *settings == self.settings {
+            return;
+        }
+        let settings = settings.clone();
+        if settings.clear_color != self.settings.clear_color {
+            self.set_clear(settings.clear_color);
+        }
+        if settings.cell_color != self.settings.cell_color {
+            self.set_cell_color(settings.cell_color);
+        }
+        if settings.dots != self.settings.dots {
+            self.dots.set_settings(&self.core, settings.dots);
+        }
+        if settings.grid != self.settings.grid {
+            self.grid.set_settings(&self.core, settings.grid);
+        }
+        if settings.cell_fill != self.settings.cell_fill {
+            self.set_cell_fill(settings.cell_fill);
+        }
+        if settings.smooth_panning != self.settings.smooth_panning {
+            self.set_smooth_panning(settings.smooth_panning);
+        }
+        if settings.msaa != self.settings.msaa {
+            self.set_sample_count(settings.msaa.sample_count());
+        }
+        if settings.uncap_frame_rate != self.settings.uncap_frame_rate {
+            // Mailbox doesn't tear, so it's only passed over if it isn't
+            // supported
+            let mode = [wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate]
+                .into_iter()
+                .find(|m| settings.uncap_frame_rate && self.core.present_modes.contains(m))
+                .unwrap_or(wgpu::PresentMode::Fifo);
+            self.set_present_mode(mode);
+        }
+        self.settings = settings;
+    }
+
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    fn handle_request(&mut self, request: RenderRequest) {
+        match request {
+            #[cfg(not(target_arch = "wasm32"))]
+            RenderRequest::ExportView { scale } => {
+                let name = chrono::Local::now()
+                    .format("view-%Y%m%d-%H%M%S.png")
+                    .to_string();
+                match self.export_current_view(name.as_ref(), scale) {
+                    Ok(()) => log::info!("Saved the view to {name}"),
+                    Err(e) => log::error!("Failed to export the view: {e}"),
+                }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            RenderRequest::ExportFlipbook {
+                frames,
+                cols,
+                cell_px,
+            } => {
+                let name = chrono::Local::now()
+                    .format("flipbook-%Y%m%d-%H%M%S.png")
+                    .to_string();
+                match self.export_flipbook(frames, cols, cell_px, name.as_ref()) {
+                    Ok(()) => log::info!("Saved the flipbook to {name}"),
+                    Err(e) => log::error!("Failed to export the flipbook: {e}"),
+                }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            RenderRequest::LoadCellTexture(path) => {
+                let result = std::fs::read(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|bytes| self.set_cell_texture(&bytes));
+                if let Err(e) = result {
+                    self.game_state
+                        .lock()
+                        .unwrap()
+                        .show_toast(format!("Couldn't use {} for cells: {e}", path.display()));
+                }
+            }
+        }
+    }
+
+    /// Draw the scene with `count` samples per pixel, or the most under that
+    /// the adapter supports. Returns the number used.
+    pub fn set_sample_count(&mut self, count: u32) -> u32 {
+        let supported = self
+            .core
+            .sample_counts
+            .iter()
+            .copied()
+            .filter(|c| *c <= count)
+            .max()
+            .unwrap_or(1);
+        if supported != count {
+            log::warn!("{count} samples per pixel isn't supported, so using {supported}");
+        }
+        if supported != self.sample_count {
+            let device = &self.core.device;
+            self.sample_count = supported;
+            self.render_pipeline = self.pipelines.cells(device, supported);
+            self.bg_render_pipeline = self.pipelines.bg(device, supported);
+            self.dots.set_sample_count(device, supported);
+            self.msaa_view = create_msaa_view(
+                device,
+                self.core.config.format,
+                self.size.width,
+                self.size.height,
+                supported,
+            );
+        }
+        supported
+    }
+
+    /// Change how frames are presented, or to `Fifo`, which is always
+    /// supported, if `mode` isn't. Returns the mode used.
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        let mode = if self.core.present_modes.contains(&mode) {
+            mode
+        } else {
+            log::warn!("The present mode {mode:?} isn't supported, so using Fifo");
+            wgpu::PresentMode::Fifo
+        };
+        if mode != self.core.config.present_mode {
+            self.core.config.present_mode = mode;
+            self.reconfigure();
+        }
+        mode
+    }
+
+    /// Choose how the frame is cleared. With a color, the frame is cleared to
+    /// it and the textured background isn't drawn. Without one, the textured
+    /// background is used.
+    ///
+    /// A color that isn't fully opaque will switch the surface to an alpha
+    /// mode that keeps it, if there is one, so that transparent windows work.
+    pub fn set_clear(&mut self, color: Option<wgpu::Color>) {
+        let transparent = color.is_some_and(|c| c.a < 1.0);
+        let alpha_mode = self
+            .core
+            .alpha_modes
+            .iter()
+            .copied()
+            .find(|m| {
+                !transparent
+                    || matches!(
+                        m,
+                        wgpu::CompositeAlphaMode::PreMultiplied
+                            | wgpu::CompositeAlphaMode::PostMultiplied
+                    )
+            })
+            .unwrap_or(self.core.alpha_modes[0]);
+        if alpha_mode != self.core.config.alpha_mode {
+            self.core.config.alpha_mode = alpha_mode;
+            self.reconfigure();
+        }
+        self.clear_color = color.map(|c| {
+            if alpha_mode == wgpu::CompositeAlphaMode::PreMultiplied {
+                wgpu::Color {
+                    r: c.r * c.a,
+                    g: c.g * c.a,
+                    b: c.b * c.a,
+                    a: c.a,
+                }
+            } else {
+                c
+            }
+        });
+    }
+
+    /// Draw living cells with an image of one's own instead of `live.png`, in
+    /// any format the `image` crate can read. Its sides have to be powers of
+    /// two so that it scales down evenly.
+    pub fn set_cell_texture(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        let image = image::load_from_memory(bytes)?;
+        let (width, height) = (image.width(), image.height());
+        if !width.is_power_of_two() || !height.is_power_of_two() {
+            anyhow::bail!(
+                "The image is {width}\u{d7}{height}, but its sides have to be powers of two"
+            );
+        }
+        let max = self.core.device.limits().max_texture_dimension_2d;
+        if width > max || height > max {
+            anyhow::bail!(
+                "The image is {width}\u{d7}{height}, but can be at most {max}\u{d7}{max}"
+            );
+        }
+        let device = &self.core.device;
+        let texture =
+            texture::Texture::from_image(device, &self.core.queue, &image, Some("cell sprite"))?;
+        self.rsc.diffuse_bind_group = texture_bind_group(
+            device,
+            &self.rsc.texture_bind_group_layout,
+            &texture,
+            "diffuse_bind_group",
+        );
+        self.rsc.diffuse_texture = texture;
+        Ok(())
+    }
+
+    /// Tile an image of one's own behind the cells instead of the dead cell
+    /// texture, or with `None`, draw no texture at all, just a solid color.
+    /// A solid color from `set_clear` still takes precedence over either.
+    pub fn set_background(&mut self, image: Option<&image::DynamicImage>) -> anyhow::Result<()> {
+        let Some(image) = image else {
+            self.draw_background = false;
+            return Ok(());
+        };
+        let device = &self.core.device;
+        let texture =
+            texture::Texture::from_image(device, &self.core.queue, image, Some("background"))?;
+        self.rsc.bg_texture_bind_group = texture_bind_group(
+            device,
+            &self.rsc.texture_bind_group_layout,
+            &texture,
+            "bg_texture_bind_group",
+        );
+        self.rsc.bg_texture = texture;
+        self.draw_background = true;
+        Ok(())
+    }
 
     /// Render to the window.
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        self.measure_frame();
         let output = self.core.surface.get_current_texture()?;
         let view = output
             .texture
@@ -810,20 +1704,71 @@ impl<'a> RenderState<'a> {
                     label: Some("Render Encoder"),
                 });
 
-        // Create and complete the render pass for the background
-        {
+        self.draw_scene(&mut encoder, &view, self.msaa_view.as_ref());
+
+        // Render the GUI
+        let (encoder, egui_tdelta) =
+            self.egui
+                .render(&self.core.config, &self.core.queue, &view, encoder);
+
+        self.core.queue.submit(iter::once(encoder.finish()));
+
+        output.present();
+
+        self.egui.remove_textures(egui_tdelta);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(e) = self.record_frame() {
+            log::error!("Stopped recording: {e}");
+            self.recording = None;
+        }
+
+        Ok(())
+    }
+
+    /// Count a frame towards the average frame rate and pass it on to the GUI
+    fn measure_frame(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last_frame.replace(now) {
+            let seconds = now.duration_since(last).as_secs_f64();
+            if seconds > 0.0 {
+                let fps = seconds.recip();
+                self.frame_rate = if self.frame_rate == 0.0 {
+                    fps
+                } else {
+                    self.frame_rate + (fps - self.frame_rate) * FRAME_RATE_SMOOTHING
+                };
+            }
+        }
+        self.egui.set_frame_rate(self.frame_rate);
+    }
+
+    /// Record the passes that draw the background, cells and grid lines to a
+    /// view. This is everything but the GUI.
+    ///
+    /// With more than one sample per pixel, the background and cells are
+    /// drawn to `msaa`, a multisampled texture of the same size as `view`, and
+    /// resolved to `view` before the grid lines are drawn over them.
+    fn draw_scene(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        msaa: Option<&wgpu::TextureView>,
+    ) {
+        let (target, resolve_target) = match msaa {
+            Some(msaa) => (msaa, Some(view)),
+            None => (view, None),
+        };
+        // Create and complete the render pass for the background, unless it's
+        // being replaced by a solid color
+        if self.clear_color.is_none() && self.draw_background {
             let mut first_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("BG Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: target,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(BACKGROUND_COLOR),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
@@ -848,11 +1793,22 @@ impl<'a> RenderState<'a> {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view: target,
+                    resolve_target,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
+                        load: match self.clear_color {
+                            Some(color) => wgpu::LoadOp::Clear(color),
+                            None if !self.draw_background => {
+                                wgpu::LoadOp::Clear(BACKGROUND_COLOR)
+                            }
+                            None => wgpu::LoadOp::Load,
+                        },
+                        // Only what's resolved is needed afterwards
+                        store: if msaa.is_some() {
+                            wgpu::StoreOp::Discard
+                        } else {
+                            wgpu::StoreOp::Store
+                        },
                     },
                 })],
                 depth_stencil_attachment: None,
@@ -860,6 +1816,14 @@ impl<'a> RenderState<'a> {
                 timestamp_writes: None,
             });
 
+            self.dots.draw(
+                &mut render_pass,
+                &self.rsc.res_bind_group,
+                &self.rsc.offset_bind_group,
+                &self.rsc.vertex_buffer,
+                self.num_vertices,
+            );
+
             render_pass.set_pipeline(&self.render_pipeline);
             render_pass.set_bind_group(0, &self.rsc.res_bind_group, &[]);
             render_pass.set_bind_group(1, &self.rsc.radius_bind_group, &[]);
@@ -872,18 +1836,81 @@ impl<'a> RenderState<'a> {
 
             render_pass.draw(0..self.num_vertices, 0..self.cells.len() as _);
         }
+        self.grid.draw(
+            encoder,
+            view,
+            &self.rsc.res_bind_group,
+            &self.rsc.radius_bind_group,
+            &self.rsc.offset_bind_group,
+            &self.rsc.bg_vertex_buffer,
+        );
+    }
+}
 
-        // Render the GUI
-        let (encoder, egui_tdelta) =
-            self.egui
-                .render(&self.core.config, &self.core.queue, &view, encoder);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        self.core.queue.submit(iter::once(encoder.finish()));
+    #[test]
+    fn buffers_grow_with_room_to_spare() {
+        assert_eq!(resized_capacity(0, 1, 0), Some(1));
+        assert_eq!(resized_capacity(100, 101, 0), Some(151));
+        assert_eq!(resized_capacity(100, 1000, 100), Some(1500));
+    }
 
-        output.present();
+    #[test]
+    fn buffers_in_use_are_kept() {
+        assert_eq!(resized_capacity(100, 100, 0), None);
+        assert_eq!(resized_capacity(100, 25, 0), None);
+        assert_eq!(resized_capacity(0, 0, 0), None);
+    }
 
-        self.egui.remove_textures(egui_tdelta);
+    #[test]
+    fn mostly_empty_buffers_shrink() {
+        assert_eq!(resized_capacity(1000, 24, 0), Some(36));
+        assert_eq!(resized_capacity(1000, 0, 0), Some(0));
+        // Never below the smallest capacity
+        assert_eq!(resized_capacity(1000, 24, 512), Some(512));
+        assert_eq!(resized_capacity(512, 0, 512), None);
+    }
 
-        Ok(())
+    #[test]
+    fn shrinking_waits_for_the_population_to_stay_down() {
+        let min_capacity = 512;
+        let mut capacity = min_capacity;
+        let mut underused = 0;
+        // Grow the buffer the way writing instances does, then update once
+        let mut update = |capacity: &mut u64, used: u64| {
+            if used > *capacity {
+                *capacity = resized_capacity(*capacity, used, min_capacity).unwrap();
+            }
+            if let Some(new_size) = delayed_shrink(*capacity, used, min_capacity, &mut underused) {
+                *capacity = new_size;
+            }
+        };
+        for used in [1000, 10_000, 100_000] {
+            update(&mut capacity, used);
+        }
+        assert_eq!(capacity, 150_000);
+
+        // A dip shorter than the wait doesn't shrink it
+        for _ in 1..SHRINK_AFTER_UPDATES {
+            update(&mut capacity, 100);
+        }
+        assert_eq!(capacity, 150_000);
+        // Growing again starts the wait over
+        update(&mut capacity, 100_000);
+        for _ in 1..SHRINK_AFTER_UPDATES {
+            update(&mut capacity, 100);
+        }
+        assert_eq!(capacity, 150_000);
+
+        // Staying down for the whole wait does
+        update(&mut capacity, 100);
+        assert_eq!(capacity, min_capacity);
+        for _ in 0..2 * SHRINK_AFTER_UPDATES {
+            update(&mut capacity, 100);
+        }
+        assert_eq!(capacity, min_capacity);
     }
 }
@@ -1,6 +1,10 @@
 struct InstanceInput {
     @location(1) offset: vec2<f32>,
     @location(2) center: vec2<f32>,
+    // 0 for none, 1 for born, 2 for died, 3 for a ghost, 4 for heat, 5 for
+    // decaying, 6 for a wall, 7 for fading out
+    @location(5) highlight: u32,
+    @location(6) level: f32,
 }
 
 struct Res {
@@ -14,8 +18,11 @@ var<uniform> res: Res;
 
 struct Rad {
     data: f32,
-    padding: f32,
-    padding2: vec2<f32>,
+    // 1 if the cells are drawn in the solid color instead of their texture
+    solid: f32,
+    // How much of its square a cell is drawn over
+    fill: f32,
+    padding2: f32,
 }
 
 @group(1) @binding(0)
@@ -43,6 +50,8 @@ struct VertexOutput {
     @location(0) frag_coord: vec4<f32>,
     @location(4) circle_center: vec2<f32>,
     @location(3) tex_coords: vec2<f32>,
+    @location(5) @interpolate(flat) highlight: u32,
+    @location(6) @interpolate(flat) level: f32,
 };
 
 @vertex
@@ -66,6 +75,8 @@ fn vs_main(
     out.frag_coord = out.clip_position;
     out.circle_center = instance.center;
     out.tex_coords = model.tex_coords;
+    out.highlight = instance.highlight;
+    out.level = instance.level;
     return out;
 }
 
@@ -75,12 +86,55 @@ var t_diffuse: texture_2d<f32>;
 @group(3) @binding(1)
 var s_diffuse: sampler;
 
+// Cold to hot, going from blue through red to yellow
+fn heat_color(heat: f32) -> vec3<f32> {
+    let cold = vec3<f32>(0.1, 0.2, 0.9);
+    let warm = vec3<f32>(0.9, 0.1, 0.1);
+    let hot = vec3<f32>(1.0, 0.9, 0.2);
+    if heat < 0.5 {
+        return mix(cold, warm, heat * 2);
+    }
+    return mix(warm, hot, heat * 2 - 1);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     //let res = res.data;
-    let radius = radius.data;
+    let solid = radius.solid;
+    // The size the cell is drawn at, rather than the size of its square
+    let radius = radius.data * radius.fill;
     //let pan = pan.data;
     // At exremely far zooms, interpolate between the texture and a solid color
-    let factor = smoothstep(0.01, 0.02, radius);
-    return factor * textureSample(t_diffuse, s_diffuse, in.tex_coords) + (color * (1 - factor));
+    let factor = smoothstep(0.01, 0.02, radius) * (1 - solid);
+    let base = factor * textureSample(t_diffuse, s_diffuse, in.tex_coords) + (color * (1 - factor));
+    switch in.highlight {
+        case 1u: {
+            return vec4<f32>(mix(base.rgb, vec3<f32>(0.1, 0.9, 0.2), 0.6), base.a);
+        }
+        case 2u: {
+            return vec4<f32>(mix(base.rgb, vec3<f32>(0.9, 0.1, 0.1), 0.6), base.a * 0.6);
+        }
+        case 3u: {
+            return vec4<f32>(base.rgb, base.a * 0.4);
+        }
+        case 4u: {
+            // The sprite's shape, in the color of the heat
+            return vec4<f32>(heat_color(in.level), base.a);
+        }
+        case 5u: {
+            // Bluer and fainter the closer the cell is to dying
+            let faded = mix(base.rgb, vec3<f32>(0.2, 0.4, 1.0), 0.6);
+            return vec4<f32>(faded, base.a * (1 - 0.8 * in.level));
+        }
+        case 6u: {
+            // Grey and solid, so walls stand out from living cells
+            return vec4<f32>(vec3<f32>(0.45, 0.45, 0.5), max(base.a, 0.9));
+        }
+        case 7u: {
+            return vec4<f32>(base.rgb, base.a * (1 - in.level));
+        }
+        default: {
+            return base;
+        }
+    }
 }
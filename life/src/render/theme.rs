@@ -0,0 +1,68 @@
+//! Named sets of colors that go together, so the look can be changed all at
+//! once instead of one color at a time.
+
+use super::RenderSettings;
+
+/// Colors for the cells, the background and the grid lines
+pub struct Theme {
+    pub name: &'static str,
+    /// A solid color for the cells, or `None` to use their texture
+    pub cell_color: Option<[f32; 4]>,
+    /// A solid color for the background, or `None` to use its texture
+    pub background: Option<wgpu::Color>,
+    /// The color of the grid lines, if they're turned on. Themes without one
+    /// leave it as it is.
+    pub grid_color: Option<[f32; 3]>,
+}
+
+impl Theme {
+    /// Change the colors in `settings` to this theme's. Everything else is
+    /// left alone.
+    pub fn apply(&self, settings: &mut RenderSettings) {
+        settings.cell_color = self.cell_color;
+        settings.clear_color = self.background;
+        if let Some(color) = self.grid_color {
+            settings.grid.color = color;
+        }
+    }
+}
+
+/// The theme with this name, if there is one
+pub fn find(name: &str) -> Option<&'static Theme> {
+    THEMES.iter().find(|t| t.name == name)
+}
+
+/// Every theme, in the order they're listed and cycled through. The first is
+/// the one the game starts with.
+pub const THEMES: &[Theme] = &[
+    Theme {
+        name: "Sprites",
+        cell_color: None,
+        background: None,
+        grid_color: Some([1.0, 1.0, 1.0]),
+    },
+    Theme {
+        name: "Classic",
+        cell_color: Some([1.0, 1.0, 1.0, 1.0]),
+        background: Some(wgpu::Color::BLACK),
+        grid_color: Some([0.5, 0.5, 0.5]),
+    },
+    Theme {
+        name: "Matrix",
+        cell_color: Some([0.0, 1.0, 0.25, 1.0]),
+        background: Some(wgpu::Color::BLACK),
+        grid_color: Some([0.0, 0.4, 0.1]),
+    },
+    // Solarized dark, with the yellow accent for the cells
+    Theme {
+        name: "Solarized",
+        cell_color: Some([0.71, 0.54, 0.0, 1.0]), // #B58900
+        background: Some(wgpu::Color {
+            r: 0.0,
+            g: 0.169,
+            b: 0.212,
+            a: 1.0,
+        }), // #002B36
+        grid_color: Some([0.345, 0.431, 0.459]), // #586E75
+    },
+];
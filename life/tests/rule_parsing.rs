@@ -0,0 +1,31 @@
+//! Checks that rules typed in by the player are read or turned down without
+//! panicking.
+
+use life::Ruleset;
+
+#[test]
+fn ranged_rule() {
+    let rule: Ruleset = "R2,C0,M0,S6-9,B7-8,NM".parse().expect("The rule is valid");
+    assert!(rule.birth[7] && rule.birth[8] && !rule.birth[6]);
+    assert!((6..=9).all(|n| rule.survive[n]) && !rule.survive[10]);
+}
+
+#[test]
+fn reversed_range() {
+    assert!("R2,C0,M0,S9-6,B7-8,NM".parse::<Ruleset>().is_err());
+    assert!("R2,C0,M0,S6-9,B8-7,NM".parse::<Ruleset>().is_err());
+}
+
+#[test]
+fn range_past_the_neighborhood() {
+    // A range 1 Moore neighborhood only has 8 neighbors
+    assert!("R1,C0,M0,S2-9,B3,NM".parse::<Ruleset>().is_err());
+    // A range 2 von Neumann neighborhood only has 12
+    assert!("R2,C0,M0,S2-4,B3-13,NN".parse::<Ruleset>().is_err());
+}
+
+#[test]
+fn single_count_range() {
+    let rule: Ruleset = "R1,C0,M0,S3-3,B3,NM".parse().expect("The rule is valid");
+    assert!(rule.survive[3] && !rule.survive[2] && !rule.survive[4]);
+}
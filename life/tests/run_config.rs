@@ -0,0 +1,21 @@
+//! Checks that a `RunConfig` only starts the game at a zoom it can show.
+
+use life::RunConfig;
+
+#[test]
+fn unusable_grid_sizes_are_replaced() {
+    let default = RunConfig::default().grid_size;
+    for grid_size in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, 0.0, -5.0] {
+        let config = RunConfig::default().with_grid_size(grid_size);
+        assert_eq!(config.grid_size, default, "{grid_size}");
+    }
+}
+
+#[test]
+fn grid_sizes_are_clamped() {
+    let tiny = RunConfig::default().with_grid_size(0.01).grid_size;
+    let huge = RunConfig::default().with_grid_size(1e9).grid_size;
+    assert_eq!(tiny, 1.0);
+    assert_eq!(huge, 200.0);
+    assert_eq!(RunConfig::default().with_grid_size(25.0).grid_size, 25.0);
+}
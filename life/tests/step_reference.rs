@@ -0,0 +1,308 @@
+//! Checks that `Simulation::step` agrees with a slow but obviously correct
+//! version of it on random boards, so that the faster ways of stepping can be
+//! changed without quietly breaking the rules.
+
+use std::collections::HashSet;
+
+use life::{BoundaryMode, Neighborhood, Ruleset, Simulation};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use vec2::Vector2;
+
+/// How many random boards to try for each case
+const BOARDS: u64 = 20;
+/// How many generations to step each board
+const GENERATIONS: usize = 8;
+
+/// Compute the next generation by looking at every cell that could possibly
+/// be alive in it and counting its neighbors one by one. `area` is the corner
+/// and size of a region holding every living cell.
+fn reference_step(
+    living: &HashSet<Vector2<i32>>,
+    ruleset: &Ruleset,
+    boundary: BoundaryMode,
+    area: (Vector2<i32>, Vector2<i32>),
+) -> HashSet<Vector2<i32>> {
+    let range = ruleset.neighborhood.range() as i32;
+    let von_neumann = matches!(ruleset.neighborhood, Neighborhood::VonNeumann { .. });
+    // Cells can only be born within reach of a living one
+    let (min, max) = match boundary {
+        BoundaryMode::Infinite => (
+            area.0 - Vector2::new(range, range),
+            area.0 + area.1 + Vector2::new(range, range),
+        ),
+        BoundaryMode::Toroidal { width, height } => {
+            (Vector2::new(0, 0), Vector2::new(width, height))
+        }
+    };
+
+    let mut next = HashSet::new();
+    for y in min.y..max.y {
+        for x in min.x..max.x {
+            let mut count = 0;
+            for dy in -range..=range {
+                for dx in -range..=range {
+                    if (dx, dy) == (0, 0) || (von_neumann && dx.abs() + dy.abs() > range) {
+                        continue;
+                    }
+                    let neighbor = boundary.wrap(Vector2::new(x + dx, y + dy));
+                    if living.contains(&neighbor) {
+                        count += 1;
+                    }
+                }
+            }
+            let cell = Vector2::new(x, y);
+            let alive = if living.contains(&cell) {
+                ruleset.survive[count]
+            } else {
+                ruleset.birth[count]
+            };
+            if alive {
+                next.insert(cell);
+            }
+        }
+    }
+    next
+}
+
+/// Fill `area` with living cells at random, `density` of them on average
+fn random_board(
+    rng: &mut StdRng,
+    area: (Vector2<i32>, Vector2<i32>),
+    density: f64,
+) -> Vec<Vector2<i32>> {
+    let (corner, size) = area;
+    let mut cells = Vec::new();
+    for y in corner.y..corner.y + size.y {
+        for x in corner.x..corner.x + size.x {
+            if rng.gen_bool(density) {
+                cells.push(Vector2::new(x, y));
+            }
+        }
+    }
+    cells
+}
+
+/// Step random boards with both `Simulation` and `reference_step` and check
+/// that every generation comes out the same
+fn check(rule: &str, boundary: BoundaryMode, area: (Vector2<i32>, Vector2<i32>), density: f64) {
+    let ruleset: Ruleset = rule.parse().expect("The rule is valid");
+    let range = ruleset.neighborhood.range() as i32;
+    for seed in 0..BOARDS {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut sim = Simulation::new();
+        sim.set_ruleset(ruleset);
+        sim.set_boundary(boundary);
+        let mut expected = HashSet::new();
+        for cell in random_board(&mut rng, area, density) {
+            sim.toggle(cell);
+            expected.insert(cell);
+        }
+
+        // The living cells can spread by up to the range every generation
+        let mut area = area;
+        for generation in 1..=GENERATIONS {
+            expected = reference_step(&expected, &ruleset, boundary, area);
+            area = (
+                area.0 - Vector2::new(range, range),
+                area.1 + Vector2::new(2 * range, 2 * range),
+            );
+            sim.step();
+            let actual = sim.living_cells().collect::<HashSet<_>>();
+            assert!(
+                actual == expected,
+                "{rule} on {boundary} differs from the reference at generation {generation} of \
+                 seed {seed}: {} cells are missing and {} are extra",
+                expected.difference(&actual).count(),
+                actual.difference(&expected).count(),
+            );
+        }
+    }
+}
+
+/// A region straddling the origin, so that negative coordinates and the
+/// edges between chunks are both covered
+fn around_origin(size: i32) -> (Vector2<i32>, Vector2<i32>) {
+    (Vector2::new(-size / 2, -size / 2), Vector2::new(size, size))
+}
+
+#[test]
+fn sparse_conway() {
+    check("B3/S23", BoundaryMode::Infinite, around_origin(40), 0.05);
+}
+
+#[test]
+fn dense_conway() {
+    // Crowded enough to be stepped a chunk at a time
+    check("B3/S23", BoundaryMode::Infinite, around_origin(96), 0.5);
+}
+
+#[test]
+fn dense_highlife() {
+    check("B36/S23", BoundaryMode::Infinite, around_origin(96), 0.4);
+}
+
+#[test]
+fn survival_without_neighbors() {
+    check(
+        "B3/S012345678",
+        BoundaryMode::Infinite,
+        around_origin(24),
+        0.1,
+    );
+}
+
+#[test]
+fn toroidal_conway() {
+    let boundary = BoundaryMode::Toroidal {
+        width: 23,
+        height: 17,
+    };
+    check(
+        "B3/S23",
+        boundary,
+        (Vector2::new(0, 0), Vector2::new(23, 17)),
+        0.35,
+    );
+}
+
+#[test]
+fn larger_than_life() {
+    check(
+        "R2,C0,M0,S6-9,B7-8,NM",
+        BoundaryMode::Infinite,
+        around_origin(24),
+        0.3,
+    );
+}
+
+#[test]
+fn von_neumann() {
+    check(
+        "R2,C0,M0,S2-4,B3,NN",
+        BoundaryMode::Infinite,
+        around_origin(24),
+        0.2,
+    );
+}
+
+#[test]
+fn toroidal_larger_than_life() {
+    let boundary = BoundaryMode::Toroidal {
+        width: 12,
+        height: 9,
+    };
+    check(
+        "R3,C0,M0,S9-16,B12-14,NM",
+        boundary,
+        (Vector2::new(0, 0), Vector2::new(12, 9)),
+        0.4,
+    );
+}
+
+#[test]
+fn smallest_torus() {
+    let boundary = BoundaryMode::Toroidal {
+        width: BoundaryMode::min_torus_size(1),
+        height: 4,
+    };
+    check(
+        "B3/S23",
+        boundary,
+        (
+            Vector2::new(0, 0),
+            Vector2::new(BoundaryMode::min_torus_size(1), 4),
+        ),
+        0.4,
+    );
+}
+
+/// `reference_step` counts a cell once for every offset that wraps onto it,
+/// so it can't tell whether a board is big enough for its neighborhood. On
+/// the smallest torus for a range, every neighbor has to be a different cell.
+#[test]
+fn smallest_torus_has_distinct_neighbors() {
+    for range in 2..=life::MAX_RANGE {
+        for neighborhood in [
+            Neighborhood::Moore { range },
+            Neighborhood::VonNeumann { range },
+        ] {
+            let size = BoundaryMode::min_torus_size(range);
+            let boundary = BoundaryMode::Toroidal {
+                width: size,
+                height: size,
+            };
+            for y in 0..size {
+                for x in 0..size {
+                    let cell = Vector2::new(x, y);
+                    let neighbors: HashSet<_> = neighborhood
+                        .offsets()
+                        .into_iter()
+                        .map(|o| boundary.wrap(cell + o))
+                        .collect();
+                    assert_eq!(neighbors.len(), neighborhood.size(), "{neighborhood:?}");
+                    assert!(!neighbors.contains(&cell), "{neighborhood:?}");
+                }
+            }
+        }
+    }
+    check(
+        "R2,C0,M0,S6-9,B7-8,NM",
+        BoundaryMode::Toroidal {
+            width: BoundaryMode::min_torus_size(2),
+            height: BoundaryMode::min_torus_size(2),
+        },
+        (Vector2::new(0, 0), Vector2::new(5, 5)),
+        0.4,
+    );
+}
+
+#[test]
+fn too_small_torus() {
+    let mut sim = Simulation::new();
+    sim.set_boundary(BoundaryMode::Toroidal {
+        width: 0,
+        height: 1,
+    });
+    assert_eq!(
+        sim.boundary(),
+        BoundaryMode::Toroidal {
+            width: BoundaryMode::min_torus_size(1),
+            height: BoundaryMode::min_torus_size(1),
+        }
+    );
+    sim.toggle(Vector2::new(5, -1));
+    sim.step();
+
+    // A rule that reaches further grows the board to fit it
+    let ruleset = "R3,C0,M0,S9-16,B12-14,NM"
+        .parse()
+        .expect("The rule is valid");
+    sim.set_ruleset(ruleset);
+    assert_eq!(
+        sim.boundary(),
+        BoundaryMode::Toroidal {
+            width: BoundaryMode::min_torus_size(3),
+            height: BoundaryMode::min_torus_size(3),
+        }
+    );
+    sim.step();
+}
+
+/// A lone cell far from a crowded board survives only if the rule has S0, even
+/// when the rest of the board is stepped by chunks.
+#[test]
+fn lone_cell_on_crowded_board_under_s0() {
+    let lone = Vector2::new(1000, -1000);
+    for (rule, survives) in [("B3/S023", true), ("B3/S23", false)] {
+        let mut sim = Simulation::new();
+        sim.set_ruleset(rule.parse().expect("The rule is valid"));
+        // Enough cells far from the lone one to be stepped by chunks
+        let mut rng = StdRng::seed_from_u64(0);
+        for cell in random_board(&mut rng, around_origin(64), 0.5) {
+            sim.toggle(cell);
+        }
+        sim.toggle(lone);
+        sim.step();
+        assert_eq!(sim.is_alive(lone), survives, "{rule}");
+    }
+}